@@ -18,6 +18,35 @@ pub fn is_binary(file_path: String) -> Result<bool> {
     Ok(from_utf8(&buffer).map_or(true, |_file_content| false))
 }
 
+/// Format of a file's content, as far as it can be told from the first bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentFormat {
+    Text,
+    Dlt,
+    PcapLegacy,
+    PcapNG,
+    Binary,
+}
+
+/// Detects the format from the starting chunk of a file
+pub fn detect_format(chunk: &[u8]) -> ContentFormat {
+    match chunk {
+        // storage header of DLT files
+        [b'D', b'L', b'T', 0x01, ..] => ContentFormat::Dlt,
+        [0x0a, 0x0d, 0x0d, 0x0a, ..] => ContentFormat::PcapNG,
+        // micro- and nanosecond captures in both byte orders
+        [0xa1, 0xb2, 0xc3, 0xd4, ..]
+        | [0xd4, 0xc3, 0xb2, 0xa1, ..]
+        | [0xa1, 0xb2, 0x3c, 0x4d, ..]
+        | [0x4d, 0x3c, 0xb2, 0xa1, ..] => ContentFormat::PcapLegacy,
+        // the chunk might end within a multi-byte character
+        _ => match from_utf8(chunk) {
+            Err(err) if err.error_len().is_some() => ContentFormat::Binary,
+            _ => ContentFormat::Text,
+        },
+    }
+}
+
 fn fetch_starting_chunk(file_path: &Path) -> Result<Vec<u8>> {
     let bytes_to_read: u64 = (metadata(file_path)?.len().max(1) - 1).min(BYTES_TO_READ);
 
@@ -85,6 +114,28 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_detect_format() -> Result<()> {
+        for (file, format) in [
+            ("attachments.dlt", ContentFormat::Dlt),
+            ("someip.pcap", ContentFormat::PcapLegacy),
+            ("someip.pcapng", ContentFormat::PcapNG),
+            ("chinese_poem.txt", ContentFormat::Text),
+            ("empty.txt", ContentFormat::Text),
+        ] {
+            let chunk =
+                fetch_starting_chunk(&Path::new("../../../../developing/resources").join(file))?;
+            assert_eq!(detect_format(&chunk), format, "{file}");
+        }
+        // cut within a character
+        assert_eq!(
+            detect_format(&"poem 詩".as_bytes()[..7]),
+            ContentFormat::Text
+        );
+        assert_eq!(detect_format(&[0x00, 0xff, 0x10]), ContentFormat::Binary);
+        Ok(())
+    }
+
     #[test]
     fn test_is_binary_when_wrong_file_path_is_given() -> Result<()> {
         assert!(is_binary(String::from("../../developing/resources/empty.text")).is_err());
@@ -5,7 +5,7 @@ use processor::grabber::LineRange;
 use rustyline::{error::ReadlineError, DefaultEditor};
 use session::session::Session;
 use sources::{
-    factory::{DltParserSettings, FileFormat, ObserveOptions, ParserType},
+    factory::{DltParserSettings, FileFormat, ObserveOptions, ParserType, TextParserSettings},
     producer::MessageProducer,
     socket::udp::UdpSource,
 };
@@ -88,7 +88,7 @@ pub(crate) async fn handle_interactive_session(input: Option<PathBuf>) {
                         start = Instant::now();
                         let uuid = Uuid::new_v4();
                         let file_path = input.clone().expect("input must be present");
-                        session.observe(uuid, ObserveOptions::file(file_path.clone(),FileFormat::Text, ParserType::Text)).expect("observe failed");
+                        session.observe(uuid, ObserveOptions::file(file_path.clone(),FileFormat::Text, ParserType::Text(TextParserSettings::default()))).expect("observe failed");
                     }
                     Some(Command::Dlt) => {
                         println!("dlt command received");
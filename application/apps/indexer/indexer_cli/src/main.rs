@@ -13,7 +13,7 @@
 extern crate chrono;
 extern crate dirs;
 extern crate indexer_base;
-// extern crate merging;
+extern crate merging;
 extern crate processor;
 
 #[macro_use]
@@ -34,13 +34,22 @@ use env_logger::Env;
 use futures::{pin_mut, stream::StreamExt};
 use indexer_base::config::*;
 use indicatif::{ProgressBar, ProgressStyle};
+use merging::merger::{combined_file_size, read_merge_options, MergeError, Merger};
 use parsers::{
     dlt::{attachment::FileExtractor, DltParser, DltRangeParser},
     someip::SomeipParser,
     text::StringTokenizer,
     LogMessage, MessageStreamItem, ParseYield,
 };
-use processor::{export::export_raw, grabber::GrabError, text_source::TextFileSource};
+use processor::{
+    export::export_raw,
+    grabber::GrabError,
+    parse::{
+        detect_timestamp_formats, detect_timestamp_formats_in_file, extract_posix_timestamp,
+        lookup_regex_for_format_str, FormatCandidate,
+    },
+    text_source::TextFileSource,
+};
 use serde::{Deserialize, Serialize};
 use sources::{
     binary::{pcap::ng::PcapngByteSource, raw::BinaryByteSource},
@@ -48,12 +57,15 @@ use sources::{
 };
 use std::{
     fs::File,
-    io::BufReader,
+    io::{BufRead, BufReader, BufWriter, Write},
     path::{Path, PathBuf},
 };
 use structopt::StructOpt;
 use tokio_util::sync::CancellationToken;
 
+/// Amount of lines used to detect the timestamp format of a file
+const DISCOVER_SAMPLE_LINES: usize = 1000;
+
 lazy_static! {
     static ref EXAMPLE_FIBEX: std::path::PathBuf =
         std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -145,7 +157,6 @@ enum Chip {
             name = "FORMAT_STR",
             help = "format string to use"
         )]
-        #[allow(dead_code)]
         format_string: Option<String>,
         #[structopt(
             short = "t",
@@ -153,7 +164,6 @@ enum Chip {
             name = "SAMPLE",
             help = "test string to use"
         )]
-        #[allow(dead_code)]
         test_string: Option<String>,
         #[structopt(
             short = "c",
@@ -161,10 +171,8 @@ enum Chip {
             name = "CONFIG",
             help = "test a file using this configuration"
         )]
-        #[allow(dead_code)]
         test_config: Option<PathBuf>,
         #[structopt(short, long, help = "display duration info")]
-        #[allow(dead_code)]
         stdout: bool,
     },
     #[structopt(about = "handling dlt input")]
@@ -278,7 +286,6 @@ enum Chip {
             help = "string to extract date from",
             name = "INPUT"
         )]
-        #[allow(dead_code)]
         input_string: Option<String>,
         #[structopt(
             short,
@@ -286,14 +293,12 @@ enum Chip {
             name = "CONFIG",
             help = "file that contains a list of files to analyze"
         )]
-        #[allow(dead_code)]
         config_file: Option<String>,
         #[structopt(
             short = "f",
             long = "file",
             help = "file where the timeformat should be detected"
         )]
-        #[allow(dead_code)]
         input_file: Option<PathBuf>,
     },
     #[structopt(about = "test date discovery, either from a string or from a file")]
@@ -350,13 +355,6 @@ enum Chip {
         merge_config: PathBuf,
         #[structopt(long = "out", name = "OUT", help = "Output file")]
         output: PathBuf,
-        #[structopt(
-            short,
-            long,
-            default_value = "500",
-            help = "How many lines should be in a chunk (used for access later)"
-        )]
-        chunk_size: usize,
         #[structopt(short, long, help = "append to file if exists")]
         append: bool,
     },
@@ -374,11 +372,11 @@ pub async fn main() -> Result<()> {
     let opt = Chip::from_args();
     match opt {
         Chip::Format {
-            format_string: _,
-            test_string: _,
-            test_config: _,
-            stdout: _,
-        } => println!("handle_format_subcommand not currently implemented"),
+            format_string,
+            test_string,
+            test_config,
+            stdout,
+        } => handle_format_subcommand(format_string, test_string, test_config, stdout, start),
         Chip::Grab {
             input,
             start_pos,
@@ -466,10 +464,10 @@ pub async fn main() -> Result<()> {
         } => handle_dlt_stats_subcommand(&input, count, legacy, start, stdout).await,
         Chip::Detect { input } => handle_detect_file_type_subcommand(&input).await,
         Chip::Discover {
-            input_string: _,
-            config_file: _,
-            input_file: _,
-        } => println!("handle_discover_subcommand currently not implemented"),
+            input_string,
+            config_file,
+            input_file,
+        } => handle_discover_subcommand(input_string, config_file, input_file),
         Chip::Export {
             file,
             legacy,
@@ -488,9 +486,8 @@ pub async fn main() -> Result<()> {
         Chip::Merge {
             merge_config,
             output,
-            chunk_size,
             append,
-        } => handle_merge_subcommand(merge_config, append, output, chunk_size).await,
+        } => handle_merge_subcommand(merge_config, append, output, start).await,
         Chip::Session { input } => handle_interactive_session(input).await,
     }
 
@@ -702,67 +699,194 @@ pub async fn main() -> Result<()> {
     }
 
     async fn handle_merge_subcommand(
-        _merge_conf_path: PathBuf,
-        _append: bool,
-        _out_path: PathBuf,
-        _chunk_size: usize,
+        merge_conf_path: PathBuf,
+        append: bool,
+        out_path: PathBuf,
+        start: std::time::Instant,
     ) {
-        unimplemented!("needs implmementation using stream architecture");
-        // debug!("handle_merge_subcommand");
-        // // let concat_conf_path_string_res: clap::Result<String> = matches.value_of_t("concat_config");
-        // let (tx, rx): (cc::Sender<ChunkResults>, cc::Receiver<ChunkResults>) = unbounded();
+        debug!("handle_merge_subcommand");
+        let options = match File::open(&merge_conf_path)
+            .map_err(MergeError::from)
+            .and_then(|mut f| read_merge_options(&mut f, merge_conf_path.parent()))
+        {
+            Ok(options) => options,
+            Err(why) => {
+                eprintln!("couldn't read merge config: {why}");
+                std::process::exit(2)
+            }
+        };
+        let total = match combined_file_size(&options) {
+            Ok(total) => total,
+            Err(why) => {
+                eprintln!("couldn't process: {why}");
+                std::process::exit(2)
+            }
+        };
+        let mut merger = match Merger::new(options) {
+            Ok(merger) => merger,
+            Err(why) => {
+                eprintln!("couldn't process: {why}");
+                std::process::exit(2)
+            }
+        };
+        let out_file = match fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(&out_path)
+        {
+            Ok(file) => file,
+            Err(why) => {
+                eprintln!("couldn't open {out_path:?}: {why}");
+                std::process::exit(2)
+            }
+        };
+        let mut writer = BufWriter::new(out_file);
+        let progress_bar = initialize_progress_bar(total);
+        let mut lines: usize = 0;
+        while let Some((_, line)) = merger.next() {
+            if let Err(why) = writeln!(writer, "{}", line.content) {
+                eprintln!("couldn't write to {out_path:?}: {why}");
+                std::process::exit(2)
+            }
+            progress_bar.set_position(merger.processed_bytes());
+            lines += 1;
+        }
+        if let Err(why) = writer.flush() {
+            eprintln!("couldn't write to {out_path:?}: {why}");
+            std::process::exit(2)
+        }
+        progress_bar.finish_and_clear();
+        if merger.lines_with_year_missing() > 0 {
+            eprintln!(
+                "year was missing for {} lines",
+                merger.lines_with_year_missing()
+            );
+        }
+        duration_report(start, format!("merging {lines} lines"));
+    }
 
-        // let progress_bar = initialize_progress_bar(100_u64);
-        // thread::spawn(move || {
-        //     if let Err(why) = merge_files_use_config_file(
-        //         &merge_conf_path,
-        //         &out_path,
-        //         append,
-        //         chunk_size,
-        //         tx,
-        //         None,
-        //     ) {
-        //         report_error(format!("couldn't process: {why}"));
-        //         std::process::exit(2)
-        //     }
-        // });
-        // let mut chunks: Vec<Chunk> = vec![];
-        // loop {
-        //     match rx.recv() {
-        //         Err(why) => {
-        //             report_error(format!("couldn't process: {why}"));
-        //             std::process::exit(2)
-        //         }
-        //         Ok(Ok(IndexingProgress::Finished { .. })) => {
-        //             println!("received finish event");
-        //             progress_bar.finish_and_clear();
-        //             break;
-        //         }
-        //         Ok(Ok(IndexingProgress::Progress { ticks })) => {
-        //             let progress_fraction = ticks.0 as f64 / ticks.1 as f64;
-        //             let pos = (progress_fraction * 100f64) as u64;
-        //             progress_bar.set_position(pos);
-        //         }
-        //         Ok(Ok(IndexingProgress::GotItem { item: chunk })) => {
-        //             chunks.push(chunk);
-        //         }
-        //         Ok(Err(Notification {
-        //             severity,
-        //             content,
-        //             line,
-        //         })) => {
-        //             if severity == Severity::WARNING {
-        //                 report_warning_ln(content, line);
-        //             } else {
-        //                 report_error_ln(content, line);
-        //             }
-        //         }
-        //         Ok(_) => report_warning("process finished without result"),
-        //     }
-        // }
+    fn handle_format_subcommand(
+        format_string: Option<String>,
+        test_string: Option<String>,
+        test_config: Option<PathBuf>,
+        status_updates: bool,
+        start: std::time::Instant,
+    ) {
+        debug!("handle_format_subcommand");
+        if let Some(config_path) = test_config {
+            let options = match fs::File::open(&config_path)
+                .map_err(|e| e.to_string())
+                .and_then(|mut f| read_format_string_options(&mut f))
+            {
+                Ok(options) => options,
+                Err(why) => {
+                    eprintln!("couldn't read format config: {why}");
+                    std::process::exit(2)
+                }
+            };
+            let regex = match lookup_regex_for_format_str(&options.format) {
+                Ok(regex) => regex,
+                Err(why) => {
+                    eprintln!("invalid format string: {why}");
+                    std::process::exit(2)
+                }
+            };
+            let file = match fs::File::open(&options.file) {
+                Ok(file) => file,
+                Err(why) => {
+                    eprintln!("couldn't open {}: {why}", options.file);
+                    std::process::exit(2)
+                }
+            };
+            let (mut tested, mut matched) = (0usize, 0usize);
+            for line in BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .take(options.lines_to_test.max(0) as usize)
+            {
+                tested += 1;
+                if extract_posix_timestamp(&line, &regex, None, None).is_ok() {
+                    matched += 1;
+                } else {
+                    println!("no timestamp in line {tested}: {line}");
+                }
+            }
+            println!("{matched} of {tested} lines matched \"{}\"", options.format);
+        } else if let (Some(format), Some(test)) = (format_string, test_string) {
+            match lookup_regex_for_format_str(&format) {
+                Ok(regex) => {
+                    println!("regex: {}", regex.as_str());
+                    match extract_posix_timestamp(&test, &regex, None, None) {
+                        Ok((timestamp, year_was_missing)) => {
+                            println!("timestamp: {timestamp}");
+                            if year_was_missing {
+                                println!("(year was missing, current year was used)");
+                            }
+                        }
+                        Err(why) => println!("no match: {why}"),
+                    }
+                }
+                Err(why) => {
+                    eprintln!("invalid format string: {why}");
+                    std::process::exit(2)
+                }
+            }
+        } else {
+            eprintln!("either a format string with a test string or a config is needed");
+            std::process::exit(2)
+        }
+        if status_updates {
+            duration_report(start, "testing format".to_string());
+        }
+    }
 
-        // println!("done with handle_merge_subcommand");
-        // std::process::exit(0)
+    fn handle_discover_subcommand(
+        input_string: Option<String>,
+        config_file: Option<String>,
+        input_file: Option<PathBuf>,
+    ) {
+        debug!("handle_discover_subcommand");
+        let to_json = |candidates: &[FormatCandidate]| match serde_json::to_string(candidates) {
+            Ok(json) => json,
+            Err(why) => {
+                eprintln!("serializing result failed: {why}");
+                std::process::exit(2)
+            }
+        };
+        let detect_in_file =
+            |path: &Path| match detect_timestamp_formats_in_file(path, DISCOVER_SAMPLE_LINES, None)
+            {
+                Ok(candidates) => candidates.unwrap_or_default(),
+                Err(why) => {
+                    eprintln!("couldn't process {path:?}: {why}");
+                    std::process::exit(2)
+                }
+            };
+        if let Some(input) = input_string {
+            println!("{}", to_json(&detect_timestamp_formats(&[input.as_str()])));
+        } else if let Some(path) = input_file {
+            println!("{}", to_json(&detect_in_file(&path)));
+        } else if let Some(config) = config_file {
+            let files: Vec<String> = match fs::read_to_string(&config)
+                .map_err(|e| e.to_string())
+                .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+            {
+                Ok(files) => files,
+                Err(why) => {
+                    eprintln!("couldn't read config {config}: {why}");
+                    std::process::exit(2)
+                }
+            };
+            for file in files {
+                let candidates = detect_in_file(Path::new(&file));
+                println!("{file}: {}", to_json(&candidates));
+            }
+        } else {
+            eprintln!("either an input string, a file or a config is needed");
+            std::process::exit(2)
+        }
     }
 
     fn to_pair(input: &str) -> Result<IndexSection> {
@@ -1353,7 +1477,7 @@ fn _load_test_fibex() -> FibexConfig {
     }
 }
 
-fn _initialize_progress_bar(len: u64) -> ProgressBar {
+fn initialize_progress_bar(len: u64) -> ProgressBar {
     let progress_bar = ProgressBar::new(len);
     progress_bar.set_style(ProgressStyle::default_bar()
                 .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
@@ -1523,7 +1647,7 @@ async fn detect_messages_type(input: &Path) -> Result<bool, DltParseError> {
         }
         {
             println!("try text parser");
-            let txt_parser = StringTokenizer {};
+            let txt_parser = StringTokenizer::default();
             let buf_reader = BufReader::new(fs::File::open(input)?);
             let source = BinaryByteSource::new(buf_reader);
             let mut txt_msg_producer = MessageProducer::new(txt_parser, source, None);
@@ -14,3 +14,6 @@
 extern crate log;
 
 pub mod merger;
+
+#[cfg(test)]
+mod tests;
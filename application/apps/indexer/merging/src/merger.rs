@@ -9,20 +9,19 @@
 // Dissemination of this information or reproduction of this material
 // is strictly forbidden unless prior written permission is obtained
 // from E.S.R.Labs.
-// use crossbeam_channel as cc;
-// use indexer_base::{
-//     error_reporter::*,
-//     progress::{IndexingProgress, ProgressReporter},
-//     timedline::*,
-//     utils,
-// };
-// use processor::parse::{line_to_timed_line, lookup_regex_for_format_str, DateParseError};
-// use regex::Regex;
+use indexer_base::{timedline::TimedLine, utils};
+use log::{trace, warn};
+use processor::parse::{line_to_timed_line, lookup_regex_for_format_str, DateParseError};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    fs::{self, File},
+    io::{BufRead, BufReader, Read},
+    iter::Peekable,
+    path::{Path, PathBuf},
+};
 use thiserror::Error;
-// use tokio_stream::Stream;
-// use tokio_util::sync::CancellationToken;
 
 #[derive(Debug, Error)]
 pub enum MergeError {
@@ -32,6 +31,8 @@ pub enum MergeError {
     IoProblem(#[from] std::io::Error),
     #[error("JSON possible: {0:?}")]
     JsonProblem(#[from] serde_json::Error),
+    #[error("Date format problem: {0}")]
+    DateFormat(#[from] DateParseError),
     #[error("Merge problem: {0}")]
     GeneralMergingProblem(String),
 }
@@ -50,8 +51,9 @@ pub struct FileMergeOptions {
     pub format: String,
 }
 
-/*
-
+/// Reads the merge configuration (a json array of `FileMergeOptions`).
+/// If `relative_path` is provided, all paths of the configuration are
+/// interpreted relative to it.
 pub fn read_merge_options(
     f: &mut File,
     relative_path: Option<impl AsRef<Path>>,
@@ -72,41 +74,9 @@ pub fn read_merge_options(
     }
 }
 
-#[allow(dead_code)]
-pub struct FileLogEntryProducer {
-    timed_line_iterator: TimedLineIter,
-}
-#[allow(dead_code)]
-impl FileLogEntryProducer {
-    pub(crate) fn new(
-        read_from: File,
-        tag: String,
-        regex: Regex,
-        year: Option<i32>,
-        time_offset: Option<i64>,
-        current_line_nr: usize,
-    ) -> FileLogEntryProducer {
-        let iter = TimedLineIter::new(read_from, tag, regex, year, time_offset, current_line_nr);
-        FileLogEntryProducer {
-            timed_line_iterator: iter,
-        }
-    }
-}
-
-impl Stream for FileLogEntryProducer {
-    type Item = Result<Option<TimedLine>, MergeError>;
-    fn poll_next(
-        mut self: std::pin::Pin<&mut Self>,
-        _cx: &mut std::task::Context,
-    ) -> core::task::Poll<Option<Self::Item>> {
-        let next = self.timed_line_iterator.next();
-        match next {
-            Some(msg) => core::task::Poll::Ready(Some(Ok(Some(msg)))),
-            None => core::task::Poll::Ready(None),
-        }
-    }
-}
-
+/// Iterates over the lines of a file and extracts the timestamp of each line.
+/// Lines without a timestamp get the timestamp of the line before (or 0 if there
+/// was none yet), so they stay together with the entry they belong to.
 pub struct TimedLineIter {
     reader: BufReader<File>,
     tag: String,
@@ -115,8 +85,8 @@ pub struct TimedLineIter {
     time_offset: Option<i64>,
     last_timestamp: i64,
     pub current_line_nr: usize,
-    reporter: Reporter,
 }
+
 impl TimedLineIter {
     pub fn new(
         read_from: File,
@@ -135,22 +105,18 @@ impl TimedLineIter {
             time_offset,
             last_timestamp: 0,
             current_line_nr,
-            reporter: Default::default(),
         }
     }
 }
+
 impl Iterator for TimedLineIter {
     type Item = TimedLine;
     fn next(&mut self) -> Option<TimedLine> {
         let mut buf = vec![];
         match self.reader.read_until(b'\n', &mut buf) {
-            Ok(len) => {
-                if len == 0 {
-                    self.reporter.flush();
-                    return None;
-                }
-                let original_line_length = len;
-                let s = unsafe { std::str::from_utf8_unchecked(&buf) };
+            Ok(0) => None,
+            Ok(original_line_length) => {
+                let s = String::from_utf8_lossy(&buf);
                 let trimmed_line = s.trim_matches(utils::is_newline);
                 let timed_line = line_to_timed_line(
                     trimmed_line,
@@ -160,7 +126,6 @@ impl Iterator for TimedLineIter {
                     self.year,
                     self.time_offset,
                     self.current_line_nr,
-                    &mut self.reporter,
                 )
                 .unwrap_or_else(|_| TimedLine {
                     content: trimmed_line.to_string(),
@@ -182,81 +147,79 @@ impl Iterator for TimedLineIter {
     }
 }
 
-/// Merge files.
+/// Merges the lines of several files so that the result is in the correct timely order.
 ///
-/// merge all files so that the resulting file contains the log entries in the correct timely order
-/// this function takes a list of `FileMergeOptions` which contains the file path together with
-/// the date format that was detected or specified for the file. Each enty also can contain an offest in ms
-/// that will be added/subtracted from each detected timestamp.
-///
-/// As a result, updates of the progress are reported through the `update_channel`
-/// when finished, the resulting output file is stored in `out_path`
-///
-pub fn merge_files_use_config(
-    options: Vec<FileMergeOptions>,
-    out_path: &Path,
-    append: bool,
-    chunk_size: usize, // used for mapping line numbers to byte positions
-    update_channel: cc::Sender<ChunkResults>,
-    shutdown_token: Option<CancellationToken>,
-) -> Result<(), MergeError> {
-    trace!("merge {} files", options.len());
-    do_the_merge(
-        append,
-        out_path,
-        chunk_size,
-        options,
-        update_channel,
-        shutdown_token,
-    )
+/// Each file is described by `FileMergeOptions` which contains the format of the
+/// timestamps together with an optional year and offset (in ms).
+/// The merger yields the index of the input a line belongs to together with the line itself.
+/// If lines of different inputs have the same timestamp, the input listed first wins.
+/// Empty lines are skipped.
+pub struct Merger {
+    inputs: Vec<Peekable<TimedLineIter>>,
+    processed_bytes: u64,
+    lines_with_year_missing: usize,
 }
 
-/// Merge files using a config file
-///
-/// Convenience function that enables specifing a file that contains the merge-configuration
-/// important: when using this version of the merge function, the relative path to the config
-/// file is considered and prependend to the file paths in the config file
-///
-pub fn merge_files_use_config_file(
-    config_path: &Path,
-    out_path: &Path,
-    append: bool,
-    chunk_size: usize, // used for mapping line numbers to byte positions
-    update_channel: cc::Sender<ChunkResults>,
-    shutdown_token: Option<CancellationToken>,
-) -> Result<(), MergeError> {
-    trace!("merge files using config from {}", config_path.display());
-    let mut merge_option_file = File::open(config_path)?;
-    let options: Vec<FileMergeOptions> =
-        read_merge_options(&mut merge_option_file, config_path.parent())?;
-    trace!("with options...");
-    do_the_merge(
-        append,
-        out_path,
-        chunk_size,
-        options,
-        update_channel,
-        shutdown_token,
-    )
+impl Merger {
+    pub fn new(options: Vec<FileMergeOptions>) -> Result<Self, MergeError> {
+        trace!("create Merger ({} files)", options.len());
+        let inputs = options
+            .into_iter()
+            .map(|input| -> Result<Peekable<TimedLineIter>, MergeError> {
+                let regex = lookup_regex_for_format_str(&input.format)?;
+                let file = File::open(&input.path).map_err(|e| {
+                    MergeError::WrongConfiguration(format!("cannot open {}: {e}", input.path))
+                })?;
+                Ok(
+                    TimedLineIter::new(file, input.tag, regex, input.year, input.offset, 0)
+                        .peekable(),
+                )
+            })
+            .collect::<Result<Vec<_>, MergeError>>()?;
+        Ok(Merger {
+            inputs,
+            processed_bytes: 0,
+            lines_with_year_missing: 0,
+        })
+    }
+
+    /// Amount of bytes of all inputs consumed so far
+    pub fn processed_bytes(&self) -> u64 {
+        self.processed_bytes
+    }
+
+    /// Number of merged lines for which the year had to be guessed
+    pub fn lines_with_year_missing(&self) -> usize {
+        self.lines_with_year_missing
+    }
 }
 
-fn do_the_merge(
-    append: bool,
-    out_path: &Path,
-    chunk_size: usize,
-    merger_inputs: Vec<FileMergeOptions>,
-    update_channel: cc::Sender<ChunkResults>,
-    shutdown_token: Option<CancellationToken>,
-) -> Result<(), MergeError> {
-    let mut writer = IndexOutput::new(
-        append,
-        out_path,
-        chunk_size,
-        combined_file_size(&merger_inputs)?,
-        update_channel.clone(),
-    )?;
-    trace!("calling merge_inputs_with_writer");
-    merge_inputs_with_writer(&mut writer, merger_inputs, update_channel, shutdown_token)
+impl Iterator for Merger {
+    type Item = (usize, TimedLine);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // keep track of the min timestamp together with the index of the input it belongs to
+            let mut minimum: Option<(i64, usize)> = None;
+            for (i, input) in self.inputs.iter_mut().enumerate() {
+                if let Some(entry) = input.peek() {
+                    if minimum.is_none_or(|(t_min, _)| entry.timestamp < t_min) {
+                        minimum = Some((entry.timestamp, i));
+                    }
+                }
+            }
+            let (_, index) = minimum?;
+            let line = self.inputs[index].next()?;
+            // important: keep track of how many bytes we processed
+            self.processed_bytes += line.original_length as u64;
+            if line.content.is_empty() {
+                continue;
+            }
+            if line.year_was_missing {
+                self.lines_with_year_missing += 1;
+            }
+            return Some((index, line));
+        }
+    }
 }
 
 pub trait Len {
@@ -282,134 +245,7 @@ impl Len for PathBuf {
     }
 }
 
-pub trait TimedLogEntry: PartialOrd {
-    fn get_timestamp() -> Option<i64>;
-    fn year_missing() -> bool;
-}
-
-// impl PartialOrd for TimedLogEntry {
-//     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-//         Some(self.get_timestamp().cmp(&other.get_timestamp()))
-//     }
-// }
-
-// impl TimedLogEntry for TimedLine {}
-// // Box<&dyn TimedLineIter> as Box<&dyn Iterator<Item = TimedLogEntry>>
-// // 1.TimedLine
-// // 2.DltRecord
-
-// pub(crate) fn merge_inputs_with_writer<T: Read>(
-//     writer: &mut IndexOutput,
-//     merger_inputs: Vec<impl Iterator<Item = TimedLogEntry>>,
-//     update_channel: cc::Sender<ChunkResults>,
-//     shutdown_rx: Option<&cc::Receiver<()>>,
-// ) -> Result<()> {
-
-pub(crate) fn merge_inputs_with_writer(
-    writer: &mut IndexOutput,
-    merger_inputs: Vec<FileMergeOptions>,
-    update_channel: cc::Sender<ChunkResults>,
-    shutdown_token: Option<CancellationToken>,
-) -> Result<(), MergeError> {
-    trace!("merge_inputs_with_writer ({} files)", merger_inputs.len());
-    let mut lines_with_year_missing = 0usize;
-    // create a peekable iterator for all file inputs
-    let mut readers: Vec<Peekable<TimedLineIter>> = merger_inputs
-        .into_iter()
-        .map(
-            |input: FileMergeOptions| -> Result<Peekable<TimedLineIter>, MergeError> {
-                let file_path = PathBuf::from(input.path);
-                let absolute_path = file_path;
-                trace!("absolute_path was: {:?}", absolute_path);
-                Ok(TimedLineIter::new(
-                    File::open(absolute_path)?,
-                    input.tag,
-                    lookup_regex_for_format_str(&input.format)?,
-                    input.year,
-                    input.offset,
-                    writer.line_nr,
-                )
-                .peekable())
-            },
-        )
-        .filter_map(Result::ok) // TODO better error handling
-        .collect();
-    let mut stopped = false;
-    loop {
-        if stopped {
-            info!("we where stopped while merging");
-            break;
-        }
-        // keep track of the min timestamp together with the index of the file it belongs to
-        // to do this, we peek an entry of each reader and of all those entries we
-        // find the minimum timestamp entry
-        let mut minimum: Option<(i64, usize)> = None;
-        for (i, iter) in readers.iter_mut().enumerate() {
-            if let Some(entry) = iter.peek() {
-                match minimum {
-                    Some((t_min, _)) => {
-                        if entry.timestamp < t_min {
-                            minimum = Some((entry.timestamp, i));
-                        }
-                    }
-                    None => {
-                        minimum = Some((entry.timestamp, i));
-                    }
-                }
-                if entry.year_was_missing {
-                    lines_with_year_missing += 1
-                }
-            }
-        }
-        if let Some((_, min_index)) = minimum {
-            // we found an entry with a minimal timestamp
-            if let Some(line) = readers[min_index].next() {
-                // important: keep track of how many bytes we processed
-                let trimmed_len = line.content.len();
-                if trimmed_len > 0 {
-                    writer.add_to_chunk(&line.content, &line.tag, line.original_length)?;
-                    stopped = if let Some(shutdown_token) = &shutdown_token {
-                        shutdown_token.is_cancelled()
-                    } else {
-                        false
-                    };
-                }
-            } else {
-                break;
-            }
-        } else {
-            break;
-        }
-    }
-    if stopped {
-        debug!("sending IndexingProgress::Stopped");
-        update_channel
-            .send(Ok(IndexingProgress::Stopped))
-            .expect("sending to update_channel failed");
-    } else {
-        if lines_with_year_missing > 0 {
-            report_warning(format!(
-                "year was missing for {lines_with_year_missing} lines"
-            ));
-        }
-        writer.write_rest()?;
-    }
-    update_channel
-        .send(Ok(IndexingProgress::Finished))
-        .expect("sending to update channel failed");
-    Ok(())
-}
-
-pub struct IndexOutput {
-    pub(crate) line_nr: usize,
-    update_channel: cc::Sender<ChunkResults>,
-    chunk_count: usize,
-    chunk_factory: ChunkFactory,
-    buf_writer: BufWriter<File>,
-    progress_reporter: ProgressReporter<Chunk>,
-}
-
-pub(crate) fn combined_file_size<T>(paths: &[T]) -> Result<u64, MergeError>
+pub fn combined_file_size<T>(paths: &[T]) -> Result<u64, MergeError>
 where
     T: Len + Debug,
 {
@@ -420,77 +256,3 @@ where
         ))),
     })
 }
-
-impl IndexOutput {
-    fn new(
-        append: bool,
-        out_path: &Path,
-        chunk_size: usize,
-        combined_size: u64,
-        update_channel: cc::Sender<ChunkResults>,
-    ) -> Result<Self, MergeError> {
-        let out_file: File = if append {
-            std::fs::OpenOptions::new()
-                .append(true)
-                .create(true)
-                .open(out_path)?
-        } else {
-            File::create(out_path)?
-        };
-        let line_nr = if append {
-            utils::next_line_nr(out_path)
-                .map_err(|e| MergeError::GeneralMergingProblem(format!("{e:?}")))?
-        } else {
-            0
-        };
-        let original_file_size = out_file.metadata()?.len() as usize;
-        let buf_writer = BufWriter::with_capacity(100 * 1024 * 1024, out_file);
-
-        let progress_reporter = ProgressReporter::new(combined_size, update_channel.clone());
-        Ok(IndexOutput {
-            line_nr,
-            update_channel,
-            chunk_factory: ChunkFactory::new(chunk_size, original_file_size),
-            chunk_count: 0usize,
-            buf_writer,
-            progress_reporter,
-        })
-    }
-
-    pub(crate) fn add_to_chunk(
-        &mut self,
-        content: &str,
-        tag: &str,
-        original_len: usize,
-    ) -> Result<(), MergeError> {
-        let additional_bytes =
-            utils::write_tagged_line(tag, &mut self.buf_writer, content, self.line_nr, true, None)?;
-        self.line_nr += 1;
-        self.progress_reporter.make_progress(original_len);
-        if let Some(chunk) = self.chunk_factory.add_bytes(
-            self.line_nr, // TODO avoid passing in this line...error prone
-            additional_bytes,
-        ) {
-            self.chunk_count += 1;
-            self.buf_writer.flush()?;
-            self.update_channel
-                .send(Ok(IndexingProgress::GotItem { item: chunk }))
-                .expect("sending to update channel failed");
-        }
-        Ok(())
-    }
-    pub(crate) fn write_rest(&mut self) -> Result<(), MergeError> {
-        self.buf_writer.flush()?;
-        if let Some(chunk) = self
-            .chunk_factory
-            .create_last_chunk(self.line_nr, self.chunk_count > 0)
-        {
-            self.update_channel
-                .send(Ok(IndexingProgress::GotItem { item: chunk }))
-                .expect("update_channel send failed");
-        }
-        Ok(())
-    }
-}
-
-*/
@@ -1,45 +1,91 @@
 #[cfg(test)]
 mod tests {
-    // Note this useful idiom: importing names from outer (for mod tests) scope.
     use crate::merger::*;
-    use crossbeam_channel as cc;
-    use indexer_base::{
-        chunks::ChunkResults,
-        progress::{IndexingProgress, Notification},
-    };
     use pretty_assertions::assert_eq;
     use std::{fs, path::PathBuf};
-    use tempdir::TempDir;
-    extern crate log;
 
-    use log::LevelFilter;
-    use log4rs::{
-        append::console::ConsoleAppender,
-        config::{Appender, Config, Root},
-    };
-    use std::sync::Once;
+    fn sample_dir(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("test_samples")
+            .join(name)
+    }
+
+    fn merge_sample(name: &str) -> Vec<String> {
+        let dir = sample_dir(name);
+        let mut config = fs::File::open(dir.join("config.json")).expect("config missing");
+        let options = read_merge_options(&mut config, Some(&dir)).expect("invalid config");
+        Merger::new(options)
+            .expect("could not create merger")
+            .map(|(_, line)| line.content)
+            .collect()
+    }
+
+    /// expected results contain the content of each line followed by tag and line number
+    fn expected_lines(name: &str) -> Vec<String> {
+        fs::read_to_string(sample_dir(name).join("expected.merged"))
+            .expect("expected result missing")
+            .lines()
+            .map(|l| l.split('\u{0003}').next().unwrap_or_default().to_owned())
+            .collect()
+    }
+
+    fn test_sample(name: &str) {
+        assert_eq!(expected_lines(name), merge_sample(name));
+    }
+
+    #[test]
+    fn test_merge_simple_both_regex_2() {
+        test_sample("simple_both_regex_2");
+    }
 
-    static INIT: Once = Once::new();
+    #[test]
+    fn test_merge_missing_timestamp_lines() {
+        test_sample("missing_timestamp_lines");
+    }
+
+    #[test]
+    fn test_merge_not_standard_format() {
+        test_sample("not_standard_format");
+    }
 
-    fn init_logging() {
-        INIT.call_once(|| {
-            let stdout = ConsoleAppender::builder().build();
-            let config = Config::builder()
-                .appender(Appender::builder().build("stdout", Box::new(stdout)))
-                .build(Root::builder().appender("stdout").build(LevelFilter::Trace))
-                .unwrap();
+    #[test]
+    fn test_merge_two_adb_files() {
+        test_sample("two_adb_files");
+    }
 
-            match log4rs::init_config(config) {
-                Ok(_) => (),
-                Err(e) => println!("error initalizing logger: {e:?}"),
-            }
-        });
+    #[test]
+    fn test_merge_indexer_log_files() {
+        test_sample("indexer_log_files");
     }
 
     #[test]
-    fn test_merge_inputs_with_writer() {
-        assert_eq!(true, true)
+    fn test_merge_reports_input_index_and_progress() {
+        let dir = sample_dir("simple_both_regex_2");
+        let mut config = fs::File::open(dir.join("config.json")).expect("config missing");
+        let options = read_merge_options(&mut config, Some(&dir)).expect("invalid config");
+        let total = combined_file_size(&options).expect("size not available");
+        let mut merger = Merger::new(options).expect("could not create merger");
+        let indices: Vec<usize> = merger.by_ref().map(|(index, _)| index).collect();
+        assert_eq!(vec![0, 0, 0, 1, 0, 0], indices[..6].to_vec());
+        assert_eq!(total, merger.processed_bytes());
+        assert_eq!(0, merger.lines_with_year_missing());
     }
 
-    // TODO test files with lines without timestamp
+    #[test]
+    fn test_merge_with_invalid_format() {
+        let options = vec![FileMergeOptions {
+            path: sample_dir("simple_both_regex_2")
+                .join("a.log")
+                .to_string_lossy()
+                .into(),
+            offset: None,
+            year: None,
+            tag: "A".to_owned(),
+            format: "hh:mm".to_owned(),
+        }];
+        assert!(matches!(
+            Merger::new(options),
+            Err(MergeError::DateFormat(_))
+        ));
+    }
 }
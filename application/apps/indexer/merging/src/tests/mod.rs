@@ -1,2 +1 @@
-#[macro_use]
 mod merger_tests;
@@ -0,0 +1,143 @@
+use crate::{
+    column_value,
+    text::{StringMessage, StringTokenizer, TextEncoding},
+    Error, LogMessage, ParseYield, Parser, COLUMN_SEP,
+};
+use regex::Regex;
+use serde::Serialize;
+use std::{fmt, io::Write};
+
+/// Splits text lines into columns defined by the named capture groups of a regex
+///
+/// Each named group becomes one column (in the order of the groups within the pattern).
+/// Lines which don't match the pattern are delivered as a single column.
+pub struct ColumnsParser {
+    tokenizer: StringTokenizer,
+    regex: Regex,
+}
+
+impl ColumnsParser {
+    pub fn new(pattern: &str, encoding: TextEncoding) -> Result<Self, Error> {
+        let regex =
+            Regex::new(pattern).map_err(|e| Error::Parse(format!("invalid columns regex: {e}")))?;
+        if regex.capture_names().flatten().next().is_none() {
+            return Err(Error::Parse(String::from(
+                "columns regex doesn't define any named capture group",
+            )));
+        }
+        Ok(Self {
+            tokenizer: StringTokenizer::new(encoding),
+            regex,
+        })
+    }
+
+    /// Names of the columns, taken from the named capture groups
+    pub fn columns(&self) -> Vec<String> {
+        self.regex
+            .capture_names()
+            .flatten()
+            .map(String::from)
+            .collect()
+    }
+
+    fn split(&self, line: String) -> ColumnsMessage {
+        let columns = self.regex.captures(&line).map(|caps| {
+            self.regex
+                .capture_names()
+                .flatten()
+                .map(|name| {
+                    caps.name(name)
+                        .map(|m| column_value(m.as_str()))
+                        .unwrap_or_default()
+                })
+                .collect()
+        });
+        ColumnsMessage::new(line, columns)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct ColumnsMessage {
+    line: String,
+    /// `None` if the line couldn't be split into columns
+    columns: Option<Vec<String>>,
+}
+
+impl ColumnsMessage {
+    pub(crate) fn new(line: String, columns: Option<Vec<String>>) -> Self {
+        Self { line, columns }
+    }
+}
+
+impl fmt::Display for ColumnsMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.columns.as_ref() {
+            Some(columns) => write!(f, "{}", columns.join(COLUMN_SEP)),
+            None => write!(f, "{}", self.line),
+        }
+    }
+}
+
+impl LogMessage for ColumnsMessage {
+    fn to_writer<W: Write>(&self, writer: &mut W) -> Result<usize, std::io::Error> {
+        let len = self.line.len();
+        writer.write_all(self.line.as_bytes())?;
+        Ok(len)
+    }
+}
+
+impl Parser<ColumnsMessage> for ColumnsParser
+where
+    ColumnsMessage: LogMessage,
+{
+    fn parse(
+        &mut self,
+        input: &[u8],
+        timestamp: Option<u64>,
+    ) -> Result<impl Iterator<Item = (usize, Option<ParseYield<ColumnsMessage>>)>, Error> {
+        let items = Parser::<StringMessage>::parse(&mut self.tokenizer, input, timestamp)?
+            .collect::<Vec<_>>();
+        Ok(items
+            .into_iter()
+            .map(|(consumed, item)| match item {
+                Some(ParseYield::Message(StringMessage { content })) => {
+                    (consumed, Some(self.split(content).into()))
+                }
+                _ => (consumed, None),
+            })
+            .collect::<Vec<_>>()
+            .into_iter())
+    }
+}
+
+#[test]
+fn test_columns_parser() {
+    let mut parser = ColumnsParser::new(
+        r"^(?P<time>\S+ \S+) \[(?P<level>\w+)\] (?:(?P<module>\w+): )?(?P<msg>.*)$",
+        TextEncoding::Auto,
+    )
+    .unwrap();
+    assert_eq!(parser.columns(), vec!["time", "level", "module", "msg"]);
+    let mut input: &[u8] =
+        b"2024-01-01 10:00:00 [INFO] net: link up\n2024-01-01 10:00:01 [WARN] no module\ngarbage\n2024-01-01 10:00:02 [INFO] a\x04b\n";
+    let mut lines = vec![];
+    while !input.is_empty() {
+        let (consumed, msg) = parser.parse(input, None).unwrap().next().unwrap();
+        if let Some(ParseYield::Message(msg)) = msg {
+            lines.push(msg.to_string());
+        }
+        input = &input[consumed..];
+    }
+    assert_eq!(
+        lines,
+        vec![
+            "2024-01-01 10:00:00\u{0004}INFO\u{0004}net\u{0004}link up",
+            "2024-01-01 10:00:01\u{0004}WARN\u{0004}\u{0004}no module",
+            "garbage",
+            // the column separator doesn't split a value
+            "2024-01-01 10:00:02\u{0004}INFO\u{0004}\u{0004}a b",
+        ]
+    );
+    assert!(ColumnsParser::new(r"^(\w+) (\w+)$", TextEncoding::Auto).is_err());
+    assert!(ColumnsParser::new(r"^(?P<a>\w+", TextEncoding::Auto).is_err());
+}
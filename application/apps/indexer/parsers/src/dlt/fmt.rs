@@ -33,7 +33,7 @@ use std::{
     str,
 };
 
-const DLT_COLUMN_SENTINAL: char = '\u{0004}';
+const DLT_COLUMN_SENTINAL: &str = crate::COLUMN_SEP;
 const DLT_ARGUMENT_SENTINAL: char = '\u{0005}';
 const DLT_NEWLINE_SENTINAL_SLICE: &[u8] = &[0x6];
 
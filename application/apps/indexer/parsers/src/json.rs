@@ -0,0 +1,127 @@
+use crate::{
+    columns::ColumnsMessage,
+    text::{StringMessage, StringTokenizer, TextEncoding},
+    column_value, Error, ParseYield, Parser, LINE_SEP,
+};
+use serde_json::Value;
+
+/// Parser for JSON Lines (one JSON object per line)
+///
+/// The values of the configured key paths are delivered as columns. A key path
+/// addresses nested values separated by dots (e.g. `ctx.request_id`); array elements
+/// can be addressed by their index. Lines which aren't a JSON object are passed through
+/// unchanged.
+pub struct JsonParser {
+    tokenizer: StringTokenizer,
+    keys: Vec<String>,
+}
+
+impl JsonParser {
+    pub fn new(keys: Vec<String>, encoding: TextEncoding) -> Result<Self, Error> {
+        if keys.is_empty() {
+            return Err(Error::Parse(String::from(
+                "no keys for JSON columns are defined",
+            )));
+        }
+        Ok(Self {
+            tokenizer: StringTokenizer::new(encoding),
+            keys,
+        })
+    }
+
+    /// Names of the columns, which are the configured key paths
+    pub fn columns(&self) -> Vec<String> {
+        self.keys.clone()
+    }
+
+    fn flatten(&self, line: String) -> ColumnsMessage {
+        let columns = match serde_json::from_str::<Value>(&line) {
+            Ok(object @ Value::Object(_)) => Some(
+                self.keys
+                    .iter()
+                    .map(|key| lookup(&object, key).map(to_column).unwrap_or_default())
+                    .collect(),
+            ),
+            _ => None,
+        };
+        ColumnsMessage::new(line, columns)
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(value, |value, segment| match value {
+            Value::Object(map) => map.get(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+}
+
+fn to_column(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => column_value(&s.replace('\n', LINE_SEP)),
+        other => other.to_string(),
+    }
+}
+
+impl Parser<ColumnsMessage> for JsonParser {
+    fn parse(
+        &mut self,
+        input: &[u8],
+        timestamp: Option<u64>,
+    ) -> Result<impl Iterator<Item = (usize, Option<ParseYield<ColumnsMessage>>)>, Error> {
+        let items = Parser::<StringMessage>::parse(&mut self.tokenizer, input, timestamp)?
+            .collect::<Vec<_>>();
+        Ok(items
+            .into_iter()
+            .map(|(consumed, item)| match item {
+                Some(ParseYield::Message(StringMessage { content })) => {
+                    (consumed, Some(self.flatten(content).into()))
+                }
+                _ => (consumed, None),
+            })
+            .collect::<Vec<_>>()
+            .into_iter())
+    }
+}
+
+#[test]
+fn test_json_parser() {
+    let mut parser = JsonParser::new(
+        vec![
+            String::from("ts"),
+            String::from("level"),
+            String::from("msg"),
+            String::from("ctx.request_id"),
+            String::from("tags.1"),
+        ],
+        TextEncoding::Auto,
+    )
+    .unwrap();
+    let mut input: &[u8] = br#"{"ts":"2024-01-01T10:00:00Z","level":"info","msg":"two\nlines","ctx":{"request_id":42},"tags":["a","b"]}
+{"ts":"2024-01-01T10:00:01Z","level":"warn","msg":null}
+{"ts":"2024-01-01T10:00:02Z","level":"info","msg":"a\u0004b"}
+{"ts": broken
+[1,2]
+"#;
+    let mut lines = vec![];
+    while !input.is_empty() {
+        let (consumed, msg) = parser.parse(input, None).unwrap().next().unwrap();
+        if let Some(ParseYield::Message(msg)) = msg {
+            lines.push(msg.to_string());
+        }
+        input = &input[consumed..];
+    }
+    assert_eq!(
+        lines,
+        vec![
+            "2024-01-01T10:00:00Z\u{0004}info\u{0004}two\u{0006}lines\u{0004}42\u{0004}b",
+            "2024-01-01T10:00:01Z\u{0004}warn\u{0004}\u{0004}\u{0004}",
+            "2024-01-01T10:00:02Z\u{0004}info\u{0004}a b\u{0004}\u{0004}",
+            r#"{"ts": broken"#,
+            "[1,2]",
+        ]
+    );
+    assert!(JsonParser::new(vec![], TextEncoding::Auto).is_err());
+}
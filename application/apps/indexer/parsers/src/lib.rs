@@ -1,6 +1,9 @@
 #![deny(unused_crate_dependencies)]
+pub mod columns;
 pub mod dlt;
+pub mod json;
 pub mod someip;
+pub mod syslog;
 pub mod text;
 use serde::Serialize;
 use std::{fmt::Display, io::Write};
@@ -8,6 +11,17 @@ use thiserror::Error;
 
 extern crate log;
 
+/// Marker for a column separator in the output string.
+pub const COLUMN_SEP: &str = "\u{0004}"; // EOT
+/// Marker for a newline in the output string.
+pub const LINE_SEP: &str = "\u{0006}"; // ACK
+
+/// Replaces the column separator within the value of a column, which would split the
+/// value into several columns otherwise.
+pub(crate) fn column_value(value: &str) -> String {
+    value.replace(COLUMN_SEP, " ")
+}
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Parse error: {0}")]
@@ -52,6 +66,15 @@ pub trait Parser<T> {
         input: &[u8],
         timestamp: Option<u64>,
     ) -> Result<impl Iterator<Item = (usize, Option<ParseYield<T>>)>, Error>;
+
+    /// Called if [`Parser::parse`] reported [`Error::Incomplete`] and the source won't provide
+    /// more bytes for now (e.g. the end of a file which isn't tailed was reached, or a tailed
+    /// file is idle). Parsers which hold back data until they see the start of the next
+    /// message can deliver it here together with the consumed bytes count. The input might
+    /// be passed to [`Parser::parse`] again afterwards if the source continues.
+    fn parse_rest(&mut self, _input: &[u8]) -> Option<(usize, ParseYield<T>)> {
+        None
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -1,11 +1,10 @@
-use crate::{Error, LogMessage, ParseYield, Parser};
+use crate::{Error, LogMessage, ParseYield, Parser, COLUMN_SEP, LINE_SEP};
 use std::{
     borrow::Cow,
     cmp::Ordering,
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt::{self, Display},
     io::Write,
-    iter,
     path::PathBuf,
     sync::Mutex,
 };
@@ -18,15 +17,10 @@ use someip_payload::{
 };
 
 use lazy_static::lazy_static;
-use log::{debug, error};
+use log::{debug, error, trace};
 use regex::Regex;
 use serde::Serialize;
 
-/// Marker for a column separator in the output string.
-const COLUMN_SEP: &str = "\u{0004}"; // EOT
-/// Marker for a newline in the output string.
-const LINE_SEP: &str = "\u{0006}"; // ACK
-
 /// Wrapper for a fibex-model (new-type pattern).
 pub struct FibexMetadata {
     model: FibexModel,
@@ -166,9 +160,203 @@ impl FibexTypeCache {
     }
 }
 
+/// Flag of the message type for segmented messages (SOME/IP-TP).
+const TP_FLAG: u8 = 0x20;
+/// Length of the SOME/IP-TP header following the SOME/IP header.
+const TP_HEADER_LENGTH: usize = 4;
+/// Max. count of segmented messages being reassembled at the same time.
+const MAX_PENDING_TP_MESSAGES: usize = 64;
+/// Max. size of a reassembled message.
+const MAX_TP_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+/// Protocol version of the SOME/IP header.
+const PROTOCOL_VERSION: u8 = 0x01;
+/// Message types (without the TP flag): request, request without return, notification,
+/// response and error.
+const MESSAGE_TYPES: [u8; 5] = [0x00, 0x01, 0x02, 0x80, 0x81];
+/// Max. length of a message the statistic waits for; longer ones are considered invalid.
+const MAX_COLLECTED_MESSAGE_LENGTH: usize = 64 * 1024;
+
+/// Identifies a segmented message: service, method, client and session id.
+type TpKey = (u16, u16, u16, u16);
+
+/// The segments of a SOME/IP-TP message received so far.
+struct TpMessage {
+    /// Header of the first received segment.
+    header: [u8; Header::LENGTH],
+    /// Segment payloads by their offset.
+    segments: BTreeMap<usize, Vec<u8>>,
+    /// Highest offset received so far.
+    highest_offset: usize,
+    /// Length of the whole payload, known as soon as the last segment was received.
+    total_len: Option<usize>,
+    /// Sequence number of the first segment, used to drop the oldest message.
+    started: u64,
+}
+
+impl TpMessage {
+    /// Returns the ranges of the payload which are missing.
+    fn missing(&self) -> Vec<(usize, Option<usize>)> {
+        let mut missing = vec![];
+        let mut expected = 0;
+        for (offset, data) in &self.segments {
+            if *offset > expected {
+                missing.push((expected, Some(*offset)));
+            }
+            expected = expected.max(offset + data.len());
+        }
+        match self.total_len {
+            Some(total_len) if expected < total_len => missing.push((expected, Some(total_len))),
+            None => missing.push((expected, None)),
+            _ => {}
+        }
+        missing
+    }
+
+    /// Builds the complete message out of the segments.
+    fn assemble(&self) -> Vec<u8> {
+        let total_len = self.total_len.unwrap_or(0);
+        let mut bytes = Vec::with_capacity(Header::LENGTH + total_len);
+        bytes.extend_from_slice(&self.header);
+        bytes[4..8].copy_from_slice(&((total_len + 8) as u32).to_be_bytes());
+        bytes[14] &= !TP_FLAG;
+        for (offset, data) in &self.segments {
+            // overlapping segments are possible
+            let written = bytes.len() - Header::LENGTH;
+            if offset + data.len() > written {
+                bytes.extend_from_slice(&data[written - offset..]);
+            }
+        }
+        bytes.truncate(Header::LENGTH + total_len);
+        bytes
+    }
+}
+
+/// Reassembles segmented messages (SOME/IP-TP).
+#[derive(Default)]
+struct TpReassembler {
+    messages: HashMap<TpKey, TpMessage>,
+    sequence: u64,
+}
+
+/// Outcome of adding a segment to the [`TpReassembler`].
+#[derive(Default)]
+struct TpOutcome {
+    /// Descriptions of problems with the segments (e.g. missing segments).
+    reports: Vec<String>,
+    /// The reassembled message, if the segment completed one.
+    message: Option<Vec<u8>>,
+}
+
+impl TpReassembler {
+    fn add_segment(&mut self, segment: &[u8]) -> TpOutcome {
+        let mut outcome = TpOutcome::default();
+        let key: TpKey = (
+            u16::from_be_bytes([segment[0], segment[1]]),
+            u16::from_be_bytes([segment[2], segment[3]]),
+            u16::from_be_bytes([segment[8], segment[9]]),
+            u16::from_be_bytes([segment[10], segment[11]]),
+        );
+        let tp_header = u32::from_be_bytes([segment[16], segment[17], segment[18], segment[19]]);
+        let offset = (tp_header & 0xFFFF_FFF0) as usize;
+        let more_segments = tp_header & 0x01 != 0;
+        let data = &segment[Header::LENGTH + TP_HEADER_LENGTH..];
+        let header_columns = raw_header_string(segment);
+
+        if !self.messages.contains_key(&key) {
+            // a sender starting the next message is done with the previous one
+            let (service, method, client, _) = key;
+            let abandoned = self
+                .messages
+                .keys()
+                .filter(|(s, m, c, _)| (*s, *m, *c) == (service, method, client))
+                .copied()
+                .collect::<Vec<TpKey>>();
+            for key in abandoned {
+                self.drop_incomplete(&key, &mut outcome);
+            }
+            if self.messages.len() >= MAX_PENDING_TP_MESSAGES {
+                if let Some(oldest) = self
+                    .messages
+                    .iter()
+                    .min_by_key(|(_, message)| message.started)
+                    .map(|(key, _)| *key)
+                {
+                    self.drop_incomplete(&oldest, &mut outcome);
+                }
+            }
+            let mut header = [0u8; Header::LENGTH];
+            header.copy_from_slice(&segment[..Header::LENGTH]);
+            self.sequence += 1;
+            self.messages.insert(
+                key,
+                TpMessage {
+                    header,
+                    segments: BTreeMap::new(),
+                    highest_offset: 0,
+                    total_len: None,
+                    started: self.sequence,
+                },
+            );
+        }
+        let Some(message) = self.messages.get_mut(&key) else {
+            return outcome;
+        };
+        if offset < message.highest_offset || message.segments.contains_key(&offset) {
+            outcome.reports.push(format!(
+                "TP{COLUMN_SEP}{header_columns}{COLUMN_SEP}Out-of-order segment at offset {offset} (after offset {})",
+                message.highest_offset
+            ));
+        }
+        if offset + data.len() > MAX_TP_MESSAGE_SIZE {
+            outcome.reports.push(format!(
+                "TP{COLUMN_SEP}{header_columns}{COLUMN_SEP}Message exceeds {MAX_TP_MESSAGE_SIZE} bytes, dropped"
+            ));
+            self.messages.remove(&key);
+            return outcome;
+        }
+        message.highest_offset = message.highest_offset.max(offset);
+        message.segments.insert(offset, data.to_vec());
+        if !more_segments {
+            message.total_len = Some(offset + data.len());
+        }
+        if message.total_len.is_some() && message.missing().is_empty() {
+            outcome.message = self.messages.remove(&key).map(|message| message.assemble());
+        }
+        outcome
+    }
+
+    /// Drops a message which can't be completed anymore and reports the missing segments.
+    fn drop_incomplete(&mut self, key: &TpKey, outcome: &mut TpOutcome) {
+        if let Some(message) = self.messages.remove(key) {
+            let missing = message
+                .missing()
+                .iter()
+                .map(|(start, end)| match end {
+                    Some(end) => format!("{start}..{end}"),
+                    None => format!("{start}..end"),
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+            outcome.reports.push(format!(
+                "TP{COLUMN_SEP}{}{COLUMN_SEP}Incomplete message, missing bytes {missing}",
+                raw_header_string(&message.header)
+            ));
+        }
+    }
+}
+
+/// Checks if the input begins with a segmented message (SOME/IP-TP).
+fn is_tp_segment(input: &[u8]) -> bool {
+    input.len() >= Header::LENGTH && input[14] & TP_FLAG != 0
+}
+
+/// A parsed item together with the consumed bytes.
+type ParsedItem = (usize, Option<ParseYield<SomeipLogMessage>>);
+
 /// A parser for SOME/IP log messages.
 pub struct SomeipParser {
     fibex_metadata: Option<FibexMetadata>,
+    tp: TpReassembler,
 }
 
 impl Default for SomeipParser {
@@ -182,6 +370,7 @@ impl SomeipParser {
     pub fn new() -> Self {
         SomeipParser {
             fibex_metadata: None,
+            tp: TpReassembler::default(),
         }
     }
 
@@ -189,6 +378,7 @@ impl SomeipParser {
     pub fn from_fibex_files(paths: Vec<PathBuf>) -> Self {
         SomeipParser {
             fibex_metadata: FibexMetadata::from_fibex_files(paths),
+            tp: TpReassembler::default(),
         }
     }
 
@@ -270,6 +460,62 @@ impl SomeipParser {
             }
         }
     }
+
+    /// Buffers a segment of a SOME/IP-TP message. The message is parsed as soon as
+    /// all of its segments were received.
+    fn parse_tp_segment(
+        &mut self,
+        input: &[u8],
+        timestamp: Option<u64>,
+    ) -> Result<Vec<ParsedItem>, Error> {
+        let min_len = Header::LENGTH + TP_HEADER_LENGTH;
+        if input.len() < min_len {
+            return Err(Error::Parse(format!(
+                "Not enough data: min: {min_len}, actual: {}",
+                input.len()
+            )));
+        }
+        let len = u32::from_be_bytes([input[4], input[5], input[6], input[7]]) as usize + 8;
+        if len < min_len || input.len() < len {
+            return Err(Error::Parse(format!(
+                "Not enough data: min: {}, actual: {}",
+                len.max(min_len),
+                input.len()
+            )));
+        }
+        let consumed = if input.len() - len < Header::LENGTH {
+            input.len()
+        } else {
+            len
+        };
+        debug!("at {} : TP segment ({} bytes)", timestamp.unwrap_or(0), len);
+        let outcome = self.tp.add_segment(&input[..len]);
+        let mut items = outcome
+            .reports
+            .into_iter()
+            .map(|report| {
+                Some(ParseYield::from(SomeipLogMessage::from(
+                    report,
+                    input[..len].to_vec(),
+                )))
+            })
+            .collect::<Vec<_>>();
+        if let Some(bytes) = outcome.message {
+            let (_, message) =
+                SomeipParser::parse_message(self.fibex_metadata.as_ref(), &bytes, timestamp)?;
+            items.push(Some(ParseYield::from(message)));
+        }
+        if items.is_empty() {
+            // the segment is buffered
+            return Ok(vec![(consumed, None)]);
+        }
+        // the first item accounts for the consumed bytes
+        Ok(items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| (if i == 0 { consumed } else { 0 }, item))
+            .collect())
+    }
 }
 
 unsafe impl Send for SomeipParser {}
@@ -281,10 +527,13 @@ impl Parser<SomeipLogMessage> for SomeipParser {
         input: &[u8],
         timestamp: Option<u64>,
     ) -> Result<impl Iterator<Item = (usize, Option<ParseYield<SomeipLogMessage>>)>, Error> {
+        if is_tp_segment(input) {
+            return Ok(self.parse_tp_segment(input, timestamp)?.into_iter());
+        }
         let item = SomeipParser::parse_message(self.fibex_metadata.as_ref(), input, timestamp)
             .map(|(rest, message)| (rest, Some(ParseYield::from(message))))?;
 
-        Ok(iter::once(item))
+        Ok(vec![item].into_iter())
     }
 }
 
@@ -302,6 +551,21 @@ fn header_string(header: &Header) -> String {
     )
 }
 
+/// Same as [`header_string`] but read from the raw bytes of a header.
+fn raw_header_string(header: &[u8]) -> String {
+    format!(
+        "{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}",
+        u16::from_be_bytes([header[0], header[1]]),
+        u16::from_be_bytes([header[2], header[3]]),
+        u32::from_be_bytes([header[4], header[5], header[6], header[7]]),
+        u16::from_be_bytes([header[8], header[9]]),
+        u16::from_be_bytes([header[10], header[11]]),
+        header[13],
+        header[14],
+        header[15],
+    )
+}
+
 fn sd_message_string(header: &Header, payload: &SdPayload) -> String {
     let mut string = format!(
         "SD{}{}{}Flags [{:02X?}]",
@@ -507,12 +771,149 @@ impl fmt::Debug for SomeipLogMessage {
 fn merge_columns(columns: &str) -> Cow<str> {
     lazy_static! {
         static ref REGEX : Regex = Regex::new(
-            &format!("(SD|RPC){COLUMN_SEP}(\\d+){COLUMN_SEP}(\\d+){COLUMN_SEP}(\\d+){COLUMN_SEP}(\\d+){COLUMN_SEP}(\\d+){COLUMN_SEP}(\\d+){COLUMN_SEP}(\\d+){COLUMN_SEP}(\\d+){COLUMN_SEP}(.*)")
+            &format!("(SD|RPC|TP){COLUMN_SEP}(\\d+){COLUMN_SEP}(\\d+){COLUMN_SEP}(\\d+){COLUMN_SEP}(\\d+){COLUMN_SEP}(\\d+){COLUMN_SEP}(\\d+){COLUMN_SEP}(\\d+){COLUMN_SEP}(\\d+){COLUMN_SEP}(.*)")
             ).unwrap();
     }
     REGEX.replace(columns, "${1} SERV:${2} METH:${3} LENG:${4} CLID:${5} SEID:${6} IVER:${7} MSTP:${8} RETC:${9} ${10}")
 }
 
+/// Counts of ids and their related sub-ids.
+type StatisticCounts = BTreeMap<u16, BTreeMap<u16, usize>>;
+
+/// Statistic on the SOME/IP messages of one or more captures.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SomeipStatistic {
+    /// Statistic on service-ids and related method-ids
+    #[serde(serialize_with = "serialize_counts")]
+    services: StatisticCounts,
+    /// Statistic on service-ids and related instance-ids (as seen by service discovery)
+    #[serde(serialize_with = "serialize_counts")]
+    instances: StatisticCounts,
+    /// Statistic on message-types and related return-codes
+    #[serde(serialize_with = "serialize_counts")]
+    messages: StatisticCounts,
+}
+
+impl SomeipStatistic {
+    /// Creates a new empty statistic.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts the SOME/IP messages contained in the given input.
+    /// Segmented messages (SOME/IP-TP) are counted once, by their first segment.
+    /// Bytes not starting a valid header (e.g. the payload of other protocols) are
+    /// skipped one by one until the next header is found.
+    /// Returns the number of processed bytes; an incomplete message at the end of the
+    /// input isn't processed until the rest of it is available.
+    pub fn collect(&mut self, input: &[u8]) -> usize {
+        let mut pos = 0;
+        while input.len() - pos >= Header::LENGTH {
+            let message = &input[pos..];
+            let len =
+                u32::from_be_bytes([message[4], message[5], message[6], message[7]]) as usize + 8;
+            if !(Header::LENGTH..=MAX_COLLECTED_MESSAGE_LENGTH).contains(&len)
+                || message[12] != PROTOCOL_VERSION
+                || !MESSAGE_TYPES.contains(&(message[14] & !TP_FLAG))
+            {
+                trace!("no SOME/IP header at {}", pos);
+                pos += 1;
+                continue;
+            }
+            if len > message.len() {
+                break;
+            }
+            pos += len;
+            let message = &message[..len];
+            if is_tp_segment(message) {
+                let first = len >= Header::LENGTH + TP_HEADER_LENGTH
+                    && message[Header::LENGTH..Header::LENGTH + TP_HEADER_LENGTH]
+                        .iter()
+                        .zip([0xFF, 0xFF, 0xFF, 0xF0])
+                        .all(|(b, mask)| b & mask == 0);
+                if !first {
+                    continue;
+                }
+            }
+            let service_id = u16::from_be_bytes([message[0], message[1]]);
+            let method_id = u16::from_be_bytes([message[2], message[3]]);
+            count(&mut self.services, service_id, method_id);
+            count(
+                &mut self.messages,
+                (message[14] & !TP_FLAG) as u16,
+                message[15] as u16,
+            );
+            if let Ok(Message::Sd(_, payload)) = Message::from_slice(message) {
+                for entry in payload.entries.iter() {
+                    let (service_id, instance_id) = match entry {
+                        SdEntry::FindService(value) | SdEntry::OfferService(value) => {
+                            (value.service_id, value.instance_id)
+                        }
+                        SdEntry::SubscribeEventgroup(value)
+                        | SdEntry::SubscribeEventgroupAck(value) => {
+                            (value.service_id, value.instance_id)
+                        }
+                    };
+                    count(&mut self.instances, service_id, instance_id);
+                }
+            }
+        }
+        pos
+    }
+
+    /// Adds the counts of the other statistic to this one.
+    pub fn merge(&mut self, other: SomeipStatistic) {
+        for (target, source) in [
+            (&mut self.services, other.services),
+            (&mut self.instances, other.instances),
+            (&mut self.messages, other.messages),
+        ] {
+            for (id, details) in source {
+                let entry = target.entry(id).or_default();
+                for (detail, num) in details {
+                    *entry.entry(detail).or_default() += num;
+                }
+            }
+        }
+    }
+}
+
+fn count(counts: &mut StatisticCounts, id: u16, detail: u16) {
+    *counts.entry(id).or_default().entry(detail).or_default() += 1;
+}
+
+/// Serializes the counts as list of items, each with the total count of the id
+/// and the counts of its sub-ids as details.
+fn serialize_counts<S>(counts: &StatisticCounts, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    #[derive(Serialize)]
+    struct Detail {
+        id: u16,
+        num: usize,
+    }
+
+    #[derive(Serialize)]
+    struct Item {
+        item: Detail,
+        details: Vec<Detail>,
+    }
+
+    serializer.collect_seq(counts.iter().map(|(id, details)| {
+        Item {
+            item: Detail {
+                id: *id,
+                num: details.values().sum(),
+            },
+            details: details
+                .iter()
+                .map(|(id, num)| Detail { id: *id, num: *num })
+                .collect(),
+        }
+    }))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -694,6 +1095,7 @@ mod test {
         let fibex_metadata = test_metadata();
         let mut parser = SomeipParser {
             fibex_metadata: Some(fibex_metadata),
+            ..SomeipParser::new()
         };
         let (consumed, message) = parser.parse(input, None).unwrap().next().unwrap();
         assert_eq!(consumed, input.len());
@@ -747,6 +1149,7 @@ mod test {
         let fibex_metadata = test_metadata();
         let mut parser = SomeipParser {
             fibex_metadata: Some(fibex_metadata),
+            ..SomeipParser::new()
         };
         let (consumed, message) = parser.parse(input, None).unwrap().next().unwrap();
         assert_eq!(consumed, input.len());
@@ -772,6 +1175,7 @@ mod test {
         let fibex_metadata = test_metadata();
         let mut parser = SomeipParser {
             fibex_metadata: Some(fibex_metadata),
+            ..SomeipParser::new()
         };
         let (consumed, message) = parser.parse(input, None).unwrap().next().unwrap();
         assert_eq!(consumed, input.len());
@@ -797,6 +1201,7 @@ mod test {
         let fibex_metadata = test_metadata();
         let mut parser = SomeipParser {
             fibex_metadata: Some(fibex_metadata),
+            ..SomeipParser::new()
         };
         let (consumed, message) = parser.parse(input, None).unwrap().next().unwrap();
         assert_eq!(consumed, input.len());
@@ -822,6 +1227,7 @@ mod test {
         let fibex_metadata = test_metadata();
         let mut parser = SomeipParser {
             fibex_metadata: Some(fibex_metadata),
+            ..SomeipParser::new()
         };
         let (consumed, message) = parser.parse(input, None).unwrap().next().unwrap();
         assert_eq!(consumed, input.len());
@@ -847,6 +1253,7 @@ mod test {
         let fibex_metadata = test_metadata();
         let mut parser = SomeipParser {
             fibex_metadata: Some(fibex_metadata),
+            ..SomeipParser::new()
         };
         let (consumed, message) = parser.parse(input, None).unwrap().next().unwrap();
         assert_eq!(consumed, input.len());
@@ -929,6 +1336,171 @@ mod test {
         }
     }
 
+    /// Builds a SOME/IP-TP segment of the notification 259/32773 (session 2).
+    fn tp_segment(session: u8, offset: u32, more: bool, payload: &[u8]) -> Vec<u8> {
+        let mut segment = vec![
+            0x01, 0x03, 0x80, 0x05, // serviceId(u16), methodId(u16)
+        ];
+        segment.extend_from_slice(&(payload.len() as u32 + 12).to_be_bytes());
+        segment.extend_from_slice(&[
+            0x00, 0x01, 0x00, session, // clientId(u16), sessionId(u16)
+            0x01, 0x01, 0x22, 0x00, // proto(u8), version(u8), messageType,(u8) returnCode(u8)
+        ]);
+        segment.extend_from_slice(&(offset | more as u32).to_be_bytes());
+        segment.extend_from_slice(payload);
+        segment
+    }
+
+    fn parse_all(parser: &mut SomeipParser, input: &[u8]) -> Vec<(usize, Option<String>)> {
+        parser
+            .parse(input, None)
+            .unwrap()
+            .map(|(consumed, item)| match item {
+                Some(ParseYield::Message(item)) => (consumed, Some(format!("{:?}", item))),
+                None => (consumed, None),
+                _ => panic!("unexpected parse yield"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_tp_segments() {
+        let payload: Vec<u8> = (0..18).collect();
+        let first = tp_segment(2, 0, true, &payload[..16]);
+        let last = tp_segment(2, 16, false, &payload[16..]);
+
+        let mut parser = SomeipParser::new();
+        assert_eq!(parse_all(&mut parser, &first), vec![(first.len(), None)]);
+        assert_eq!(
+            parse_all(&mut parser, &last),
+            vec![(
+                last.len(),
+                Some(String::from(
+                    "RPC SERV:259 METH:32773 LENG:26 CLID:1 SEID:2 IVER:1 MSTP:2 RETC:0 \
+                    [00, 01, 02, 03, 04, 05, 06, 07, 08, 09, 0A, 0B, 0C, 0D, 0E, 0F, 10, 11]"
+                ))
+            )]
+        );
+    }
+
+    #[test]
+    fn parse_tp_segments_out_of_order() {
+        let payload: Vec<u8> = (0..18).collect();
+        let first = tp_segment(2, 0, true, &payload[..16]);
+        let last = tp_segment(2, 16, false, &payload[16..]);
+
+        let mut parser = SomeipParser::new();
+        assert_eq!(parse_all(&mut parser, &last), vec![(last.len(), None)]);
+        let items = parse_all(&mut parser, &first);
+        assert_eq!(items.len(), 2);
+        assert_eq!(
+            items[0],
+            (
+                first.len(),
+                Some(String::from(
+                    "TP SERV:259 METH:32773 LENG:28 CLID:1 SEID:2 IVER:1 MSTP:34 RETC:0 \
+                    Out-of-order segment at offset 0 (after offset 16)"
+                ))
+            )
+        );
+        assert_eq!(items[1].0, 0);
+        assert!(items[1].1.as_ref().unwrap().starts_with("RPC SERV:259"));
+    }
+
+    #[test]
+    fn parse_tp_segments_missing() {
+        let payload: Vec<u8> = (0..48).collect();
+        let second = tp_segment(2, 16, true, &payload[16..32]);
+        let next_message = tp_segment(3, 0, true, &payload[..16]);
+
+        let mut parser = SomeipParser::new();
+        assert_eq!(parse_all(&mut parser, &second), vec![(second.len(), None)]);
+        assert_eq!(
+            parse_all(&mut parser, &next_message),
+            vec![(
+                next_message.len(),
+                Some(String::from(
+                    "TP SERV:259 METH:32773 LENG:28 CLID:1 SEID:2 IVER:1 MSTP:34 RETC:0 \
+                    Incomplete message, missing bytes 0..16, 32..end"
+                ))
+            )]
+        );
+    }
+
+    #[test]
+    fn collect_statistic() {
+        let sd_message: &[u8] = &[
+            0xFF, 0xFF, 0x81, 0x00, // serviceId(u16), methodId(u16)
+            0x00, 0x00, 0x00, 0x24, // length(u32)
+            0x00, 0x00, 0x00, 0x00, // clientId(u16), sessionId(u16)
+            0x01, 0x01, 0x02, 0x00, // proto(u8), version(u8), messageType,(u8) returnCode(u8)
+            0xC0, 0x00, 0x00, 0x00, // sdFlags(08), reserved(u24)
+            // entries
+            0x00, 0x00, 0x00, 0x10, // entries-length(u32)
+            // offer-service
+            0x01, 0x00, 0x00, 0x00, // entryType(u8), index1(u8), index2,(u8) num1|2(u8)
+            0x01, 0x03, 0x00, 0x02, // serviceId(u16), instanceId(u16)
+            0x02, 0x00, 0x00, 0x03, // majorVersion(u8), ttl(u24)
+            0x00, 0x00, 0x00, 0x01, // minorVersion(u32)
+            // options
+            0x00, 0x00, 0x00, 0x00, // options-length(u32)
+        ];
+        let rpc_message: &[u8] = &[
+            0x01, 0x03, 0x80, 0x05, // serviceId(u16), methodId(u16)
+            0x00, 0x00, 0x00, 0x08, // length(u32)
+            0x00, 0x01, 0x00, 0x02, // clientId(u16), sessionId(u16)
+            0x01, 0x01, 0x81, 0x01, // proto(u8), version(u8), messageType,(u8) returnCode(u8)
+        ];
+        let payload: Vec<u8> = (0..18).collect();
+
+        let mut statistic = SomeipStatistic::new();
+        assert_eq!(statistic.collect(sd_message), sd_message.len());
+        assert_eq!(
+            statistic.collect(&[rpc_message, rpc_message].concat()),
+            2 * rpc_message.len()
+        );
+        let first_segment = tp_segment(2, 0, true, &payload[..16]);
+        assert_eq!(statistic.collect(&first_segment), first_segment.len());
+        let last_segment = tp_segment(2, 16, false, &payload[16..]);
+        assert_eq!(statistic.collect(&last_segment), last_segment.len());
+        // incomplete messages are kept until the rest is available
+        assert_eq!(statistic.collect(&rpc_message[..12]), 0);
+
+        let mut merged = SomeipStatistic::new();
+        merged.merge(statistic);
+        assert_eq!(
+            serde_json::to_string(&merged).unwrap(),
+            r#"{"services":[{"item":{"id":259,"num":3},"details":[{"id":32773,"num":3}]},{"item":{"id":65535,"num":1},"details":[{"id":33024,"num":1}]}],"instances":[{"item":{"id":259,"num":1},"details":[{"id":2,"num":1}]}],"messages":[{"item":{"id":2,"num":2},"details":[{"id":0,"num":2}]},{"item":{"id":129,"num":2},"details":[{"id":1,"num":2}]}]}"#
+        );
+    }
+
+    #[test]
+    fn collect_statistic_resync() {
+        let rpc_message: &[u8] = &[
+            0x01, 0x03, 0x80, 0x05, // serviceId(u16), methodId(u16)
+            0x00, 0x00, 0x00, 0x08, // length(u32)
+            0x00, 0x01, 0x00, 0x02, // clientId(u16), sessionId(u16)
+            0x01, 0x01, 0x02, 0x00, // proto(u8), version(u8), messageType,(u8) returnCode(u8)
+        ];
+        // payload of another protocol in front of the messages
+        let input = [
+            b"\x00\x00\xff\xff\xff\xffno SOME/IP at all".as_slice(),
+            rpc_message,
+            rpc_message,
+        ]
+        .concat();
+
+        let mut statistic = SomeipStatistic::new();
+        assert_eq!(statistic.collect(&input), input.len());
+        assert_eq!(statistic.services[&259][&32773], 2);
+        // the length of an invalid header doesn't hold back the input
+        let mut bogus = rpc_message.to_vec();
+        bogus[4..8].copy_from_slice(&0x0100_0000u32.to_be_bytes());
+        let input = [bogus.as_slice(), rpc_message].concat();
+        assert_eq!(statistic.collect(&input), input.len());
+        assert_eq!(statistic.services[&259][&32773], 3);
+    }
+
     #[test]
     fn service_lookup() {
         let xml = r#"
@@ -0,0 +1,549 @@
+//! # Syslog messages (RFC 3164 and RFC 5424)
+//!
+//! Messages are delivered as columns. Lines which aren't syslog messages are passed
+//! through unchanged.
+use crate::{Error, LogMessage, ParseYield, Parser, COLUMN_SEP, LINE_SEP};
+use memchr::memchr2;
+use regex::bytes::Regex;
+use serde::Serialize;
+use std::{fmt, io::Write, str};
+
+pub const COLUMNS: &[&str] = &[
+    "Facility",
+    "Severity",
+    "Timestamp",
+    "Hostname",
+    "App",
+    "ProcId",
+    "MsgId",
+    "StructuredData",
+    "Message",
+];
+
+lazy_static::lazy_static! {
+    /// Length prefix of the octet counting framing (RFC 6587)
+    static ref OCTET_COUNT: Regex = Regex::new(r"^([1-9]\d{0,8}) <").unwrap();
+}
+
+const FACILITIES: &[&str] = &[
+    "kern",
+    "user",
+    "mail",
+    "daemon",
+    "auth",
+    "syslog",
+    "lpr",
+    "news",
+    "uucp",
+    "cron",
+    "authpriv",
+    "ftp",
+    "ntp",
+    "security",
+    "console",
+    "solaris-cron",
+    "local0",
+    "local1",
+    "local2",
+    "local3",
+    "local4",
+    "local5",
+    "local6",
+    "local7",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Severity {
+    Emergency,
+    Alert,
+    Critical,
+    Error,
+    Warning,
+    Notice,
+    Informational,
+    Debug,
+}
+
+impl Severity {
+    fn from_pri(pri: u8) -> Self {
+        match pri & 0x07 {
+            0 => Severity::Emergency,
+            1 => Severity::Alert,
+            2 => Severity::Critical,
+            3 => Severity::Error,
+            4 => Severity::Warning,
+            5 => Severity::Notice,
+            6 => Severity::Informational,
+            _ => Severity::Debug,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct SyslogHeader {
+    pub facility: u8,
+    pub severity: Severity,
+    pub timestamp: Option<String>,
+    pub hostname: Option<String>,
+    pub app_name: Option<String>,
+    pub proc_id: Option<String>,
+    pub msg_id: Option<String>,
+    pub structured_data: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct SyslogMessage {
+    raw: String,
+    /// `None` if the content isn't a syslog message
+    header: Option<SyslogHeader>,
+    msg: String,
+}
+
+impl SyslogMessage {
+    pub fn header(&self) -> Option<&SyslogHeader> {
+        self.header.as_ref()
+    }
+
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    fn from_raw(raw: String) -> Self {
+        match parse_message(&raw) {
+            Some((header, msg)) => {
+                let msg = msg.to_owned();
+                SyslogMessage {
+                    raw,
+                    header: Some(header),
+                    msg,
+                }
+            }
+            None => SyslogMessage {
+                msg: raw.clone(),
+                raw,
+                header: None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for SyslogMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(header) = self.header.as_ref() else {
+            return write!(f, "{}", self.raw);
+        };
+        let column = |value: &Option<String>| value.as_deref().unwrap_or("").to_owned();
+        let facility = FACILITIES
+            .get(header.facility as usize)
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| header.facility.to_string());
+        write!(
+            f,
+            "{facility}{COLUMN_SEP}{:?}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}",
+            header.severity,
+            column(&header.timestamp),
+            column(&header.hostname),
+            column(&header.app_name),
+            column(&header.proc_id),
+            column(&header.msg_id),
+            column(&header.structured_data),
+            self.msg.replace('\n', LINE_SEP),
+        )
+    }
+}
+
+impl LogMessage for SyslogMessage {
+    fn to_writer<W: Write>(&self, writer: &mut W) -> Result<usize, std::io::Error> {
+        let len = self.raw.len();
+        writer.write_all(self.raw.as_bytes())?;
+        Ok(len)
+    }
+}
+
+/// Parser for syslog messages
+///
+/// Messages are separated by newlines or NUL bytes or by the octet counting framing of
+/// RFC 6587.
+#[derive(Default)]
+pub struct SyslogParser {
+    datagrams: bool,
+}
+
+impl SyslogParser {
+    /// Creates a parser for sources delivering messages in datagrams, which load one
+    /// datagram at a time. The input is parsed completely, so the end of a datagram
+    /// terminates its last message and the next datagram isn't appended to it.
+    pub fn datagrams() -> Self {
+        SyslogParser { datagrams: true }
+    }
+}
+
+/// Finds the next message in `input`.
+/// Returns the range of the message content together with the consumed bytes count.
+fn next_frame(input: &[u8], datagrams: bool) -> Result<(usize, usize, usize), Error> {
+    if let Some(caps) = OCTET_COUNT.captures(input) {
+        let prefix = caps.get(1).map(|m| m.end() + 1).unwrap_or(0);
+        let len = str::from_utf8(&caps[1])
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(0);
+        if input.len() < prefix + len {
+            // a message is never continued by the next datagram
+            return if datagrams {
+                Ok((prefix, input.len(), input.len()))
+            } else {
+                Err(Error::Incomplete)
+            };
+        }
+        return Ok((prefix, prefix + len, prefix + len));
+    }
+    match memchr2(b'\n', b'\0', input) {
+        Some(end) => {
+            let content_end = if end > 0 && input[end - 1] == b'\r' {
+                end - 1
+            } else {
+                end
+            };
+            Ok((0, content_end, end + 1))
+        }
+        None if datagrams && !input.is_empty() => Ok((0, input.len(), input.len())),
+        None => Err(Error::Incomplete),
+    }
+}
+
+/// Parses the next message or the separators in front of it
+fn next_item(
+    input: &[u8],
+    datagrams: bool,
+) -> Result<(usize, Option<ParseYield<SyslogMessage>>), Error> {
+    // empty lines and separators between messages
+    let separators = input
+        .iter()
+        .take_while(|b| matches!(b, b'\n' | b'\r' | b'\0'))
+        .count();
+    if separators > 0 {
+        return Ok((separators, None));
+    }
+    let (start, end, consumed) = next_frame(input, datagrams)?;
+    let raw = String::from_utf8_lossy(&input[start..end]).into_owned();
+    Ok((consumed, Some(SyslogMessage::from_raw(raw).into())))
+}
+
+impl Parser<SyslogMessage> for SyslogParser {
+    fn parse(
+        &mut self,
+        input: &[u8],
+        _timestamp: Option<u64>,
+    ) -> Result<impl Iterator<Item = (usize, Option<ParseYield<SyslogMessage>>)>, Error> {
+        if !self.datagrams {
+            return next_item(input, false).map(|item| vec![item].into_iter());
+        }
+        // all messages of the datagram are delivered before the next one is loaded
+        let mut items = vec![next_item(input, true)?];
+        let mut pos = items[0].0;
+        while pos < input.len() {
+            let item = next_item(&input[pos..], true)?;
+            pos += item.0;
+            items.push(item);
+        }
+        Ok(items.into_iter())
+    }
+
+    fn parse_rest(&mut self, input: &[u8]) -> Option<(usize, ParseYield<SyslogMessage>)> {
+        let content = String::from_utf8_lossy(input);
+        let content = content.trim_end_matches(['\n', '\r', '\0']);
+        if content.is_empty() {
+            return None;
+        }
+        Some((
+            input.len(),
+            SyslogMessage::from_raw(content.to_owned()).into(),
+        ))
+    }
+}
+
+/// Splits `s` at the first space
+fn next_field(s: &str) -> Option<(&str, &str)> {
+    s.split_once(' ')
+}
+
+fn nil_or(value: &str) -> Option<String> {
+    (value != "-").then(|| value.to_owned())
+}
+
+fn parse_pri(s: &str) -> Option<(u8, &str)> {
+    let rest = s.strip_prefix('<')?;
+    let end = rest.find('>')?;
+    if end == 0 || end > 3 || !rest[..end].bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let pri = rest[..end].parse::<u8>().ok().filter(|pri| *pri <= 191)?;
+    Some((pri, &rest[end + 1..]))
+}
+
+/// Parses the header of a message; returns the header together with the message text
+pub fn parse_message(s: &str) -> Option<(SyslogHeader, &str)> {
+    let (pri, rest) = parse_pri(s)?;
+    let mut header = SyslogHeader {
+        facility: pri >> 3,
+        severity: Severity::from_pri(pri),
+        timestamp: None,
+        hostname: None,
+        app_name: None,
+        proc_id: None,
+        msg_id: None,
+        structured_data: None,
+    };
+    let msg = match next_field(rest) {
+        Some((version, rest))
+            if !version.is_empty()
+                && version.len() <= 2
+                && version.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            parse_rfc5424(rest, &mut header)?
+        }
+        _ => parse_rfc3164(rest, &mut header),
+    };
+    Some((header, msg))
+}
+
+fn parse_rfc5424<'a>(s: &'a str, header: &mut SyslogHeader) -> Option<&'a str> {
+    let (timestamp, rest) = next_field(s)?;
+    let (hostname, rest) = next_field(rest)?;
+    let (app_name, rest) = next_field(rest)?;
+    let (proc_id, rest) = next_field(rest)?;
+    let (msg_id, rest) = next_field(rest)?;
+    let (structured_data, msg) = split_structured_data(rest)?;
+    header.timestamp = nil_or(timestamp);
+    header.hostname = nil_or(hostname);
+    header.app_name = nil_or(app_name);
+    header.proc_id = nil_or(proc_id);
+    header.msg_id = nil_or(msg_id);
+    header.structured_data = nil_or(structured_data);
+    let msg = msg.strip_prefix(' ').unwrap_or(msg);
+    Some(msg.strip_prefix('\u{feff}').unwrap_or(msg))
+}
+
+/// Splits the structured data (`-` or a sequence of `[id param="value"]` elements) from the message
+fn split_structured_data(s: &str) -> Option<(&str, &str)> {
+    if let Some(rest) = s.strip_prefix('-') {
+        return Some((&s[..1], rest));
+    }
+    let mut in_element = false;
+    let mut in_value = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_value => escaped = true,
+            '"' if in_element => in_value = !in_value,
+            '[' if !in_element => in_element = true,
+            ']' if in_element && !in_value => in_element = false,
+            _ if !in_element => return (i > 0).then(|| (&s[..i], &s[i..])),
+            _ => {}
+        }
+    }
+    (!in_element && !s.is_empty()).then_some((s, ""))
+}
+
+fn is_rfc3164_timestamp(s: &str) -> bool {
+    const MONTHS: &[&str] = &[
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let b = s.as_bytes();
+    b.len() == 15
+        && MONTHS.contains(&&s[..3])
+        && b[3] == b' '
+        && (b[4] == b' ' || b[4].is_ascii_digit())
+        && b[5].is_ascii_digit()
+        && b[6] == b' '
+        && b[9] == b':'
+        && b[12] == b':'
+        && [7, 8, 10, 11, 13, 14]
+            .iter()
+            .all(|i| b[*i].is_ascii_digit())
+}
+
+fn parse_rfc3164<'a>(s: &'a str, header: &mut SyslogHeader) -> &'a str {
+    let Some(rest) = s
+        .get(..15)
+        .filter(|timestamp| is_rfc3164_timestamp(timestamp))
+        .map(|timestamp| {
+            header.timestamp = Some(timestamp.to_owned());
+            s[15..].strip_prefix(' ').unwrap_or(&s[15..])
+        })
+    else {
+        // without a valid timestamp the whole content is the message
+        return s;
+    };
+    // messages from local sockets don't contain the hostname
+    let rest = match next_field(rest) {
+        Some((hostname, rest)) if !hostname.ends_with(':') && !hostname.contains('[') => {
+            header.hostname = Some(hostname.to_owned());
+            rest
+        }
+        _ => rest,
+    };
+    let tag_end = rest.find([':', '[', ' ']).unwrap_or(rest.len());
+    let (tag, mut msg) = rest.split_at(tag_end);
+    if let Some(pid) = msg
+        .strip_prefix('[')
+        .and_then(|m| m.split_once(']'))
+        .map(|(pid, m)| {
+            msg = m;
+            pid
+        })
+    {
+        header.proc_id = Some(pid.to_owned());
+    }
+    match msg.strip_prefix(':') {
+        Some(m) if !tag.is_empty() => {
+            header.app_name = Some(tag.to_owned());
+            m.strip_prefix(' ').unwrap_or(m)
+        }
+        _ if header.proc_id.is_some() => {
+            header.app_name = Some(tag.to_owned());
+            msg.strip_prefix(' ').unwrap_or(msg)
+        }
+        _ => rest,
+    }
+}
+
+#[cfg(test)]
+fn parse_all(parser: &mut SyslogParser, mut input: &[u8]) -> Vec<String> {
+    let mut messages = vec![];
+    loop {
+        match parser.parse(input, None).map(Iterator::collect::<Vec<_>>) {
+            Ok(items) => {
+                for (consumed, msg) in items {
+                    if let Some(ParseYield::Message(msg)) = msg {
+                        messages.push(msg.to_string());
+                    }
+                    input = &input[consumed..];
+                }
+            }
+            Err(Error::Incomplete) => {
+                if let Some((_, ParseYield::Message(msg))) = parser.parse_rest(input) {
+                    messages.push(msg.to_string());
+                }
+                break;
+            }
+            Err(e) => panic!("unexpected error: {e}"),
+        }
+    }
+    messages
+}
+
+#[test]
+fn test_syslog_rfc5424() {
+    let (header, msg) = parse_message(
+        r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Appl\]ication"][x@1 a="b"] BOMAn application event"#,
+    )
+    .unwrap();
+    assert_eq!(header.facility, 20);
+    assert_eq!(header.severity, Severity::Notice);
+    assert_eq!(
+        header.timestamp.as_deref(),
+        Some("2003-10-11T22:14:15.003Z")
+    );
+    assert_eq!(header.hostname.as_deref(), Some("mymachine.example.com"));
+    assert_eq!(header.app_name.as_deref(), Some("evntslog"));
+    assert_eq!(header.proc_id, None);
+    assert_eq!(header.msg_id.as_deref(), Some("ID47"));
+    assert_eq!(
+        header.structured_data.as_deref(),
+        Some(r#"[exampleSDID@32473 iut="3" eventSource="Appl\]ication"][x@1 a="b"]"#)
+    );
+    assert_eq!(msg, "BOMAn application event");
+
+    let (header, msg) = parse_message("<34>1 2003-10-11T22:14:15Z host su 42 - -").unwrap();
+    assert_eq!(header.proc_id.as_deref(), Some("42"));
+    assert_eq!(header.structured_data, None);
+    assert_eq!(msg, "");
+}
+
+#[test]
+fn test_syslog_rfc3164() {
+    let (header, msg) =
+        parse_message("<34>Oct 11 22:14:15 mymachine su[123]: 'su root' failed").unwrap();
+    assert_eq!(header.facility, 4);
+    assert_eq!(header.severity, Severity::Critical);
+    assert_eq!(header.timestamp.as_deref(), Some("Oct 11 22:14:15"));
+    assert_eq!(header.hostname.as_deref(), Some("mymachine"));
+    assert_eq!(header.app_name.as_deref(), Some("su"));
+    assert_eq!(header.proc_id.as_deref(), Some("123"));
+    assert_eq!(msg, "'su root' failed");
+
+    let (header, msg) = parse_message("<13>Feb  5 17:32:18 kernel: usb 1-1: new device").unwrap();
+    assert_eq!(header.hostname, None);
+    assert_eq!(header.app_name.as_deref(), Some("kernel"));
+    assert_eq!(msg, "usb 1-1: new device");
+
+    let (header, msg) = parse_message("<13>no header at all").unwrap();
+    assert_eq!(header.timestamp, None);
+    assert_eq!(msg, "no header at all");
+
+    assert!(parse_message("<192>Oct 11 22:14:15 host app: x").is_none());
+    assert!(parse_message("plain line").is_none());
+}
+
+#[test]
+fn test_syslog_parser_framing() {
+    let input = b"<34>Oct 11 22:14:15 host su: one\r\n\n<13>1 - - app - - - two<13>1 - - app - - - three\nplain line\n32 <13>1 - - app - - - with\nnewline\0<13>Oct 11 22:14:16 host app: last";
+    let mut parser = SyslogParser::default();
+    assert_eq!(
+        parse_all(&mut parser, input),
+        vec![
+            "auth\u{4}Critical\u{4}Oct 11 22:14:15\u{4}host\u{4}su\u{4}\u{4}\u{4}\u{4}one",
+            "user\u{4}Notice\u{4}\u{4}\u{4}app\u{4}\u{4}\u{4}\u{4}two<13>1 - - app - - - three",
+            "plain line",
+            "user\u{4}Notice\u{4}\u{4}\u{4}app\u{4}\u{4}\u{4}\u{4}with\u{6}newline",
+            "user\u{4}Notice\u{4}Oct 11 22:14:16\u{4}host\u{4}app\u{4}\u{4}\u{4}\u{4}last",
+        ]
+    );
+    // a datagram is a complete message even without a trailing newline
+    let mut parser = SyslogParser::datagrams();
+    let (consumed, msg) = parser
+        .parse(b"<13>1 - - app - - - datagram", None)
+        .unwrap()
+        .next()
+        .unwrap();
+    assert_eq!(consumed, 28);
+    assert!(matches!(msg, Some(ParseYield::Message(_))));
+    // all messages of a datagram are parsed at once, the next datagram isn't appended
+    let items = parser
+        .parse(
+            b"<13>1 - - app - - - two <13>1 x\nplain text\n20 <13>1 - - app",
+            None,
+        )
+        .unwrap()
+        .map(|(consumed, msg)| {
+            (
+                consumed,
+                msg.map(|msg| match msg {
+                    ParseYield::Message(msg) => msg.to_string(),
+                    _ => panic!("unexpected attachment"),
+                }),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        items,
+        vec![
+            (
+                32,
+                Some(String::from(
+                    "user\u{4}Notice\u{4}\u{4}\u{4}app\u{4}\u{4}\u{4}\u{4}two <13>1 x"
+                ))
+            ),
+            (11, Some(String::from("plain text"))),
+            // an incomplete message isn't continued by the next datagram
+            (16, Some(String::from("<13>1 - - app"))),
+        ]
+    );
+    // octet counting waits for the complete message
+    assert!(matches!(
+        SyslogParser::default().parse(b"40 <13>1 - - app", None),
+        Err(Error::Incomplete)
+    ));
+}
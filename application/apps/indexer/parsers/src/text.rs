@@ -1,12 +1,135 @@
-use crate::{Error, LogMessage, ParseYield, Parser};
-use serde::Serialize;
-use std::{fmt, io::Write, iter};
+use crate::{Error, LogMessage, ParseYield, Parser, LINE_SEP};
+use encoding_rs::{Encoding, SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, fmt, io::Write, iter};
 
-pub struct StringTokenizer {}
+/// Character encoding of a text source
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextEncoding {
+    /// Detected by the byte order mark; UTF-8 is used if there is none
+    #[default]
+    Auto,
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    ShiftJis,
+    /// ISO-8859-1, decoded as its superset windows-1252
+    Latin1,
+}
+
+impl TextEncoding {
+    /// Detects the encoding by the byte order mark at the beginning of `input`.
+    /// Returns the encoding together with the length of the byte order mark.
+    pub fn from_bom(input: &[u8]) -> Option<(TextEncoding, usize)> {
+        Encoding::for_bom(input).map(|(encoding, bom_len)| {
+            let encoding = if encoding == UTF_16LE {
+                TextEncoding::Utf16Le
+            } else if encoding == UTF_16BE {
+                TextEncoding::Utf16Be
+            } else {
+                TextEncoding::Utf8
+            };
+            (encoding, bom_len)
+        })
+    }
+
+    /// Resolves `Auto` using the first bytes of a source
+    pub fn resolve(self, head: &[u8]) -> TextEncoding {
+        match self {
+            TextEncoding::Auto => TextEncoding::from_bom(head)
+                .map(|(encoding, _)| encoding)
+                .unwrap_or(TextEncoding::Utf8),
+            encoding => encoding,
+        }
+    }
+
+    fn encoding(&self) -> &'static Encoding {
+        match self {
+            TextEncoding::Auto | TextEncoding::Utf8 => UTF_8,
+            TextEncoding::Utf16Le => UTF_16LE,
+            TextEncoding::Utf16Be => UTF_16BE,
+            TextEncoding::ShiftJis => SHIFT_JIS,
+            TextEncoding::Latin1 => WINDOWS_1252,
+        }
+    }
+
+    /// Finds the end of the first line in `input`.
+    /// Returns the length of the line content together with the length including the newline.
+    fn line_end(&self, input: &[u8]) -> Option<(usize, usize)> {
+        use memchr::memchr;
+        let newline_unit = |unit: [u8; 2]| {
+            input
+                .chunks_exact(2)
+                .position(|c| c == unit)
+                .map(|i| (i * 2, i * 2 + 2))
+        };
+        match self {
+            TextEncoding::Utf16Le => newline_unit([b'\n', 0]),
+            TextEncoding::Utf16Be => newline_unit([0, b'\n']),
+            // in all other supported encodings a newline byte is always a newline
+            _ => memchr(b'\n', input).map(|pos| (pos, pos + 1)),
+        }
+    }
+
+    fn decode<'a>(&self, input: &'a [u8]) -> Cow<'a, str> {
+        match self {
+            TextEncoding::Auto | TextEncoding::Utf8 => String::from_utf8_lossy(input),
+            encoding => encoding.encoding().decode_without_bom_handling(input).0,
+        }
+    }
+}
+
+/// Splits text into lines and decodes them into UTF-8
+///
+/// If a record start regex is set, lines not matching it are joined to the line before,
+/// so e.g. stack traces end up in one message.
+#[derive(Default)]
+pub struct StringTokenizer {
+    encoding: TextEncoding,
+    bom_checked: bool,
+    record_start: Option<Regex>,
+}
+
+impl StringTokenizer {
+    pub fn new(encoding: TextEncoding) -> Self {
+        Self {
+            encoding,
+            bom_checked: false,
+            record_start: None,
+        }
+    }
+
+    /// Groups lines into records, each record begins with a line matching `pattern`.
+    pub fn with_record_start(mut self, pattern: &str) -> Result<Self, Error> {
+        self.record_start = Some(
+            Regex::new(pattern)
+                .map_err(|e| Error::Parse(format!("invalid record start regex: {e}")))?,
+        );
+        Ok(self)
+    }
+
+    /// Collects the lines of the record at the beginning of `input`.
+    /// Returns the consumed bytes together with the lines or `None` if the end of
+    /// the record isn't part of `input`.
+    fn next_record(&self, input: &[u8], record_start: &Regex) -> Option<(usize, Vec<String>)> {
+        let (first_len, mut pos) = self.encoding.line_end(input)?;
+        let mut lines = vec![self.encoding.decode(&input[..first_len]).to_string()];
+        loop {
+            let (len, consumed) = self.encoding.line_end(&input[pos..])?;
+            let line = self.encoding.decode(&input[pos..pos + len]);
+            if record_start.is_match(&line) {
+                return Some((pos, lines));
+            }
+            lines.push(line.to_string());
+            pos += consumed;
+        }
+    }
+}
 
 #[derive(Debug, PartialEq, Eq, Serialize)]
 pub struct StringMessage {
-    content: String,
+    pub(crate) content: String,
 }
 
 impl fmt::Display for StringMessage {
@@ -32,17 +155,34 @@ where
         input: &[u8],
         _timestamp: Option<u64>,
     ) -> Result<impl Iterator<Item = (usize, Option<ParseYield<StringMessage>>)>, Error> {
-        // TODO: support non-utf8 encodings
-        use memchr::memchr;
         if input.is_empty() {
             return Ok(iter::once((input.len(), None)));
         }
-        let item = if let Some(msg_size) = memchr(b'\n', input) {
-            let content = String::from_utf8_lossy(&input[..msg_size]);
+        if !self.bom_checked {
+            self.bom_checked = true;
+            if let Some((encoding, bom_len)) = TextEncoding::from_bom(input) {
+                if self.encoding == TextEncoding::Auto {
+                    self.encoding = encoding;
+                }
+                // the byte order mark is not part of the content
+                return Ok(iter::once((bom_len, None)));
+            }
+        }
+        if let Some(record_start) = self.record_start.as_ref() {
+            let (consumed, lines) = self
+                .next_record(input, record_start)
+                .ok_or(Error::Incomplete)?;
+            let string_msg = StringMessage {
+                content: lines.join(LINE_SEP),
+            };
+            return Ok(iter::once((consumed, Some(string_msg.into()))));
+        }
+        let item = if let Some((msg_size, consumed)) = self.encoding.line_end(input) {
+            let content = self.encoding.decode(&input[..msg_size]);
             let string_msg = StringMessage {
                 content: content.to_string(),
             };
-            (msg_size + 1, Some(string_msg.into()))
+            (consumed, Some(string_msg.into()))
         } else {
             (
                 input.len(),
@@ -54,11 +194,36 @@ where
 
         Ok(iter::once(item))
     }
+
+    fn parse_rest(&mut self, input: &[u8]) -> Option<(usize, ParseYield<StringMessage>)> {
+        self.record_start.as_ref()?;
+        if input.is_empty() {
+            return None;
+        }
+        // the record ends with the available input
+        let mut lines = vec![];
+        let mut pos = 0;
+        while pos < input.len() {
+            let (len, consumed) = self
+                .encoding
+                .line_end(&input[pos..])
+                .unwrap_or((input.len() - pos, input.len() - pos));
+            lines.push(self.encoding.decode(&input[pos..pos + len]).to_string());
+            pos += consumed;
+        }
+        Some((
+            input.len(),
+            StringMessage {
+                content: lines.join(LINE_SEP),
+            }
+            .into(),
+        ))
+    }
 }
 
 #[test]
 fn test_string_tokenizer() {
-    let mut parser = StringTokenizer {};
+    let mut parser = StringTokenizer::default();
     let content = b"hello\nworld\n";
     let (consumed_1, first_msg) = parser.parse(content, None).unwrap().next().unwrap();
     match first_msg {
@@ -80,3 +245,99 @@ fn test_string_tokenizer() {
     );
     assert!(third_msg.is_none());
 }
+
+#[cfg(test)]
+fn parse_all(parser: &mut StringTokenizer, mut input: &[u8]) -> Vec<String> {
+    let mut lines = vec![];
+    while !input.is_empty() {
+        let (consumed, msg) = parser.parse(input, None).unwrap().next().unwrap();
+        if let Some(ParseYield::Message(StringMessage { content })) = msg {
+            lines.push(content);
+        }
+        input = &input[consumed..];
+    }
+    lines
+}
+
+#[test]
+fn test_string_tokenizer_utf16_with_bom() {
+    let mut input: Vec<u8> = vec![0xFF, 0xFE];
+    for c in "grüße\n日本\n".encode_utf16() {
+        input.extend_from_slice(&c.to_le_bytes());
+    }
+    // the newline unit must be found at even offsets only
+    for c in "\u{0a0a}x\n".encode_utf16() {
+        input.extend_from_slice(&c.to_le_bytes());
+    }
+    let mut parser = StringTokenizer::default();
+    assert_eq!(
+        parse_all(&mut parser, &input),
+        vec!["grüße", "日本", "\u{0a0a}x"]
+    );
+
+    let mut input: Vec<u8> = vec![0xFE, 0xFF];
+    for c in "big\nendian\n".encode_utf16() {
+        input.extend_from_slice(&c.to_be_bytes());
+    }
+    let mut parser = StringTokenizer::default();
+    assert_eq!(parse_all(&mut parser, &input), vec!["big", "endian"]);
+}
+
+#[test]
+fn test_string_tokenizer_legacy_encodings() {
+    // "ログ\n" in Shift-JIS
+    let mut parser = StringTokenizer::new(TextEncoding::ShiftJis);
+    assert_eq!(
+        parse_all(&mut parser, &[0x83, 0x8D, 0x83, 0x4F, b'\n']),
+        vec!["ログ"]
+    );
+    // "Größe\n" in Latin-1
+    let mut parser = StringTokenizer::new(TextEncoding::Latin1);
+    assert_eq!(
+        parse_all(&mut parser, &[b'G', b'r', 0xF6, 0xDF, b'e', b'\n']),
+        vec!["Größe"]
+    );
+    // without a BOM, auto detection falls back to UTF-8
+    let mut parser = StringTokenizer::default();
+    assert_eq!(parse_all(&mut parser, "Größe\n".as_bytes()), vec!["Größe"]);
+    assert_eq!(TextEncoding::Auto.resolve(b"abc"), TextEncoding::Utf8);
+    assert_eq!(
+        TextEncoding::Auto.resolve(&[0xFF, 0xFE, b'a', 0]),
+        TextEncoding::Utf16Le
+    );
+}
+
+#[test]
+fn test_string_tokenizer_records() {
+    let mut parser = StringTokenizer::default()
+        .with_record_start(r"^\d{4}-\d{2}-\d{2} ")
+        .unwrap();
+    let input = b"2024-01-01 ERROR failed\njava.lang.Exception: boom\n\tat Main.main(Main.java:3)\n2024-01-01 INFO next\n2024-01-01 INFO last\ncontinued";
+    let (consumed, msg) = parser.parse(input, None).unwrap().next().unwrap();
+    match msg {
+        Some(ParseYield::Message(StringMessage { content })) => assert_eq!(
+            content,
+            "2024-01-01 ERROR failed\u{0006}java.lang.Exception: boom\u{0006}\tat Main.main(Main.java:3)"
+        ),
+        _ => panic!("record did not match"),
+    }
+    let rest = &input[consumed..];
+    let (consumed, msg) = parser.parse(rest, None).unwrap().next().unwrap();
+    match msg {
+        Some(ParseYield::Message(StringMessage { content })) => {
+            assert_eq!(content, "2024-01-01 INFO next")
+        }
+        _ => panic!("second record did not match"),
+    }
+    // the end of the last record isn't known before the source has no more data
+    let rest = &rest[consumed..];
+    assert!(matches!(parser.parse(rest, None), Err(Error::Incomplete)));
+    match parser.parse_rest(rest) {
+        Some((consumed, ParseYield::Message(StringMessage { content }))) => {
+            assert_eq!(consumed, rest.len());
+            assert_eq!(content, "2024-01-01 INFO last\u{0006}continued");
+        }
+        _ => panic!("last record did not match"),
+    }
+    assert!(StringTokenizer::default().with_record_start("(").is_err());
+}
@@ -18,6 +18,7 @@ extern crate log;
 pub mod export;
 pub mod grabber;
 pub mod map;
+pub mod parse;
 pub mod processor;
 pub mod search;
 pub mod text_source;
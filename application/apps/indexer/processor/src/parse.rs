@@ -0,0 +1,354 @@
+// Copyright (c) 2019 E.S.R.Labs. All rights reserved.
+//
+// NOTICE:  All information contained herein is, and remains
+// the property of E.S.R.Labs and its suppliers, if any.
+// The intellectual and technical concepts contained herein are
+// proprietary to E.S.R.Labs and its suppliers and may be covered
+// by German and Foreign Patents, patents in process, and are protected
+// by trade secret or copyright law.
+// Dissemination of this information or reproduction of this material
+// is strictly forbidden unless prior written permission is obtained
+// from E.S.R.Labs.
+use chrono::{Datelike, NaiveDate, Utc};
+use indexer_base::{timedline::TimedLine, utils};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum DateParseError {
+    #[error("Format string not supported: {0}")]
+    UnsupportedFormat(String),
+    #[error("No timestamp found")]
+    NoTimestamp,
+    #[error("Invalid date: {0}")]
+    InvalidDate(String),
+}
+
+const YEAR: &str = "year";
+const SHORT_YEAR: &str = "short_year";
+const MONTH: &str = "month";
+const MONTH_NAME: &str = "month_name";
+const DAY: &str = "day";
+const HOUR: &str = "hour";
+const MINUTE: &str = "minute";
+const SECOND: &str = "second";
+const FRACTION: &str = "fraction";
+const TIMEZONE: &str = "timezone";
+const EPOCH: &str = "epoch";
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// Placeholders which can be used in a format string, each one together with the
+/// regex fragment it is translated into. Longer placeholders have to come first
+/// so that e.g. `MMM` is not consumed as `MM` followed by a literal `M`.
+const PLACEHOLDERS: [(&str, &str); 11] = [
+    ("EPOCH", r"\b(?P<epoch>\d{13})\b"),
+    ("YYYY", r"(?P<year>\d{4})"),
+    ("TZD", r"(?P<timezone>Z|[+-]\d{2}:?\d{2})"),
+    ("MMM", r"(?P<month_name>[A-Za-z]{3})"),
+    ("YY", r"(?P<short_year>\d{2})"),
+    ("MM", r"(?P<month>\d{2})"),
+    ("DD", r"(?P<day>\d{1,2})"),
+    ("hh", r"(?P<hour>\d{2})"),
+    ("mm", r"(?P<minute>\d{2})"),
+    ("ss", r"(?P<second>\d{2})"),
+    ("s", r"(?P<fraction>\d+)"),
+];
+
+/// Translates a chipmunk format string (e.g. `YYYY-MM-DD hh:mm:ss.s TZD`) into a regex
+/// which captures all parts of the timestamp.
+///
+/// Supported placeholders are `YYYY`, `YY`, `MM`, `MMM` (abbreviated month name), `DD`,
+/// `hh`, `mm`, `ss`, `s` (fraction of a second), `TZD` (`Z`, `+hhmm` or `+hh:mm`) and
+/// `EPOCH` (milliseconds since the unix epoch).
+/// Whitespace matches any amount of whitespace (none at all in front of `TZD`), every
+/// other character is matched literally.
+pub fn lookup_regex_for_format_str(format: &str) -> Result<Regex, DateParseError> {
+    let mut pattern = String::new();
+    let mut rest = format;
+    let mut used: Vec<&str> = vec![];
+    'outer: while !rest.is_empty() {
+        for (placeholder, fragment) in PLACEHOLDERS.iter() {
+            if let Some(tail) = rest.strip_prefix(placeholder) {
+                if used.contains(placeholder) {
+                    return Err(DateParseError::UnsupportedFormat(format!(
+                        "placeholder {placeholder} is used more than once in \"{format}\""
+                    )));
+                }
+                used.push(placeholder);
+                // timezones are often appended without any separator (`12:31:25.463+01:00`)
+                if *placeholder == "TZD" && pattern.ends_with(r"\s+") {
+                    pattern.truncate(pattern.len() - 1);
+                    pattern.push('*');
+                }
+                pattern.push_str(fragment);
+                rest = tail;
+                continue 'outer;
+            }
+        }
+        let mut chars = rest.chars();
+        if let Some(c) = chars.next() {
+            if !c.is_whitespace() {
+                pattern.push_str(&regex::escape(&c.to_string()));
+            } else if !pattern.ends_with(r"\s+") {
+                pattern.push_str(r"\s+");
+            }
+        }
+        rest = chars.as_str();
+    }
+    let has_date = (used.contains(&"MM") || used.contains(&"MMM")) && used.contains(&"DD");
+    let has_time = used.contains(&"hh") && used.contains(&"mm");
+    if used.contains(&"EPOCH") {
+        if used.len() > 1 {
+            return Err(DateParseError::UnsupportedFormat(format!(
+                "\"{format}\" cannot combine EPOCH with other placeholders"
+            )));
+        }
+    } else if !has_date || !has_time {
+        return Err(DateParseError::UnsupportedFormat(format!(
+            "\"{format}\" needs at least month, day, hours and minutes"
+        )));
+    }
+    Regex::new(&pattern).map_err(|e| DateParseError::UnsupportedFormat(e.to_string()))
+}
+
+/// Extracts the posix timestamp (in ms) of the first timestamp in `line` which
+/// matches `regex` (created with [`lookup_regex_for_format_str`]).
+///
+/// If the format does not contain a year, `year` is used or, if not present, the current
+/// year. The returned flag tells whether the year had to be guessed like this.
+/// `time_offset` (in ms) is added to the resulting timestamp.
+pub fn extract_posix_timestamp(
+    line: &str,
+    regex: &Regex,
+    year: Option<i32>,
+    time_offset: Option<i64>,
+) -> Result<(i64, bool), DateParseError> {
+    let caps = regex.captures(line).ok_or(DateParseError::NoTimestamp)?;
+    if let Some(epoch) = caps.name(EPOCH) {
+        let timestamp = epoch
+            .as_str()
+            .parse::<i64>()
+            .map_err(|e| DateParseError::InvalidDate(format!("{EPOCH}: {e}")))?;
+        return Ok((timestamp + time_offset.unwrap_or(0), false));
+    }
+    let number = |name: &str| -> Result<Option<u32>, DateParseError> {
+        caps.name(name)
+            .map(|m| {
+                m.as_str()
+                    .parse::<u32>()
+                    .map_err(|e| DateParseError::InvalidDate(format!("{name}: {e}")))
+            })
+            .transpose()
+    };
+    let (year, year_was_missing) = match (number(YEAR)?, number(SHORT_YEAR)?) {
+        (Some(y), _) => (y as i32, false),
+        (None, Some(y)) => (2000 + y as i32, false),
+        (None, None) => match year {
+            Some(y) => (y, false),
+            None => (Utc::now().year(), true),
+        },
+    };
+    let month = match (number(MONTH)?, caps.name(MONTH_NAME)) {
+        (Some(m), _) => m,
+        (None, Some(name)) => {
+            let name = name.as_str().to_lowercase();
+            MONTH_NAMES
+                .iter()
+                .position(|m| *m == name)
+                .map(|i| i as u32 + 1)
+                .ok_or_else(|| DateParseError::InvalidDate(format!("unknown month {name}")))?
+        }
+        (None, None) => return Err(DateParseError::InvalidDate("month missing".to_owned())),
+    };
+    let millis = match caps.name(FRACTION) {
+        // only the first 3 digits are relevant for a ms resolution
+        Some(fraction) => {
+            let digits: String = fraction.as_str().chars().take(3).collect();
+            digits
+                .parse::<u32>()
+                .map_err(|e| DateParseError::InvalidDate(format!("{FRACTION}: {e}")))?
+                * 10u32.pow(3 - digits.len() as u32)
+        }
+        None => 0,
+    };
+    let (day, hour, minute, second) = (
+        number(DAY)?.unwrap_or(1),
+        number(HOUR)?.unwrap_or(0),
+        number(MINUTE)?.unwrap_or(0),
+        number(SECOND)?.unwrap_or(0),
+    );
+    let date_time = NaiveDate::from_ymd_opt(year, month, day)
+        .and_then(|date| date.and_hms_milli_opt(hour, minute, second, millis))
+        .ok_or_else(|| DateParseError::InvalidDate(caps[0].to_owned()))?;
+    let tz_offset_ms = match caps.name(TIMEZONE) {
+        Some(tz) => parse_timezone_offset(tz.as_str())?,
+        None => 0,
+    };
+    Ok((
+        date_time.and_utc().timestamp_millis() - tz_offset_ms + time_offset.unwrap_or(0),
+        year_was_missing,
+    ))
+}
+
+/// Parses a timezone designator (`Z`, `+hhmm` or `+hh:mm`) into an offset in ms
+fn parse_timezone_offset(tz: &str) -> Result<i64, DateParseError> {
+    if tz == "Z" {
+        return Ok(0);
+    }
+    let sign = if tz.starts_with('-') { -1 } else { 1 };
+    let digits: String = tz.chars().filter(|c| c.is_ascii_digit()).collect();
+    if digits.len() != 4 {
+        return Err(DateParseError::InvalidDate(format!("timezone {tz}")));
+    }
+    let hours: i64 = digits[..2]
+        .parse()
+        .map_err(|_| DateParseError::InvalidDate(format!("timezone {tz}")))?;
+    let minutes: i64 = digits[2..]
+        .parse()
+        .map_err(|_| DateParseError::InvalidDate(format!("timezone {tz}")))?;
+    Ok(sign * (hours * 60 + minutes) * 60 * 1000)
+}
+
+/// Creates a [`TimedLine`] for a line of a text file.
+#[allow(clippy::too_many_arguments)]
+pub fn line_to_timed_line(
+    line: &str,
+    original_line_length: usize,
+    tag: &str,
+    regex: &Regex,
+    year: Option<i32>,
+    time_offset: Option<i64>,
+    line_nr: usize,
+) -> Result<TimedLine, DateParseError> {
+    let (timestamp, year_was_missing) = extract_posix_timestamp(line, regex, year, time_offset)?;
+    Ok(TimedLine {
+        timestamp,
+        content: line.to_owned(),
+        tag: tag.to_owned(),
+        original_length: original_line_length,
+        year_was_missing,
+        line_nr,
+    })
+}
+
+/// Formats which are tried when detecting the timestamp format of a file. More specific
+/// formats come first, so they win if several formats match the same lines.
+const KNOWN_FORMATS: [&str; 14] = [
+    // ISO-8601
+    "YYYY-MM-DDThh:mm:ss.s TZD",
+    "YYYY-MM-DDThh:mm:ss TZD",
+    "YYYY-MM-DDThh:mm:ss.s",
+    "YYYY-MM-DDThh:mm:ss",
+    "YYYY-MM-DD hh:mm:ss.s TZD",
+    "YYYY-MM-DD hh:mm:ss.s",
+    "YYYY-MM-DD hh:mm:ss",
+    "YYYY/MM/DD hh:mm:ss",
+    // apache access logs
+    "DD/MMM/YYYY:hh:mm:ss TZD",
+    "MM-DD-YYYY hh:mm:ss.s",
+    "MM/DD/YYYY hh:mm:ss",
+    // android logcat
+    "MM-DD hh:mm:ss.s",
+    // syslog (RFC 3164)
+    "MMM DD hh:mm:ss",
+    "EPOCH",
+];
+
+/// A timestamp format detected in a sample of lines
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormatCandidate {
+    /// Format string as used by [`lookup_regex_for_format_str`] (and the merge options)
+    pub format: String,
+    /// Regex matching a timestamp of this format
+    pub regex: String,
+    /// Share of the sampled (non-empty) lines containing a timestamp of this format (0.0 - 1.0)
+    pub confidence: f64,
+}
+
+/// Detects which of the known timestamp formats are used in `lines`.
+///
+/// All formats matching at least one line are returned, the most likely one first.
+pub fn detect_timestamp_formats(lines: &[&str]) -> Vec<FormatCandidate> {
+    detect_formats(lines, None).unwrap_or_default()
+}
+
+/// Detects the timestamp formats as [`detect_timestamp_formats`]; `None` if cancelled
+fn detect_formats(
+    lines: &[&str],
+    shutdown_token: Option<&CancellationToken>,
+) -> Option<Vec<FormatCandidate>> {
+    let lines: Vec<&str> = lines
+        .iter()
+        .map(|line| line.trim_matches(utils::is_newline))
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    if lines.is_empty() {
+        return Some(vec![]);
+    }
+    let mut candidates: Vec<FormatCandidate> = Vec::new();
+    for format in KNOWN_FORMATS {
+        if shutdown_token.is_some_and(|token| token.is_cancelled()) {
+            return None;
+        }
+        let Ok(regex) = lookup_regex_for_format_str(format) else {
+            continue;
+        };
+        // a leap year is used, so dates like 02-29 are accepted for formats without year
+        let matches = lines
+            .iter()
+            .filter(|line| extract_posix_timestamp(line, &regex, Some(2000), None).is_ok())
+            .count();
+        if matches == 0 {
+            continue;
+        }
+        candidates.push(FormatCandidate {
+            format: format.to_string(),
+            regex: regex.as_str().to_owned(),
+            confidence: matches as f64 / lines.len() as f64,
+        });
+    }
+    // stable sort keeps more specific formats in front
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    Some(candidates)
+}
+
+/// Detects the timestamp format of a text file using its first `max_lines` lines.
+/// See [`detect_timestamp_formats`]. Returns `None` if the detection was cancelled.
+pub fn detect_timestamp_formats_in_file(
+    path: &Path,
+    max_lines: usize,
+    shutdown_token: Option<CancellationToken>,
+) -> Result<Option<Vec<FormatCandidate>>, io::Error> {
+    let is_cancelled = || {
+        shutdown_token
+            .as_ref()
+            .is_some_and(|token| token.is_cancelled())
+    };
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut lines: Vec<String> = vec![];
+    let mut buf = vec![];
+    while lines.len() < max_lines {
+        if is_cancelled() {
+            return Ok(None);
+        }
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        lines.push(String::from_utf8_lossy(&buf).into_owned());
+    }
+    Ok(detect_formats(
+        &lines.iter().map(|l| l.as_str()).collect::<Vec<&str>>(),
+        shutdown_token.as_ref(),
+    ))
+}
@@ -1,2 +1,3 @@
 #[macro_use]
 mod grabber_tests;
+mod parse_tests;
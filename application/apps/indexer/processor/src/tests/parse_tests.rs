@@ -0,0 +1,155 @@
+#[cfg(test)]
+mod tests {
+    use crate::parse::{
+        detect_timestamp_formats, detect_timestamp_formats_in_file, extract_posix_timestamp,
+        line_to_timed_line, lookup_regex_for_format_str, DateParseError,
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_format_with_year_and_timezone() {
+        let regex = lookup_regex_for_format_str("YYYY-MM-DDThh:mm:ss.s TZD").unwrap();
+        assert_eq!(
+            extract_posix_timestamp(
+                "2020-03-12T12:31:25.463964+01:00 - TRACE:: parse ok",
+                &regex,
+                None,
+                None
+            ),
+            Ok((1_584_012_685_463, false))
+        );
+    }
+
+    #[test]
+    fn test_format_without_year() {
+        let regex = lookup_regex_for_format_str("MM-DD hh:mm:ss.s TZD").unwrap();
+        assert_eq!(
+            extract_posix_timestamp(
+                "05-22 12:36:36.506 +0100 I/GKI_LINUX1",
+                &regex,
+                Some(2019),
+                None
+            ),
+            Ok((1_558_524_996_506, false))
+        );
+        let (_, year_was_missing) =
+            extract_posix_timestamp("05-22 12:36:36.506 +0100 I/GKI_LINUX1", &regex, None, None)
+                .unwrap();
+        assert!(year_was_missing);
+    }
+
+    #[test]
+    fn test_format_with_month_name_and_offset() {
+        let regex = lookup_regex_for_format_str("DD/MMM/YYYY:hh:mm:ss").unwrap();
+        assert_eq!(
+            extract_posix_timestamp(
+                "127.0.0.1 - - [10/Oct/2000:13:55:36] \"GET /apache_pb.gif\"",
+                &regex,
+                None,
+                Some(-1000)
+            ),
+            Ok((971_186_135_000, false))
+        );
+    }
+
+    #[test]
+    fn test_line_without_timestamp() {
+        let regex = lookup_regex_for_format_str("MM-DD-YYYY hh:mm:ss.s").unwrap();
+        assert_eq!(
+            line_to_timed_line("no timestamp here", 17, "tag", &regex, None, None, 0),
+            Err(DateParseError::NoTimestamp)
+        );
+        let timed_line = line_to_timed_line(
+            "[ios] 05-02-2019 12:36:36.506 B0",
+            33,
+            "tag",
+            &regex,
+            None,
+            None,
+            3,
+        )
+        .unwrap();
+        assert_eq!(timed_line.timestamp, 1_556_800_596_506);
+        assert_eq!(timed_line.line_nr, 3);
+        assert_eq!(timed_line.original_length, 33);
+    }
+
+    #[test]
+    fn test_unsupported_format() {
+        assert!(lookup_regex_for_format_str("hh:mm:ss").is_err());
+        assert!(lookup_regex_for_format_str("MM-DD-YYYY hh:mm MM").is_err());
+    }
+
+    #[test]
+    fn test_epoch_format() {
+        let regex = lookup_regex_for_format_str("EPOCH").unwrap();
+        assert_eq!(
+            extract_posix_timestamp("1558528564344 [main] started", &regex, None, Some(10)),
+            Ok((1_558_528_564_354, false))
+        );
+        assert!(lookup_regex_for_format_str("EPOCH hh:mm").is_err());
+    }
+
+    #[test]
+    fn test_detect_iso_format() {
+        let lines = [
+            "2020-03-12T12:31:25.463964+01:00 - TRACE:: parse ok",
+            "2020-03-12T12:31:25.464012+01:00 - DEBUG:: next line",
+            "   continuation without timestamp",
+            "",
+            "2020-03-12T12:31:26.001000+01:00 - TRACE:: done",
+        ];
+        let candidates = detect_timestamp_formats(&lines);
+        assert_eq!(candidates[0].format, "YYYY-MM-DDThh:mm:ss.s TZD");
+        assert_eq!(candidates[0].confidence, 0.75);
+        assert!(lookup_regex_for_format_str(&candidates[0].format)
+            .unwrap()
+            .is_match(lines[0]));
+    }
+
+    #[test]
+    fn test_detect_syslog_and_logcat_formats() {
+        let syslog = [
+            "May  5 12:36:04 host sshd[42]: accepted",
+            "May 15 12:36:05 host sshd[42]: closed",
+        ];
+        let candidates = detect_timestamp_formats(&syslog);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].format, "MMM DD hh:mm:ss");
+        assert_eq!(candidates[0].confidence, 1.0);
+
+        let logcat = [
+            "05-22 12:36:36.506  1234  1240 I GKI_LINUX: started",
+            "05-22 12:37:36.006  1234  1240 D Bluetooth: connected",
+        ];
+        assert_eq!(
+            detect_timestamp_formats(&logcat)[0].format,
+            "MM-DD hh:mm:ss.s"
+        );
+    }
+
+    #[test]
+    fn test_detect_epoch_and_nothing() {
+        let candidates = detect_timestamp_formats(&["1558528564344 a", "1558528564345 b"]);
+        assert_eq!(candidates[0].format, "EPOCH");
+        assert!(detect_timestamp_formats(&["no", "timestamps", "here"]).is_empty());
+        assert!(detect_timestamp_formats(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_detect_in_file_cancelled() {
+        use std::io::Write;
+        use tokio_util::sync::CancellationToken;
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "2019-05-22 12:36:04 first").unwrap();
+        let candidates = detect_timestamp_formats_in_file(file.path(), 10, None).unwrap();
+        assert!(candidates.is_some_and(|candidates| !candidates.is_empty()));
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        assert!(
+            detect_timestamp_formats_in_file(file.path(), 10, Some(cancel))
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -3,6 +3,7 @@ use crate::{
     state::AttachmentInfo,
 };
 use crossbeam_channel as cc;
+use merging::merger::MergeError;
 use processor::{grabber::GrabError, search::error::SearchError};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -84,6 +85,16 @@ impl From<std::io::Error> for NativeError {
     }
 }
 
+impl From<MergeError> for NativeError {
+    fn from(err: MergeError) -> Self {
+        NativeError {
+            severity: Severity::ERROR,
+            kind: NativeErrorKind::Configuration,
+            message: Some(format!("Fail to merge files: {err}")),
+        }
+    }
+}
+
 impl From<sources::Error> for NativeError {
     fn from(err: sources::Error) -> Self {
         NativeError {
@@ -225,6 +236,16 @@ pub enum CallbackEvent {
      * >> Kind: repeated
      */
     Progress { uuid: Uuid, progress: Progress },
+    /**
+     * Triggered if a source delivers its content split into custom columns
+     * @event ColumnsUpdated: { source_id: u16, columns: Vec<String> }
+     * >> Scope: session
+     * >> Kind: once per source
+     */
+    ColumnsUpdated {
+        source_id: u16,
+        columns: Vec<String>,
+    },
     /**
      * Triggered on error in the scope of session
      * >> Scope: session
@@ -298,6 +319,9 @@ impl std::fmt::Display for CallbackEvent {
                 uuid: _,
                 progress: _,
             } => write!(f, "Progress"),
+            Self::ColumnsUpdated { source_id, columns } => {
+                write!(f, "ColumnsUpdated({source_id}): {}", columns.len())
+            }
             Self::SessionError(err) => write!(f, "SessionError: {err:?}"),
             Self::OperationError { uuid, error } => {
                 write!(f, "OperationError: {uuid}: {error:?}")
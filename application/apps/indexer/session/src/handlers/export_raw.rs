@@ -1,5 +1,6 @@
 use crate::{
     events::{NativeError, NativeErrorKind},
+    handlers::observing::{columns_parser, json_parser, text_tokenizer},
     operations::OperationResult,
     progress::Severity,
     state::SessionStateAPI,
@@ -9,7 +10,7 @@ use log::debug;
 use parsers::{
     dlt::{fmt::FormatOptions, DltParser},
     someip::SomeipParser,
-    text::StringTokenizer,
+    syslog::SyslogParser,
     LogMessage, MessageStreamItem,
 };
 use processor::export::{export_raw, ExportError};
@@ -18,14 +19,12 @@ use sources::{
         pcap::{legacy::PcapLegacyByteSource, ng::PcapngByteSource},
         raw::BinaryByteSource,
     },
+    compression::Input,
     factory::{FileFormat, ParserType},
     producer::MessageProducer,
-    ByteSource,
-};
-use std::{
-    fs::File,
-    path::{Path, PathBuf},
+    ByteSource, SourceFilter,
 };
+use std::path::{Path, PathBuf};
 use tokio_util::sync::CancellationToken;
 
 pub async fn execute_export(
@@ -50,7 +49,7 @@ pub async fn execute_export(
         .map(IndexSection::from)
         .collect::<Vec<IndexSection>>();
     let count = observed.get_files().len();
-    for (i, (parser, file_format, filename)) in observed.get_files().iter().enumerate() {
+    for (i, (parser, filter, file_format, filename)) in observed.get_files().iter().enumerate() {
         if indexes.is_empty() {
             break;
         }
@@ -58,6 +57,7 @@ pub async fn execute_export(
             filename,
             &out_path,
             parser,
+            filter.as_ref(),
             file_format,
             &indexes,
             i != (count - 1),
@@ -82,12 +82,13 @@ async fn assing_source(
     src: &PathBuf,
     dest: &Path,
     parser: &ParserType,
+    filter: Option<&SourceFilter>,
     file_format: &FileFormat,
     sections: &Vec<IndexSection>,
     read_to_end: bool,
     cancel: &CancellationToken,
 ) -> Result<Option<usize>, NativeError> {
-    let reader = File::open(src).map_err(|e| NativeError {
+    let reader = Input::open(src).map_err(|e| NativeError {
         severity: Severity::ERROR,
         kind: NativeErrorKind::Io,
         message: Some(format!("Fail open file {}: {}", src.to_string_lossy(), e)),
@@ -97,6 +98,7 @@ async fn assing_source(
             export(
                 dest,
                 parser,
+                filter,
                 BinaryByteSource::new(reader),
                 sections,
                 read_to_end,
@@ -108,6 +110,7 @@ async fn assing_source(
             export(
                 dest,
                 parser,
+                filter,
                 PcapngByteSource::new(reader)?,
                 sections,
                 read_to_end,
@@ -119,6 +122,7 @@ async fn assing_source(
             export(
                 dest,
                 parser,
+                filter,
                 PcapLegacyByteSource::new(reader)?,
                 sections,
                 read_to_end,
@@ -132,6 +136,7 @@ async fn assing_source(
 async fn export<S: ByteSource>(
     dest: &Path,
     parser: &ParserType,
+    filter: Option<&SourceFilter>,
     source: S,
     sections: &Vec<IndexSection>,
     read_to_end: bool,
@@ -144,7 +149,8 @@ async fn export<S: ByteSource>(
             } else {
                 SomeipParser::new()
             };
-            let mut producer = MessageProducer::new(parser, source, None);
+            let mut producer =
+                MessageProducer::new(parser, source, None).with_filter(filter.cloned());
             export_runner(
                 Box::pin(producer.as_stream()),
                 dest,
@@ -164,7 +170,8 @@ async fn export<S: ByteSource>(
                 None,
                 settings.with_storage_header,
             );
-            let mut producer = MessageProducer::new(parser, source, None);
+            let mut producer =
+                MessageProducer::new(parser, source, None).with_filter(filter.cloned());
             export_runner(
                 Box::pin(producer.as_stream()),
                 dest,
@@ -175,8 +182,48 @@ async fn export<S: ByteSource>(
             )
             .await
         }
-        ParserType::Text => {
-            let mut producer = MessageProducer::new(StringTokenizer {}, source, None);
+        ParserType::Text(settings) => {
+            let mut producer = MessageProducer::new(text_tokenizer(settings)?, source, None)
+                .with_filter(filter.cloned());
+            export_runner(
+                Box::pin(producer.as_stream()),
+                dest,
+                sections,
+                read_to_end,
+                true,
+                cancel,
+            )
+            .await
+        }
+        ParserType::Columns(settings) => {
+            let mut producer = MessageProducer::new(columns_parser(settings)?, source, None)
+                .with_filter(filter.cloned());
+            export_runner(
+                Box::pin(producer.as_stream()),
+                dest,
+                sections,
+                read_to_end,
+                true,
+                cancel,
+            )
+            .await
+        }
+        ParserType::JsonLines(settings) => {
+            let mut producer = MessageProducer::new(json_parser(settings)?, source, None)
+                .with_filter(filter.cloned());
+            export_runner(
+                Box::pin(producer.as_stream()),
+                dest,
+                sections,
+                read_to_end,
+                true,
+                cancel,
+            )
+            .await
+        }
+        ParserType::Syslog(_) => {
+            let mut producer = MessageProducer::new(SyslogParser::default(), source, None)
+                .with_filter(filter.cloned());
             export_runner(
                 Box::pin(producer.as_stream()),
                 dest,
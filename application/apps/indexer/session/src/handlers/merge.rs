@@ -0,0 +1,78 @@
+use crate::{
+    events::{CallbackEvent, NativeError, NativeErrorKind},
+    operations::{OperationAPI, OperationResult},
+    progress::{Notification, Progress, Severity, Ticks},
+    state::SessionStateAPI,
+};
+use log::debug;
+use merging::merger::{combined_file_size, FileMergeOptions, Merger};
+
+/// Progress is reported each time another percent of the input was processed
+const PROGRESS_STEPS: u64 = 100;
+
+/// Merges the lines of all given files by their timestamps and writes the result into
+/// the session file. Each file is registered as its own source using its tag as alias.
+pub async fn merge_files(
+    operation_api: &OperationAPI,
+    state: SessionStateAPI,
+    files: Vec<FileMergeOptions>,
+) -> OperationResult<()> {
+    if files.is_empty() {
+        return Err(NativeError {
+            severity: Severity::ERROR,
+            kind: NativeErrorKind::Configuration,
+            message: Some(String::from("No files are defined for Merge operation")),
+        });
+    }
+    let total = combined_file_size(&files)?;
+    let mut source_ids: Vec<u16> = vec![];
+    for file in files.iter() {
+        source_ids.push(state.add_source(&file.tag).await?);
+    }
+    let mut merger = Merger::new(files)?;
+    state.set_session_file(None).await?;
+    operation_api.processing();
+    let cancel = operation_api.cancellation_token();
+    let step = (total / PROGRESS_STEPS).max(1);
+    let mut reported: u64 = 0;
+    while let Some((index, line)) = merger.next() {
+        if cancel.is_cancelled() {
+            debug!("merging was cancelled");
+            return Ok(None);
+        }
+        state
+            .write_session_file(source_ids[index], format!("{}\n", line.content))
+            .await?;
+        let processed = merger.processed_bytes();
+        if processed - reported >= step {
+            reported = processed;
+            emit_ticks(operation_api, processed, total);
+        }
+    }
+    state.flush_session_file().await?;
+    state.file_read().await?;
+    emit_ticks(operation_api, total, total);
+    let lines_with_year_missing = merger.lines_with_year_missing();
+    if lines_with_year_missing > 0 {
+        operation_api.emit(CallbackEvent::Progress {
+            uuid: operation_api.id(),
+            progress: Progress::Notification(Notification {
+                severity: Severity::WARNING,
+                content: format!("year was missing for {lines_with_year_missing} lines"),
+                line: None,
+            }),
+        });
+    }
+    Ok(None)
+}
+
+fn emit_ticks(operation_api: &OperationAPI, count: u64, total: u64) {
+    operation_api.emit(CallbackEvent::Progress {
+        uuid: operation_api.id(),
+        progress: Progress::Ticks(Ticks {
+            count,
+            state: None,
+            total: Some(total),
+        }),
+    });
+}
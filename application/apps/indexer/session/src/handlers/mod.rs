@@ -1,5 +1,6 @@
 pub mod export_raw;
 pub mod extract;
+pub mod merge;
 pub mod observe;
 mod observing;
 pub mod search;
@@ -6,29 +6,75 @@ use crate::{
     state::SessionStateAPI,
 };
 use log::error;
+use parsers::text::TextEncoding;
 use sources::{
-    factory::{ObserveOptions, ObserveOrigin, ParserType},
+    command::process::ProcessExit,
+    compression::Input,
+    factory::{FileFormat, ObserveOptions, ObserveOrigin, ParserType, Transport},
     producer::SdeReceiver,
 };
+use std::{io::Read, path::Path};
 
 pub async fn start_observing(
     operation_api: OperationAPI,
     state: SessionStateAPI,
     mut options: ObserveOptions,
     rx_sde: Option<SdeReceiver>,
-) -> OperationResult<()> {
+) -> OperationResult<ProcessExit> {
     if let ParserType::Dlt(ref mut settings) = options.parser {
         settings.load_fibex_metadata();
     };
+    // Files in archives are replaced by plain copies before the options are stored, so
+    // re-reading the files (e.g. for raw export) works on them.
+    observing::inputs::resolve_files(&mut options.origin).await?;
     if let Err(err) = state.add_executed_observe(options.clone()).await {
         error!("Fail to store observe options: {:?}", err);
     }
+    match &options.origin {
+        // how the process has ended is the result of observing it
+        ObserveOrigin::Stream(uuid, Transport::Process(settings)) => {
+            observing::stream::observe_process(
+                operation_api,
+                state,
+                uuid,
+                settings,
+                &options.parser,
+                rx_sde,
+            )
+            .await
+        }
+        _ => {
+            observe_origin(operation_api, state, &options, rx_sde).await?;
+            Ok(None)
+        }
+    }
+}
+
+async fn observe_origin(
+    operation_api: OperationAPI,
+    state: SessionStateAPI,
+    options: &ObserveOptions,
+    rx_sde: Option<SdeReceiver>,
+) -> OperationResult<()> {
     match &options.origin {
         ObserveOrigin::File(uuid, file_origin, filename) => {
             let (is_text, session_file_origin) = (
-                matches!(options.parser, ParserType::Text),
+                matches!(options.parser, ParserType::Text(_)),
                 state.get_session_file_origin().await?,
             );
+            // Only plain UTF-8 text files (no grouped records or columns) can be used as session file directly
+            let needs_parsing = match &options.parser {
+                ParserType::Text(settings) => {
+                    matches!(file_origin, FileFormat::Text)
+                        && (settings.record_start.is_some()
+                            || settings.encoding.resolve(&read_head(filename))
+                                != TextEncoding::Utf8)
+                }
+                ParserType::Columns(_) | ParserType::JsonLines(_) | ParserType::Syslog(_) => {
+                    matches!(file_origin, FileFormat::Text)
+                }
+                _ => false,
+            };
             match session_file_origin {
                 Some(origin) if origin.is_linked() => Err(NativeError {
                     severity: Severity::ERROR,
@@ -37,14 +83,42 @@ pub async fn start_observing(
                         "Cannot observe file, because session is linked to other text file",
                     )),
                 }),
-                Some(origin) if !origin.is_linked() && is_text => {
+                _ if observing::inputs::is_compressed(filename)? => {
+                    // Compressed files are read once and decompressed on the fly; they
+                    // aren't followed
+                    observing::concat::concat_files(
+                        operation_api,
+                        state,
+                        &[(uuid.clone(), file_origin.clone(), filename.clone())],
+                        &options.parser,
+                        options.filter.as_ref(),
+                    )
+                    .await
+                }
+                _ if needs_parsing => {
+                    // Text files which have to be parsed first are read as source, which
+                    // keeps following the file as it grows
+                    let source_id = state.add_source(uuid).await?;
+                    observing::file::tail_file(
+                        operation_api,
+                        state,
+                        source_id,
+                        file_origin,
+                        filename,
+                        &options.parser,
+                        options.filter.as_ref(),
+                    )
+                    .await
+                }
+                origin if is_text && origin.is_some() => {
                     // Session file was created and some files/streams were opened already. We should check for text files
-                    // to prevent attempt to link session with text file. Using concat instead
+                    // to prevent attempt to link session with text file. Using concat instead.
                     observing::concat::concat_files(
                         operation_api,
                         state,
                         &[(uuid.clone(), file_origin.clone(), filename.clone())],
                         &options.parser,
+                        options.filter.as_ref(),
                     )
                     .await
                 }
@@ -56,6 +130,7 @@ pub async fn start_observing(
                         file_origin,
                         filename,
                         &options.parser,
+                        options.filter.as_ref(),
                     )
                     .await
                 }
@@ -69,9 +144,28 @@ pub async fn start_observing(
                     message: Some(String::from("No files are defined for Concat operation")),
                 })
             } else {
-                observing::concat::concat_files(operation_api, state, files, &options.parser).await
+                observing::concat::concat_files(
+                    operation_api,
+                    state,
+                    files,
+                    &options.parser,
+                    options.filter.as_ref(),
+                )
+                .await
             }
         }
+        ObserveOrigin::Directory(dir, pattern, file_format) => {
+            observing::directory::observe_directory(
+                operation_api,
+                state,
+                dir,
+                pattern,
+                file_format,
+                &options.parser,
+                options.filter.as_ref(),
+            )
+            .await
+        }
         ObserveOrigin::Stream(uuid, transport) => {
             observing::stream::observe_stream(
                 operation_api,
@@ -85,3 +179,14 @@ pub async fn start_observing(
         }
     }
 }
+
+/// Reads the first bytes of a file, enough to detect a byte order mark
+fn read_head(filename: &Path) -> Vec<u8> {
+    let mut head = Vec::with_capacity(4);
+    if let Ok(file) = Input::open(filename) {
+        if let Err(err) = file.take(4).read_to_end(&mut head) {
+            error!("Fail to read head of {}: {err}", filename.display());
+        }
+    }
+    head
+}
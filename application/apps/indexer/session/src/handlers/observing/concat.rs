@@ -9,9 +9,11 @@ use sources::{
         pcap::{legacy::PcapLegacyByteSource, ng::PcapngByteSource},
         raw::BinaryByteSource,
     },
+    compression::Input,
     factory::{FileFormat, ParserType},
+    SourceFilter,
 };
-use std::{fs::File, path::PathBuf};
+use std::path::PathBuf;
 
 #[allow(clippy::type_complexity)]
 pub async fn concat_files(
@@ -19,6 +21,7 @@ pub async fn concat_files(
     state: SessionStateAPI,
     files: &[(String, FileFormat, PathBuf)],
     parser: &ParserType,
+    filter: Option<&SourceFilter>,
 ) -> OperationResult<()> {
     for file in files.iter() {
         let (uuid, _file_type, _filename) = file;
@@ -35,7 +38,8 @@ pub async fn concat_files(
                 uuid,
             )),
         })?;
-        let input_file = File::open(filename).map_err(|e| NativeError {
+        // compressed files are decompressed while they are read
+        let input_file = Input::open(filename).map_err(|e| NativeError {
             severity: Severity::ERROR,
             kind: NativeErrorKind::Io,
             message: Some(format!(
@@ -52,6 +56,7 @@ pub async fn concat_files(
                     BinaryByteSource::new(input_file),
                     source_id,
                     parser,
+                    filter.cloned(),
                     None,
                     None,
                 )
@@ -64,6 +69,7 @@ pub async fn concat_files(
                     PcapLegacyByteSource::new(input_file)?,
                     source_id,
                     parser,
+                    filter.cloned(),
                     None,
                     None,
                 )
@@ -76,6 +82,7 @@ pub async fn concat_files(
                     PcapngByteSource::new(input_file)?,
                     source_id,
                     parser,
+                    filter.cloned(),
                     None,
                     None,
                 )
@@ -88,6 +95,7 @@ pub async fn concat_files(
                     BinaryByteSource::new(input_file),
                     source_id,
                     parser,
+                    filter.cloned(),
                     None,
                     None,
                 )
@@ -0,0 +1,138 @@
+use crate::{
+    events::{NativeError, NativeErrorKind},
+    handlers::observing::file,
+    operations::{OperationAPI, OperationResult},
+    progress::Severity,
+    state::SessionStateAPI,
+    tail,
+};
+use futures::{stream::FuturesUnordered, StreamExt};
+use glob::Pattern;
+use log::warn;
+use sources::{
+    factory::{FileFormat, ParserType},
+    SourceFilter,
+};
+use std::path::{Path, PathBuf};
+use tokio::{
+    join, select,
+    sync::mpsc::{channel, unbounded_channel, Receiver, Sender, UnboundedSender},
+};
+
+/// Observes the files in the directory whose names match `pattern`, including files
+/// which are created later. Each file is added as a source of its own, named by its path,
+/// and is tailed. A file which fails is dropped and tried again later.
+#[allow(clippy::type_complexity)]
+pub async fn observe_directory(
+    operation_api: OperationAPI,
+    state: SessionStateAPI,
+    dir: &Path,
+    pattern: &str,
+    file_format: &FileFormat,
+    parser: &ParserType,
+    filter: Option<&SourceFilter>,
+) -> OperationResult<()> {
+    let pattern = Pattern::new(pattern).map_err(|e| NativeError {
+        severity: Severity::ERROR,
+        kind: NativeErrorKind::Configuration,
+        message: Some(format!("Invalid pattern {pattern}: {e}")),
+    })?;
+    let (tx_files, rx_files): (
+        Sender<Result<PathBuf, tail::Error>>,
+        Receiver<Result<PathBuf, tail::Error>>,
+    ) = channel(1);
+    let (tx_dropped, rx_dropped) = unbounded_channel();
+    let (tracker, observing) = join!(
+        tail::track_dir(
+            dir,
+            &pattern,
+            tx_files,
+            rx_dropped,
+            operation_api.cancellation_token()
+        ),
+        observe_files(
+            operation_api,
+            state,
+            file_format,
+            parser,
+            filter,
+            rx_files,
+            tx_dropped
+        ),
+    );
+    observing.and_then(|result| {
+        tracker
+            .map_err(|e| NativeError {
+                severity: Severity::ERROR,
+                kind: NativeErrorKind::Interrupted,
+                message: Some(format!("Fail to track {}: {e}", dir.to_string_lossy())),
+            })
+            .map(|_| result)
+    })
+}
+
+/// Tails the files until the operation is cancelled. The failure of a file doesn't stop
+/// the others; the file is sent back to the tracker to be retried.
+async fn observe_files(
+    operation_api: OperationAPI,
+    state: SessionStateAPI,
+    file_format: &FileFormat,
+    parser: &ParserType,
+    filter: Option<&SourceFilter>,
+    mut rx_files: Receiver<Result<PathBuf, tail::Error>>,
+    tx_dropped: UnboundedSender<PathBuf>,
+) -> OperationResult<()> {
+    let cancel = operation_api.cancellation_token();
+    let mut files = FuturesUnordered::new();
+    loop {
+        select! {
+            filename = rx_files.recv() => {
+                let Some(filename) = filename else {
+                    // tracking has ended
+                    break;
+                };
+                let filename = filename.map_err(|err| NativeError {
+                    severity: Severity::ERROR,
+                    kind: NativeErrorKind::Interrupted,
+                    message: Some(err.to_string()),
+                })?;
+                // a retried file keeps its source
+                let alias = filename.to_string_lossy();
+                let source_id = match state.get_source(&alias).await? {
+                    Some(source_id) => source_id,
+                    None => state.add_source(&alias).await?,
+                };
+                // a failing file cancels its own part of the operation only
+                let (operation_api, state) = (operation_api.child(), state.clone());
+                files.push(async move {
+                    let result = file::tail_file(
+                        operation_api,
+                        state,
+                        source_id,
+                        file_format,
+                        &filename,
+                        parser,
+                        filter,
+                    )
+                    .await;
+                    (filename, result)
+                });
+            }
+            Some((filename, result)) = files.next(), if !files.is_empty() => {
+                if let Err(err) = result {
+                    warn!("Fail to observe {}: {err:?}", filename.to_string_lossy());
+                    // the tracker is done if it's closed
+                    let _ = tx_dropped.send(filename);
+                }
+            }
+            _ = cancel.cancelled() => break,
+        }
+    }
+    // the files stop reading once the operation is cancelled
+    while let Some((filename, result)) = files.next().await {
+        if let Err(err) = result {
+            warn!("Fail to observe {}: {err:?}", filename.to_string_lossy());
+        }
+    }
+    Ok(None)
+}
@@ -3,14 +3,20 @@ use crate::{
     operations::{OperationAPI, OperationResult},
     progress::Severity,
     state::SessionStateAPI,
-    tail,
+    tail::{
+        self,
+        followed::{FollowedFile, Reopen},
+        Change,
+    },
 };
+use log::warn;
 use sources::{
     binary::{
         pcap::{legacy::PcapLegacyByteSource, ng::PcapngByteSource},
         raw::BinaryByteSource,
     },
     factory::{FileFormat, ParserType},
+    ByteSource, SourceFilter,
 };
 use std::{fs::File, path::Path};
 use tokio::{
@@ -26,62 +32,34 @@ pub async fn observe_file<'a>(
     file_format: &FileFormat,
     filename: &Path,
     parser: &'a ParserType,
+    filter: Option<&SourceFilter>,
 ) -> OperationResult<()> {
     let source_id = state.add_source(uuid).await?;
-    let (tx_tail, mut rx_tail): (
-        Sender<Result<(), tail::Error>>,
-        Receiver<Result<(), tail::Error>>,
+    let (tx_changes, rx_changes): (
+        Sender<Result<Change, tail::Error>>,
+        Receiver<Result<Change, tail::Error>>,
     ) = channel(1);
+    let cancel = operation_api.cancellation_token();
     match file_format {
-        FileFormat::Binary => {
-            let source = BinaryByteSource::new(input_file(filename)?);
+        FileFormat::Binary | FileFormat::PcapLegacy | FileFormat::PcapNG => {
             let (_, listening) = join!(
-                tail::track(filename, tx_tail, operation_api.cancellation_token()),
-                super::run_source(
+                tail::track(filename, tx_changes, cancel),
+                read_file(
                     operation_api,
                     state,
-                    source,
                     source_id,
+                    file_format,
+                    filename,
                     parser,
-                    None,
-                    Some(rx_tail)
-                )
-            );
-            listening
-        }
-        FileFormat::PcapLegacy => {
-            let source = PcapLegacyByteSource::new(input_file(filename)?)?;
-            let (_, listening) = join!(
-                tail::track(filename, tx_tail, operation_api.cancellation_token()),
-                super::run_source(
-                    operation_api,
-                    state,
-                    source,
-                    source_id,
-                    parser,
-                    None,
-                    Some(rx_tail)
-                )
-            );
-            listening
-        }
-        FileFormat::PcapNG => {
-            let source = PcapngByteSource::new(input_file(filename)?)?;
-            let (_, listening) = join!(
-                tail::track(filename, tx_tail, operation_api.cancellation_token()),
-                super::run_source(
-                    operation_api,
-                    state,
-                    source,
-                    source_id,
-                    parser,
-                    None,
-                    Some(rx_tail)
+                    filter,
+                    rx_changes
                 )
             );
             listening
         }
         FileFormat::Text => {
+            // Keeps the indexed content readable after a rotation of the file
+            let linked = input_file(filename)?;
             state.set_session_file(Some(filename.to_path_buf())).await?;
             // Grab main file content
             state.update_session(source_id).await?;
@@ -89,42 +67,295 @@ pub async fn observe_file<'a>(
             // Confirm: main file content has been read
             state.file_read().await?;
             // Switching to tail
-            let cancel = operation_api.cancellation_token();
             let (result, tracker) = join!(
                 async {
                     let result = select! {
-                        res = async move {
-                            while let Some(update) = rx_tail.recv().await {
-                                update.map_err(|err| NativeError {
-                                    severity: Severity::ERROR,
-                                    kind: NativeErrorKind::Interrupted,
-                                    message: Some(err.to_string()),
-                                })?;
-                                state.update_session(source_id).await?;
-                            }
-                            Ok(())
-                        } => res,
-                        _ = cancel.cancelled() => Ok(())
+                        res = follow_linked(
+                            operation_api.clone(),
+                            state,
+                            source_id,
+                            filename,
+                            linked,
+                            parser,
+                            filter,
+                            rx_changes,
+                        ) => res,
+                        _ = cancel.cancelled() => Ok(None)
                     };
                     result
                 },
-                tail::track(filename, tx_tail, operation_api.cancellation_token()),
+                tail::track(filename, tx_changes, operation_api.cancellation_token()),
             );
-            result
-                .and_then(|_| {
-                    tracker.map_err(|e| NativeError {
+            result.and_then(|result| {
+                tracker
+                    .map_err(|e| NativeError {
                         severity: Severity::ERROR,
                         kind: NativeErrorKind::Interrupted,
                         message: Some(format!("Tailing error: {e}")),
                     })
-                })
-                .map(|_| None)
+                    .map(|_| result)
+            })
+        }
+    }
+}
+
+/// Updates the session linked to the text file while the file grows. Once the file was
+/// truncated or rotated, the session is detached from it and the new content of the file
+/// is read as source.
+#[allow(clippy::too_many_arguments)]
+async fn follow_linked(
+    operation_api: OperationAPI,
+    state: SessionStateAPI,
+    source_id: u16,
+    filename: &Path,
+    linked: File,
+    parser: &ParserType,
+    filter: Option<&SourceFilter>,
+    mut rx_changes: Receiver<Result<Change, tail::Error>>,
+) -> OperationResult<()> {
+    while let Some(change) = rx_changes.recv().await {
+        let change = change.map_err(|err| NativeError {
+            severity: Severity::ERROR,
+            kind: NativeErrorKind::Interrupted,
+            message: Some(err.to_string()),
+        })?;
+        if change == Change::Grown {
+            state.update_session(source_id).await?;
+            continue;
+        }
+        // The indexed content is gone with a truncation
+        state
+            .detach_session_file(matches!(change, Change::Rotated).then_some(linked))
+            .await?;
+        state
+            .write_session_file(source_id, marker(filename, change))
+            .await?;
+        return read_file(
+            operation_api,
+            state,
+            source_id,
+            &FileFormat::Text,
+            filename,
+            parser,
+            filter,
+            rx_changes,
+        )
+        .await;
+    }
+    Ok(None)
+}
+
+/// Reads the file as source while it changes. In contrast to [`observe_file`], text files
+/// are read as source as well instead of being linked as session file.
+pub async fn tail_file(
+    operation_api: OperationAPI,
+    state: SessionStateAPI,
+    source_id: u16,
+    file_format: &FileFormat,
+    filename: &Path,
+    parser: &ParserType,
+    filter: Option<&SourceFilter>,
+) -> OperationResult<()> {
+    let (tx_changes, rx_changes) = channel(1);
+    let tracking = operation_api.cancellation_token().child_token();
+    let reading = async {
+        let result = read_file(
+            operation_api,
+            state,
+            source_id,
+            file_format,
+            filename,
+            parser,
+            filter,
+            rx_changes,
+        )
+        .await;
+        // the file isn't tracked anymore if it couldn't be read
+        tracking.cancel();
+        result
+    };
+    let (_, listening) = join!(tail::track(filename, tx_changes, tracking.clone()), reading);
+    listening
+}
+
+/// Reads the file as source, which continues with the changes of the file
+#[allow(clippy::too_many_arguments)]
+async fn read_file(
+    operation_api: OperationAPI,
+    state: SessionStateAPI,
+    source_id: u16,
+    file_format: &FileFormat,
+    filename: &Path,
+    parser: &ParserType,
+    filter: Option<&SourceFilter>,
+    rx_changes: Receiver<Result<Change, tail::Error>>,
+) -> OperationResult<()> {
+    match file_format {
+        FileFormat::Binary | FileFormat::Text => {
+            let file = followed_file(filename)?;
+            let reopen = file.reopen();
+            follow_source(
+                operation_api,
+                state,
+                BinaryByteSource::new(file),
+                source_id,
+                filename,
+                Some(reopen),
+                parser,
+                filter,
+                rx_changes,
+            )
+            .await
+        }
+        FileFormat::PcapLegacy => {
+            // a new capture starts with a header again, which the source cannot handle
+            let source = PcapLegacyByteSource::new(input_file(filename)?)?;
+            follow_source(
+                operation_api,
+                state,
+                source,
+                source_id,
+                filename,
+                None,
+                parser,
+                filter,
+                rx_changes,
+            )
+            .await
+        }
+        FileFormat::PcapNG => {
+            // a new capture is read as further section
+            let file = followed_file(filename)?;
+            let reopen = file.reopen();
+            let source = PcapngByteSource::new(file)?;
+            follow_source(
+                operation_api,
+                state,
+                source,
+                source_id,
+                filename,
+                Some(reopen),
+                parser,
+                filter,
+                rx_changes,
+            )
+            .await
         }
     }
 }
 
+/// Runs the source, which continues as soon as its file has changed
+#[allow(clippy::too_many_arguments)]
+async fn follow_source<S: ByteSource>(
+    operation_api: OperationAPI,
+    state: SessionStateAPI,
+    source: S,
+    source_id: u16,
+    filename: &Path,
+    reopen: Option<Reopen>,
+    parser: &ParserType,
+    filter: Option<&SourceFilter>,
+    rx_changes: Receiver<Result<Change, tail::Error>>,
+) -> OperationResult<()> {
+    let (tx_tail, rx_tail): (
+        Sender<Result<(), tail::Error>>,
+        Receiver<Result<(), tail::Error>>,
+    ) = channel(1);
+    let (following, listening) = join!(
+        follow(
+            state.clone(),
+            source_id,
+            filename,
+            reopen,
+            rx_changes,
+            tx_tail
+        ),
+        super::run_source(
+            operation_api,
+            state,
+            source,
+            source_id,
+            parser,
+            filter.cloned(),
+            None,
+            Some(rx_tail)
+        )
+    );
+    following.and(listening)
+}
+
+/// Forwards the changes of the file to the source. If the source can reopen its file,
+/// it starts over after a truncation or rotation; once it has read the previous file
+/// and reopened the path, the point is marked by a row.
+async fn follow(
+    state: SessionStateAPI,
+    source_id: u16,
+    filename: &Path,
+    reopen: Option<Reopen>,
+    mut rx_changes: Receiver<Result<Change, tail::Error>>,
+    tx_tail: Sender<Result<(), tail::Error>>,
+) -> Result<(), NativeError> {
+    // change which the source is going to start over for
+    let mut reopening: Option<Change> = None;
+    loop {
+        let update = select! {
+            change = rx_changes.recv() => {
+                let Some(change) = change else {
+                    break;
+                };
+                if let Ok(change @ (Change::Truncated | Change::Rotated)) = change {
+                    if let Some(reopen) = reopen.as_ref() {
+                        reopening = Some(change);
+                        reopen.request();
+                    } else {
+                        warn!(
+                            "{} was {change}; the new content cannot be read",
+                            filename.to_string_lossy()
+                        );
+                    }
+                }
+                change.map(|_| ())
+            }
+            _ = async {
+                if let Some(reopen) = reopen.as_ref() {
+                    reopen.reopened().await;
+                }
+            }, if reopening.is_some() => {
+                // the source waits for the update, the rows of the new file follow the marker
+                if let Some(change) = reopening.take() {
+                    state
+                        .write_session_file(source_id, marker(filename, change))
+                        .await?;
+                }
+                Ok(())
+            }
+        };
+        if tx_tail.send(update).await.is_err() {
+            // the source is done
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Row which marks the start of the new content of a truncated or rotated file
+fn marker(filename: &Path, change: Change) -> String {
+    format!(
+        "--- {} was {change}, reading it from the start ---\n",
+        filename.to_string_lossy()
+    )
+}
+
+fn followed_file(filename: &Path) -> Result<FollowedFile, NativeError> {
+    FollowedFile::open(filename).map_err(|e| open_error(filename, e))
+}
+
 fn input_file(filename: &Path) -> Result<File, NativeError> {
-    File::open(filename).map_err(|e| NativeError {
+    File::open(filename).map_err(|e| open_error(filename, e))
+}
+
+fn open_error(filename: &Path, e: std::io::Error) -> NativeError {
+    NativeError {
         severity: Severity::ERROR,
         kind: NativeErrorKind::Io,
         message: Some(format!(
@@ -132,5 +363,227 @@ fn input_file(filename: &Path) -> Result<File, NativeError> {
             filename.to_string_lossy(),
             e
         )),
-    })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{state, tracker, tracker::OperationTrackerAPI};
+    use processor::grabber::LineRange;
+    use sources::factory::{SyslogParserSettings, TextParserSettings};
+    use std::{fs, io::Write, path::PathBuf};
+    use tokio::{
+        sync::mpsc::unbounded_channel,
+        task::JoinHandle,
+        time::{sleep, timeout, Duration},
+    };
+    use tokio_util::sync::CancellationToken;
+    use uuid::Uuid;
+
+    fn session() -> (OperationAPI, SessionStateAPI) {
+        let (tracker_api, rx_tracker_api) = OperationTrackerAPI::new();
+        let (state_api, rx_state_api) = SessionStateAPI::new(tracker_api.clone());
+        let (tx_callback_events, mut rx_callback_events) = unbounded_channel();
+        tokio::spawn(state::run(rx_state_api, tx_callback_events.clone()));
+        tokio::spawn(tracker::run(state_api.clone(), rx_tracker_api));
+        tokio::spawn(async move { while rx_callback_events.recv().await.is_some() {} });
+        let operation_api = OperationAPI::new(
+            state_api.clone(),
+            tracker_api,
+            tx_callback_events,
+            Uuid::new_v4(),
+            CancellationToken::new(),
+        );
+        (operation_api, state_api)
+    }
+
+    /// Tails the file as the only source of the session
+    async fn tail(
+        file_format: FileFormat,
+        path: PathBuf,
+        parser: ParserType,
+    ) -> (
+        OperationAPI,
+        SessionStateAPI,
+        JoinHandle<OperationResult<()>>,
+    ) {
+        let (operation_api, state) = session();
+        let source_id = state.add_source("test").await.unwrap();
+        let tailing = tokio::spawn({
+            let (operation_api, state) = (operation_api.clone(), state.clone());
+            async move {
+                tail_file(
+                    operation_api,
+                    state,
+                    source_id,
+                    &file_format,
+                    &path,
+                    &parser,
+                    None,
+                )
+                .await
+            }
+        });
+        (operation_api, state, tailing)
+    }
+
+    /// Ethernet frame of an IPv4 packet from 10.0.0.1 to 10.0.0.2
+    fn ipv4_frame(protocol: u8, transport: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x02; 12];
+        frame.extend_from_slice(&[0x08, 0x00, 0x45, 0x00]);
+        frame.extend_from_slice(&(20 + transport.len() as u16).to_be_bytes());
+        frame.extend_from_slice(&[0x00, 0x00, 0x40, 0x00, 0x40, protocol, 0x00, 0x00]);
+        frame.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        frame.extend_from_slice(transport);
+        frame
+    }
+
+    fn tcp_frame(seq: u32, payload: &[u8]) -> Vec<u8> {
+        let mut segment = [0x9c, 0x40, 0x0d, 0xa2].to_vec();
+        segment.extend_from_slice(&seq.to_be_bytes());
+        segment.extend_from_slice(&[0, 0, 0, 0, 0x50, 0x10, 0xff, 0xff, 0, 0, 0, 0]);
+        segment.extend_from_slice(payload);
+        ipv4_frame(0x06, &segment)
+    }
+
+    fn udp_frame(payload: &[u8]) -> Vec<u8> {
+        let mut datagram = [0x9c, 0x41, 0x0d, 0xa3].to_vec();
+        datagram.extend_from_slice(&(8 + payload.len() as u16).to_be_bytes());
+        datagram.extend_from_slice(&[0, 0]);
+        datagram.extend_from_slice(payload);
+        ipv4_frame(0x11, &datagram)
+    }
+
+    /// Capture with a section header, an ethernet interface and the frames
+    fn pcapng(frames: &[Vec<u8>]) -> Vec<u8> {
+        let block = |block_type: u32, body: &[u8]| {
+            let len = (12 + body.len()) as u32;
+            let mut block = block_type.to_le_bytes().to_vec();
+            block.extend_from_slice(&len.to_le_bytes());
+            block.extend_from_slice(body);
+            block.extend_from_slice(&len.to_le_bytes());
+            block
+        };
+        let mut header = 0x1A2B_3C4Du32.to_le_bytes().to_vec();
+        header.extend_from_slice(&[1, 0, 0, 0]);
+        header.extend_from_slice(&(-1i64).to_le_bytes());
+        let mut capture = block(0x0A0D_0D0A, &header);
+        capture.extend(block(1, &[1, 0, 0, 0, 0, 0, 0, 0]));
+        for (ts, frame) in frames.iter().enumerate() {
+            let mut packet = [0, 0, 0, 0, 0, 0, 0, 0].to_vec();
+            packet.extend_from_slice(&(ts as u32).to_le_bytes());
+            packet.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            packet.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            packet.extend_from_slice(frame);
+            packet.resize(packet.len().next_multiple_of(4), 0);
+            capture.extend(block(6, &packet));
+        }
+        capture
+    }
+
+    /// Waits until the session has `count` rows and returns them
+    async fn wait_rows(state: &SessionStateAPI, count: u64) -> Vec<String> {
+        timeout(Duration::from_secs(5), async {
+            while state.get_stream_len().await.unwrap().0 < count {
+                sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("rows are written");
+        state
+            .grab(LineRange::from(0..=count - 1))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|row| row.content)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_tail_static_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "INFO first\n  detail\nINFO last\n  detail\n").unwrap();
+        let parser = ParserType::Text(TextParserSettings {
+            record_start: Some(String::from("^INFO")),
+            ..Default::default()
+        });
+        let (operation_api, state, tailing) = tail(FileFormat::Text, path.clone(), parser).await;
+        // the last record is delivered although the file is tailed
+        let rows = wait_rows(&state, 2).await;
+        assert!(rows[0].starts_with("INFO first"));
+        assert!(rows[1].starts_with("INFO last"));
+
+        // a continued record is delivered again
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(b"  more\nINFO next\n"))
+            .unwrap();
+        let rows = wait_rows(&state, 4).await;
+        assert!(rows[2].starts_with("INFO last") && rows[2].contains("more"));
+        assert!(rows[3].starts_with("INFO next"));
+
+        operation_api.cancellation_token().cancel();
+        assert!(tailing.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_tail_static_syslog() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("syslog");
+        // the last message isn't terminated
+        fs::write(&path, "<13>1 - - app - - - one\n<13>1 - - app - - - two").unwrap();
+        let parser = ParserType::Syslog(SyslogParserSettings::default());
+        let (operation_api, state, tailing) = tail(FileFormat::Text, path, parser).await;
+        let rows = wait_rows(&state, 2).await;
+        assert!(rows[0].ends_with("one"));
+        assert!(rows[1].ends_with("two"));
+
+        operation_api.cancellation_token().cancel();
+        assert!(tailing.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_observe_static_capture() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.pcapng");
+        // the datagram waits for the record of the connection, which is continued last
+        fs::write(
+            &path,
+            pcapng(&[
+                tcp_frame(1000, b"INFO first\n"),
+                udp_frame(b"INFO datagram\n"),
+                tcp_frame(1011, b"  detail\n"),
+            ]),
+        )
+        .unwrap();
+        let parser = ParserType::Text(TextParserSettings {
+            record_start: Some(String::from("^INFO")),
+            ..Default::default()
+        });
+        let (operation_api, state) = session();
+        let observing = tokio::spawn({
+            let (operation_api, state) = (operation_api.clone(), state.clone());
+            async move {
+                observe_file(
+                    operation_api,
+                    state,
+                    "test",
+                    &FileFormat::PcapNG,
+                    &path,
+                    &parser,
+                    None,
+                )
+                .await
+            }
+        });
+        let rows = wait_rows(&state, 2).await;
+        assert!(rows[0].starts_with("INFO first") && rows[0].contains("detail"));
+        assert_eq!(rows[1], "INFO datagram");
+
+        operation_api.cancellation_token().cancel();
+        assert!(observing.await.unwrap().is_ok());
+    }
 }
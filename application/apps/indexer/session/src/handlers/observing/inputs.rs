@@ -0,0 +1,167 @@
+use crate::{
+    events::{NativeError, NativeErrorKind},
+    paths,
+    progress::Severity,
+};
+use log::{debug, warn};
+use sources::{
+    archive::{split_entry_path, Archive},
+    compression::Compression,
+    factory::ObserveOrigin,
+};
+use std::{
+    cmp::Reverse,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use tempfile::NamedTempFile;
+
+/// Cached copies which weren't used for this time are removed
+const MAX_COPY_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// Limit of the size of all cached copies; the least recently used are removed first
+const MAX_COPIES_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Replaces the paths of files in archives (e.g. `bundle.zip/logs/app.dlt`) by the paths
+/// of plain copies. Only the observed files are extracted, never the whole archive.
+/// Compressed files are kept; they are decompressed while they are read.
+pub async fn resolve_files(origin: &mut ObserveOrigin) -> Result<(), NativeError> {
+    let filenames = match origin {
+        ObserveOrigin::File(_, _, filename) => vec![filename],
+        ObserveOrigin::Concat(files) => files.iter_mut().map(|(_, _, filename)| filename).collect(),
+        ObserveOrigin::Stream(..) | ObserveOrigin::Directory(..) => vec![],
+    };
+    for filename in filenames {
+        let src = filename.clone();
+        let copy = tokio::task::spawn_blocking(move || extracted_copy(&src))
+            .await
+            .map_err(|e| NativeError {
+                severity: Severity::ERROR,
+                kind: NativeErrorKind::Io,
+                message: Some(format!("Fail to prepare file: {e}")),
+            })??;
+        if let Some(copy) = copy {
+            *filename = copy;
+        }
+    }
+    Ok(())
+}
+
+/// Returns `true` if the file is compressed and has to be decompressed while it is read
+pub fn is_compressed(filename: &Path) -> Result<bool, NativeError> {
+    Compression::of_file(filename)
+        .map(|compression| compression.is_some())
+        .map_err(|e| io_error(filename, e))
+}
+
+/// Returns the extracted copy of a file in an archive or `None` for other paths
+fn extracted_copy(src: &Path) -> Result<Option<PathBuf>, NativeError> {
+    let Some((archive_path, entry)) = split_entry_path(src) else {
+        return Ok(None);
+    };
+    let archive = Archive::open(&archive_path)
+        .map_err(|e| io_error(&archive_path, e))?
+        .ok_or_else(|| NativeError {
+            severity: Severity::ERROR,
+            kind: NativeErrorKind::Io,
+            message: Some(format!(
+                "{} doesn't exist and {} isn't an archive",
+                src.to_string_lossy(),
+                archive_path.to_string_lossy()
+            )),
+        })?;
+    let name = src
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    cached_copy(&archive_path, &entry, &name, |dest| {
+        archive.extract(&entry, dest)
+    })
+    .map(Some)
+}
+
+/// Returns the cached copy of content derived from `src`, creating it with `create`
+/// if needed. Copies are cached by the path, size and modification time of `src` and
+/// by `key`.
+fn cached_copy<F>(src: &Path, key: &str, name: &str, create: F) -> Result<PathBuf, NativeError>
+where
+    F: FnOnce(&mut File) -> io::Result<u64>,
+{
+    let metadata = fs::metadata(src).map_err(|e| io_error(src, e))?;
+    let key = format!(
+        "{}:{}:{:?}:{key}",
+        src.canonicalize()
+            .unwrap_or_else(|_| src.to_path_buf())
+            .display(),
+        metadata.len(),
+        metadata.modified().ok()
+    );
+    let hash = blake3::hash(key.as_bytes()).to_hex();
+    let hash = &hash.as_str()[..16];
+    let dir = paths::get_decompressed_dir()?;
+    let dest = dir.join(format!("{hash}-{name}"));
+    if dest.exists() {
+        debug!("Using cached copy {}", dest.to_string_lossy());
+        // the modification time tells when a copy was used last
+        if let Err(err) = File::options()
+            .append(true)
+            .open(&dest)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+        {
+            warn!("Fail to mark {} as used: {err}", dest.to_string_lossy());
+        }
+        return Ok(dest);
+    }
+    // a copy is used only once it is complete; an incomplete one is removed on failure
+    let mut part = NamedTempFile::new_in(&dir).map_err(|e| io_error(&dir, e))?;
+    let written = create(part.as_file_mut()).map_err(|e| io_error(src, e))?;
+    part.persist(&dest).map_err(|e| io_error(src, e.error))?;
+    debug!(
+        "Copied {written} bytes of {} into {}",
+        src.to_string_lossy(),
+        dest.to_string_lossy()
+    );
+    if let Err(err) = evict_copies(&dir, &dest) {
+        warn!("Fail to remove cached copies: {err}");
+    }
+    Ok(dest)
+}
+
+/// Removes the cached copies which weren't used for [`MAX_COPY_AGE`] and the least
+/// recently used copies beyond [`MAX_COPIES_SIZE`], except `keep`
+fn evict_copies(dir: &Path, keep: &Path) -> io::Result<()> {
+    let mut copies = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() && entry.path() != keep {
+            copies.push((metadata.modified()?, metadata.len(), entry.path()));
+        }
+    }
+    // the most recently used first
+    copies.sort_by_key(|(modified, ..)| Reverse(*modified));
+    let mut size = fs::metadata(keep)?.len();
+    for (modified, len, path) in copies {
+        let expired = modified.elapsed().is_ok_and(|age| age > MAX_COPY_AGE);
+        size += len;
+        if expired || size > MAX_COPIES_SIZE {
+            debug!("Removing cached copy {}", path.to_string_lossy());
+            // a copy which is still open might not be removable (e.g. on Windows); it's
+            // tried again with the next eviction
+            if let Err(err) = fs::remove_file(&path) {
+                debug!("Fail to remove {}: {err}", path.to_string_lossy());
+            }
+            size -= len;
+        }
+    }
+    Ok(())
+}
+
+fn io_error(src: &Path, e: std::io::Error) -> NativeError {
+    NativeError {
+        severity: Severity::ERROR,
+        kind: NativeErrorKind::Io,
+        message: Some(format!("Fail to read {}: {e}", src.to_string_lossy())),
+    }
+}
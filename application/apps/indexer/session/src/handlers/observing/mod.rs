@@ -1,26 +1,31 @@
 use std::path::PathBuf;
 
 use crate::{
+    events::{CallbackEvent, NativeError, NativeErrorKind},
     operations::{OperationAPI, OperationResult},
+    progress::{Notification, Progress, Severity},
     state::SessionStateAPI,
     tail,
 };
 use log::trace;
 use parsers::{
+    columns::ColumnsParser,
     dlt::{fmt::FormatOptions, DltParser},
+    json::JsonParser,
     someip::{FibexMetadata as FibexSomeipMetadata, SomeipParser},
+    syslog::{self, SyslogParser},
     text::StringTokenizer,
     LogMessage, MessageStreamItem, ParseYield, Parser,
 };
 use sources::{
-    factory::ParserType,
+    factory::{ColumnsParserSettings, JsonLinesParserSettings, ParserType, TextParserSettings},
     producer::{MessageProducer, SdeReceiver},
-    ByteSource,
+    ByteSource, SourceFilter,
 };
 use tokio::{
     select,
     sync::mpsc::Receiver,
-    time::{timeout, Duration},
+    time::{sleep, timeout, Duration},
 };
 use tokio_stream::StreamExt;
 
@@ -31,7 +36,9 @@ enum Next<T: LogMessage> {
 }
 
 pub mod concat;
+pub mod directory;
 pub mod file;
+pub mod inputs;
 pub mod stream;
 
 pub const FLUSH_TIMEOUT_IN_MS: u128 = 500;
@@ -42,6 +49,7 @@ pub async fn run_source<S: ByteSource>(
     source: S,
     source_id: u16,
     parser: &ParserType,
+    filter: Option<SourceFilter>,
     rx_sde: Option<SdeReceiver>,
     rx_tail: Option<Receiver<Result<(), tail::Error>>>,
 ) -> OperationResult<()> {
@@ -55,6 +63,7 @@ pub async fn run_source<S: ByteSource>(
         source,
         source_id,
         parser,
+        filter,
         rx_sde,
         rx_tail,
     )
@@ -74,6 +83,7 @@ async fn run_source_intern<S: ByteSource>(
     source: S,
     source_id: u16,
     parser: &ParserType,
+    filter: Option<SourceFilter>,
     rx_sde: Option<SdeReceiver>,
     rx_tail: Option<Receiver<Result<(), tail::Error>>>,
 ) -> OperationResult<()> {
@@ -85,11 +95,43 @@ async fn run_source_intern<S: ByteSource>(
                 }
                 None => SomeipParser::new(),
             };
-            let producer = MessageProducer::new(someip_parser, source, rx_sde);
+            let producer = MessageProducer::new(someip_parser, source, rx_sde).with_filter(filter);
             run_producer(operation_api, state, source_id, producer, rx_tail).await
         }
-        ParserType::Text => {
-            let producer = MessageProducer::new(StringTokenizer {}, source, rx_sde);
+        ParserType::Text(settings) => {
+            let producer =
+                MessageProducer::new(text_tokenizer(settings)?, source, rx_sde).with_filter(filter);
+            run_producer(operation_api, state, source_id, producer, rx_tail).await
+        }
+        ParserType::Columns(settings) => {
+            let columns_parser = columns_parser(settings)?;
+            operation_api.emit(CallbackEvent::ColumnsUpdated {
+                source_id,
+                columns: columns_parser.columns(),
+            });
+            let producer = MessageProducer::new(columns_parser, source, rx_sde).with_filter(filter);
+            run_producer(operation_api, state, source_id, producer, rx_tail).await
+        }
+        ParserType::JsonLines(settings) => {
+            let json_parser = json_parser(settings)?;
+            operation_api.emit(CallbackEvent::ColumnsUpdated {
+                source_id,
+                columns: json_parser.columns(),
+            });
+            let producer = MessageProducer::new(json_parser, source, rx_sde).with_filter(filter);
+            run_producer(operation_api, state, source_id, producer, rx_tail).await
+        }
+        ParserType::Syslog(settings) => {
+            operation_api.emit(CallbackEvent::ColumnsUpdated {
+                source_id,
+                columns: syslog::COLUMNS.iter().map(|c| c.to_string()).collect(),
+            });
+            let syslog_parser = if settings.datagrams {
+                SyslogParser::datagrams()
+            } else {
+                SyslogParser::default()
+            };
+            let producer = MessageProducer::new(syslog_parser, source, rx_sde).with_filter(filter);
             run_producer(operation_api, state, source_id, producer, rx_tail).await
         }
         ParserType::Dlt(settings) => {
@@ -104,12 +146,49 @@ async fn run_source_intern<S: ByteSource>(
                 someip_metadata.as_ref(),
                 settings.with_storage_header,
             );
-            let producer = MessageProducer::new(dlt_parser, source, rx_sde);
+            let producer = MessageProducer::new(dlt_parser, source, rx_sde).with_filter(filter);
             run_producer(operation_api, state, source_id, producer, rx_tail).await
         }
     }
 }
 
+/// Creates the tokenizer for text sources as configured in the settings
+pub(crate) fn text_tokenizer(
+    settings: &TextParserSettings,
+) -> Result<StringTokenizer, NativeError> {
+    let tokenizer = StringTokenizer::new(settings.encoding);
+    match settings.record_start.as_ref() {
+        Some(pattern) => tokenizer
+            .with_record_start(pattern)
+            .map_err(|e| NativeError {
+                severity: Severity::ERROR,
+                kind: NativeErrorKind::Configuration,
+                message: Some(e.to_string()),
+            }),
+        None => Ok(tokenizer),
+    }
+}
+
+/// Creates the parser for text sources with custom columns as configured in the settings
+pub(crate) fn columns_parser(
+    settings: &ColumnsParserSettings,
+) -> Result<ColumnsParser, NativeError> {
+    ColumnsParser::new(&settings.pattern, settings.encoding).map_err(|e| NativeError {
+        severity: Severity::ERROR,
+        kind: NativeErrorKind::Configuration,
+        message: Some(e.to_string()),
+    })
+}
+
+/// Creates the parser for JSON Lines sources as configured in the settings
+pub(crate) fn json_parser(settings: &JsonLinesParserSettings) -> Result<JsonParser, NativeError> {
+    JsonParser::new(settings.keys.clone(), settings.encoding).map_err(|e| NativeError {
+        severity: Severity::ERROR,
+        kind: NativeErrorKind::Configuration,
+        message: Some(e.to_string()),
+    })
+}
+
 async fn run_producer<T: LogMessage, P: Parser<T>, S: ByteSource>(
     operation_api: OperationAPI,
     state: SessionStateAPI,
@@ -121,89 +200,135 @@ async fn run_producer<T: LogMessage, P: Parser<T>, S: ByteSource>(
     state.set_session_file(None).await?;
     operation_api.processing();
     let cancel = operation_api.cancellation_token();
-    let stream = producer.as_stream();
-    futures::pin_mut!(stream);
-    let cancel_on_tail = cancel.clone();
-    while let Some(next) = select! {
-        next_from_stream = async {
-            match timeout(Duration::from_millis(FLUSH_TIMEOUT_IN_MS as u64), stream.next()).await {
-                Ok(items) => {
-                    if let Some(items) = items {
-                        Some(Next::Items(items))
-                    } else {
-                        Some(Next::Waiting)
+    let mut file_read = false;
+    // the end of a tailed source is temporary until the tail stops
+    producer = producer.with_tailing(rx_tail.is_some());
+    let mut finishing = false;
+    // the data held back at the end of a tailed source is delivered once it's idle
+    let mut flushed = false;
+    let mut reported = None;
+    loop {
+        let mut exhausted = false;
+        // the stream borrows the producer until the source is exhausted
+        {
+            let stream = producer.as_stream();
+            futures::pin_mut!(stream);
+            while let Some(next) = select! {
+                next_from_stream = async {
+                    match timeout(Duration::from_millis(FLUSH_TIMEOUT_IN_MS as u64), stream.next()).await {
+                        Ok(items) => {
+                            if let Some(items) = items {
+                                Some(Next::Items(items))
+                            } else {
+                                Some(Next::Waiting)
+                            }
+                        },
+                        Err(_) => Some(Next::Timeout),
                     }
-                },
-                Err(_) => Some(Next::Timeout),
-            }
-        } => next_from_stream,
-        _ = cancel.cancelled() => None,
-    } {
-        match next {
-            Next::Items(items) => {
-                for (_, item) in items {
-                    match item {
-                        MessageStreamItem::Item(ParseYield::Message(item)) => {
-                            state
-                                .write_session_file(source_id, format!("{item}\n"))
-                                .await?;
-                        }
-                        MessageStreamItem::Item(ParseYield::MessageAndAttachment((
-                            item,
-                            attachment,
-                        ))) => {
-                            state
-                                .write_session_file(source_id, format!("{item}\n"))
-                                .await?;
-                            state.add_attachment(attachment)?;
-                        }
-                        MessageStreamItem::Item(ParseYield::Attachment(attachment)) => {
-                            state.add_attachment(attachment)?;
+                } => next_from_stream,
+                // the rest of a source at its end is delivered even if cancelled
+                _ = cancel.cancelled(), if !finishing => None,
+            } {
+                match next {
+                    Next::Items(items) => {
+                        for (_, item) in items {
+                            match item {
+                                MessageStreamItem::Item(ParseYield::Message(item)) => {
+                                    state
+                                        .write_session_file(source_id, format!("{item}\n"))
+                                        .await?;
+                                }
+                                MessageStreamItem::Item(ParseYield::MessageAndAttachment((
+                                    item,
+                                    attachment,
+                                ))) => {
+                                    state
+                                        .write_session_file(source_id, format!("{item}\n"))
+                                        .await?;
+                                    state.add_attachment(attachment)?;
+                                }
+                                MessageStreamItem::Item(ParseYield::Attachment(attachment)) => {
+                                    state.add_attachment(attachment)?;
+                                }
+                                MessageStreamItem::Done => {
+                                    trace!("observe, message stream is done");
+                                    state.flush_session_file().await?;
+                                    // a tailed source reaches its end again with each update
+                                    if !file_read {
+                                        file_read = true;
+                                        state.file_read().await?;
+                                    }
+                                }
+                                // MessageStreamItem::FileRead => {
+                                //     state.file_read().await?;
+                                // }
+                                MessageStreamItem::Skipped => {
+                                    trace!("observe: skipped a message");
+                                }
+                                MessageStreamItem::Incomplete => {
+                                    trace!("observe: incomplete message");
+                                }
+                                MessageStreamItem::Empty => {
+                                    trace!("observe: empty message");
+                                }
+                            }
                         }
-                        MessageStreamItem::Done => {
-                            trace!("observe, message stream is done");
+                    }
+                    Next::Timeout => {
+                        if !state.is_closing() {
                             state.flush_session_file().await?;
-                            state.file_read().await?;
-                        }
-                        // MessageStreamItem::FileRead => {
-                        //     state.file_read().await?;
-                        // }
-                        MessageStreamItem::Skipped => {
-                            trace!("observe: skipped a message");
-                        }
-                        MessageStreamItem::Incomplete => {
-                            trace!("observe: incomplete message");
-                        }
-                        MessageStreamItem::Empty => {
-                            trace!("observe: empty message");
                         }
                     }
-                }
-            }
-            Next::Timeout => {
-                if !state.is_closing() {
-                    state.flush_session_file().await?;
-                }
-            }
-            Next::Waiting => {
-                if let Some(mut rx_tail) = rx_tail.take() {
-                    if select! {
-                        next_from_stream = rx_tail.recv() => {
-                            if let Some(result) = next_from_stream {
-                                result.is_err()
-                            } else {
-                                true
-                            }
-                        },
-                        _ = cancel_on_tail.cancelled() => true,
-                    } {
+                    Next::Waiting => {
+                        exhausted = true;
                         break;
                     }
-                } else {
-                    break;
                 }
             }
         }
+        // problems with the input are known once the source was finished or flushed
+        if let Some(report) = producer
+            .report()
+            .filter(|report| reported.as_ref() != Some(report))
+        {
+            operation_api.emit(CallbackEvent::Progress {
+                uuid: operation_api.id(),
+                progress: Progress::Notification(Notification {
+                    severity: Severity::WARNING,
+                    content: report.clone(),
+                    line: None,
+                }),
+            });
+            reported = Some(report);
+        }
+        if finishing || !exhausted {
+            break;
+        }
+        // the source is exhausted; a tailed source continues as soon as it has grown
+        let Some(rx_tail) = rx_tail.as_mut() else {
+            break;
+        };
+        let updated = select! {
+            next_from_tail = rx_tail.recv() => Some(matches!(next_from_tail, Some(Ok(())))),
+            _ = cancel.cancelled() => Some(false),
+            _ = sleep(Duration::from_millis(FLUSH_TIMEOUT_IN_MS as u64)), if !flushed => None,
+        };
+        match updated {
+            Some(true) => {
+                flushed = false;
+                producer.resume();
+            }
+            Some(false) => {
+                // no more data follows, records held back by the parser are delivered now
+                finishing = true;
+                producer.finish();
+            }
+            None => {
+                // the source is idle, records held back are delivered until it grows
+                flushed = true;
+                producer.flush();
+            }
+        }
     }
     debug!("listen done");
     Ok(None)
@@ -5,12 +5,25 @@ use crate::{
     progress::Severity,
     state::SessionStateAPI,
 };
+use futures::{future, stream::FuturesUnordered, Future, StreamExt};
+use log::{debug, warn};
+#[cfg(unix)]
+use sources::socket::unix::UnixSource;
 use sources::{
-    command::process::ProcessSource,
-    factory::{ParserType, Transport},
-    producer::SdeReceiver,
+    command::process::{ProcessExit, ProcessSource},
+    factory::{
+        ParserType, ProcessTransportConfig, ReconnectPolicy, SyslogParserSettings,
+        TCPServerTransportConfig, Transport,
+    },
+    producer::{SdeReceiver, SdeSender},
     serial::serialport::SerialSource,
-    socket::{tcp::TcpSource, udp::UdpSource},
+    socket::{tcp::TcpSource, tcp_server::TcpServer, udp::UdpSource, websocket::WebSocketSource},
+    ByteSource,
+};
+use tokio::{
+    select,
+    sync::mpsc::{error::SendError, unbounded_channel},
+    time::sleep,
 };
 
 pub async fn observe_stream<'a>(
@@ -21,9 +34,9 @@ pub async fn observe_stream<'a>(
     parser: &'a ParserType,
     rx_sde: Option<SdeReceiver>,
 ) -> OperationResult<()> {
-    let source_id = state.add_source(uuid).await?;
     match transport {
         Transport::UDP(settings) => {
+            let source_id = state.add_source(uuid).await?;
             let udp_source = UdpSource::new(&settings.bind_addr, settings.multicast.clone())
                 .await
                 .map_err(|e| NativeError {
@@ -31,41 +44,81 @@ pub async fn observe_stream<'a>(
                     kind: NativeErrorKind::Interrupted,
                     message: Some(format!("{e}")),
                 })?;
+            // each datagram contains complete syslog messages
+            let parser = with_complete_messages(parser);
             observing::run_source(
                 operation_api,
                 state,
                 udp_source,
                 source_id,
-                parser,
+                &parser,
+                None,
                 rx_sde,
                 None,
             )
             .await
         }
+        Transport::TCPServer(settings) => {
+            serve_tcp_clients(operation_api, state, settings, parser).await
+        }
         Transport::TCP(settings) => {
-            let tcp_source = TcpSource::new(settings.bind_addr.clone())
-                .await
-                .map_err(|e| NativeError {
-                    severity: Severity::ERROR,
-                    kind: NativeErrorKind::Interrupted,
-                    message: Some(format!("{e}")),
-                })?;
+            let source_id = state.add_source(uuid).await?;
+            let connect = move || async move {
+                TcpSource::new(settings.bind_addr.clone())
+                    .await
+                    .map_err(|e| format!("{e}"))
+            };
+            if let Some(policy) = settings.reconnect.as_ref() {
+                return observe_reconnecting(
+                    operation_api,
+                    state,
+                    source_id,
+                    parser,
+                    rx_sde,
+                    &settings.bind_addr,
+                    policy,
+                    connect,
+                )
+                .await;
+            }
+            let tcp_source = connect().await.map_err(|e| NativeError {
+                severity: Severity::ERROR,
+                kind: NativeErrorKind::Interrupted,
+                message: Some(e),
+            })?;
             observing::run_source(
                 operation_api,
                 state,
                 tcp_source,
                 source_id,
                 parser,
+                None,
                 rx_sde,
                 None,
             )
             .await
         }
         Transport::Serial(settings) => {
-            let serial_source = SerialSource::new(settings).map_err(|e| NativeError {
+            let source_id = state.add_source(uuid).await?;
+            let connect =
+                move || async move { SerialSource::new(settings).map_err(|e| format!("{e}")) };
+            if let Some(policy) = settings.reconnect.as_ref() {
+                return observe_reconnecting(
+                    operation_api,
+                    state,
+                    source_id,
+                    parser,
+                    rx_sde,
+                    &settings.path,
+                    policy,
+                    connect,
+                )
+                .await;
+            }
+            let serial_source = connect().await.map_err(|e| NativeError {
                 severity: Severity::ERROR,
                 kind: NativeErrorKind::Interrupted,
-                message: Some(format!("{e}")),
+                message: Some(e),
             })?;
             observing::run_source(
                 operation_api,
@@ -73,33 +126,324 @@ pub async fn observe_stream<'a>(
                 serial_source,
                 source_id,
                 parser,
+                None,
                 rx_sde,
                 None,
             )
             .await
         }
+        // the caller is interested in the observing only, not in how the process has ended
         Transport::Process(settings) => {
-            let process_source = ProcessSource::new(
-                settings.command.clone(),
-                settings.cwd.clone(),
-                settings.envs.clone(),
+            observe_process(operation_api, state, uuid, settings, parser, rx_sde)
+                .await
+                .map(|_| None)
+        }
+        #[cfg(unix)]
+        Transport::Unix(settings) => {
+            let source_id = state.add_source(uuid).await?;
+            let unix_source = UnixSource::new(&settings.path, settings.socket_type)
+                .await
+                .map_err(|e| NativeError {
+                    severity: Severity::ERROR,
+                    kind: NativeErrorKind::Interrupted,
+                    message: Some(format!(
+                        "Fail to open socket {}: {e}",
+                        settings.path.to_string_lossy()
+                    )),
+                })?;
+            // each datagram contains complete syslog messages
+            let parser = if unix_source.is_datagram() {
+                with_complete_messages(parser)
+            } else {
+                parser.clone()
+            };
+            observing::run_source(
+                operation_api,
+                state,
+                unix_source,
+                source_id,
+                &parser,
+                None,
+                rx_sde,
+                None,
             )
             .await
-            .map_err(|e| NativeError {
-                severity: Severity::ERROR,
-                kind: NativeErrorKind::Interrupted,
-                message: Some(format!("{e}")),
-            })?;
+        }
+        Transport::WebSocket(settings) => {
+            let source_id = state.add_source(uuid).await?;
+            let websocket_source = WebSocketSource::connect(&settings.url, &settings.headers)
+                .await
+                .map_err(|e| NativeError {
+                    severity: Severity::ERROR,
+                    kind: NativeErrorKind::Interrupted,
+                    message: Some(format!("{e}")),
+                })?;
             observing::run_source(
                 operation_api,
                 state,
-                process_source,
+                websocket_source,
                 source_id,
                 parser,
+                None,
                 rx_sde,
                 None,
             )
             .await
         }
+        #[cfg(not(unix))]
+        Transport::Unix(_) => Err(NativeError {
+            severity: Severity::ERROR,
+            kind: NativeErrorKind::Configuration,
+            message: Some(String::from(
+                "Unix domain sockets are not supported on this platform",
+            )),
+        }),
+    }
+}
+
+/// Observes the output of a process; the result reports how the process has ended
+pub async fn observe_process(
+    operation_api: OperationAPI,
+    state: SessionStateAPI,
+    uuid: &str,
+    settings: &ProcessTransportConfig,
+    parser: &ParserType,
+    rx_sde: Option<SdeReceiver>,
+) -> OperationResult<ProcessExit> {
+    let source_id = state.add_source(uuid).await?;
+    let process_source = match settings.pty.as_ref() {
+        None => {
+            ProcessSource::new(
+                settings.command.clone(),
+                settings.cwd.clone(),
+                settings.envs.clone(),
+            )
+            .await
+        }
+        #[cfg(unix)]
+        Some(size) => {
+            ProcessSource::with_pty(
+                settings.command.clone(),
+                settings.cwd.clone(),
+                settings.envs.clone(),
+                size,
+            )
+            .await
+        }
+        #[cfg(not(unix))]
+        Some(_) => Err(sources::command::process::ProcessError::Setup(
+            String::from("Pseudo-terminals are not supported on this platform"),
+        )),
+    }
+    .map_err(|e| NativeError {
+        severity: Severity::ERROR,
+        kind: NativeErrorKind::Interrupted,
+        message: Some(format!("{e}")),
+    })?;
+    let exit = process_source.exit_report();
+    observing::run_source(
+        operation_api,
+        state,
+        process_source,
+        source_id,
+        parser,
+        None,
+        rx_sde,
+        None,
+    )
+    .await?;
+    // no exit is reported if the process was killed by stopping the observing
+    Ok(exit.get())
+}
+
+/// Observes the connection and connects again once it was lost, as long as the policy
+/// allows further attempts. The loss and the restoring of the connection are marked by
+/// rows; the rows of all connections belong to the same source.
+#[allow(clippy::too_many_arguments)]
+async fn observe_reconnecting<S, F, C>(
+    operation_api: OperationAPI,
+    state: SessionStateAPI,
+    source_id: u16,
+    parser: &ParserType,
+    mut rx_sde: Option<SdeReceiver>,
+    target: &str,
+    policy: &ReconnectPolicy,
+    connect: F,
+) -> OperationResult<()>
+where
+    S: ByteSource,
+    F: Fn() -> C,
+    C: Future<Output = Result<S, String>>,
+{
+    let cancel = operation_api.cancellation_token();
+    // the first connection has to succeed anyway
+    let mut source = connect().await.map_err(|e| NativeError {
+        severity: Severity::ERROR,
+        kind: NativeErrorKind::Interrupted,
+        message: Some(e),
+    })?;
+    loop {
+        // requests are passed to the source of the current connection
+        let (tx_requests, rx_requests) = match rx_sde {
+            Some(_) => {
+                let (tx_requests, rx_requests) = unbounded_channel();
+                (Some(tx_requests), Some(rx_requests))
+            }
+            None => (None, None),
+        };
+        select! {
+            result = observing::run_source(
+                operation_api.clone(),
+                state.clone(),
+                source,
+                source_id,
+                parser,
+                None,
+                rx_requests,
+                None,
+            ) => {
+                result?;
+            }
+            _ = pass_requests(&mut rx_sde, tx_requests) => {}
+        }
+        if cancel.is_cancelled() {
+            break;
+        }
+        write_marker(&state, source_id, &format!("connection to {target} lost")).await?;
+        let mut attempt = 0;
+        source = loop {
+            let Some(delay) = policy.delay(attempt) else {
+                write_marker(
+                    &state,
+                    source_id,
+                    &format!("connection to {target} not restored after {attempt} attempts"),
+                )
+                .await?;
+                return Err(NativeError {
+                    severity: Severity::ERROR,
+                    kind: NativeErrorKind::Interrupted,
+                    message: Some(format!(
+                        "Fail to restore connection to {target} after {attempt} attempts"
+                    )),
+                });
+            };
+            attempt += 1;
+            let connected = select! {
+                connected = async {
+                    sleep(delay).await;
+                    connect().await
+                } => connected,
+                _ = reject_requests(&mut rx_sde) => unreachable!("requests are rejected endlessly"),
+                _ = cancel.cancelled() => return Ok(None),
+            };
+            match connected {
+                Ok(source) => break source,
+                Err(err) => {
+                    debug!("Attempt {attempt} to connect to {target} failed: {err}");
+                }
+            }
+        };
+        write_marker(
+            &state,
+            source_id,
+            &format!("connection to {target} restored"),
+        )
+        .await?;
+    }
+    Ok(None)
+}
+
+/// Writes a row marking a change of the connection
+async fn write_marker(
+    state: &SessionStateAPI,
+    source_id: u16,
+    msg: &str,
+) -> Result<(), NativeError> {
+    state
+        .write_session_file(source_id, format!("--- {msg} ---\n"))
+        .await?;
+    state.flush_session_file().await
+}
+
+/// Passes the requests to the source of the current connection; never ends
+async fn pass_requests(rx_sde: &mut Option<SdeReceiver>, tx_requests: Option<SdeSender>) {
+    if let (Some(rx_sde), Some(tx_requests)) = (rx_sde.as_mut(), tx_requests) {
+        while let Some(msg) = rx_sde.recv().await {
+            if let Err(SendError((_, tx_response))) = tx_requests.send(msg) {
+                // the connection was lost right now
+                let _ = tx_response.send(Err(String::from("Connection is lost")));
+            }
+        }
+    }
+    future::pending().await
+}
+
+/// Answers the requests while there is no connection; never ends
+async fn reject_requests(rx_sde: &mut Option<SdeReceiver>) {
+    if let Some(rx_sde) = rx_sde.as_mut() {
+        while let Some((_, tx_response)) = rx_sde.recv().await {
+            let _ = tx_response.send(Err(String::from("Connection is lost")));
+        }
+    }
+    future::pending().await
+}
+
+/// Used for sources delivering complete messages only, so the syslog parser doesn't
+/// have to wait for the next message to see the end of the current one.
+fn with_complete_messages(parser: &ParserType) -> ParserType {
+    match parser {
+        ParserType::Syslog(_) => ParserType::Syslog(SyslogParserSettings { datagrams: true }),
+        parser => parser.clone(),
+    }
+}
+
+/// Accepts clients until the operation is cancelled. Each client is added as a source
+/// of its own, named by its address.
+async fn serve_tcp_clients(
+    operation_api: OperationAPI,
+    state: SessionStateAPI,
+    settings: &TCPServerTransportConfig,
+    parser: &ParserType,
+) -> OperationResult<()> {
+    let server = TcpServer::bind(&settings.bind_addr)
+        .await
+        .map_err(|e| NativeError {
+            severity: Severity::ERROR,
+            kind: NativeErrorKind::Interrupted,
+            message: Some(format!("Fail to listen on {}: {e}", settings.bind_addr)),
+        })?;
+    // the server delivers complete messages only
+    let parser = with_complete_messages(parser);
+    let cancel = operation_api.cancellation_token();
+    let mut clients = FuturesUnordered::new();
+    loop {
+        select! {
+            client = server.accept() => {
+                let client = match client {
+                    Ok(client) => client,
+                    Err(err) => {
+                        warn!("Fail to accept TCP client: {err}");
+                        continue;
+                    }
+                };
+                let source_id = state.add_source(&client.peer().to_string()).await?;
+                clients.push(observing::run_source(
+                    operation_api.clone(),
+                    state.clone(),
+                    client,
+                    source_id,
+                    &parser,
+                    None,
+                    None,
+                    None,
+                ));
+            }
+            Some(result) = clients.next(), if !clients.is_empty() => {
+                // client has disconnected
+                result?;
+            }
+            _ = cancel.cancelled() => break,
+        }
     }
+    Ok(None)
 }
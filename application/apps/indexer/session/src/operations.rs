@@ -131,9 +131,6 @@ pub enum OperationKind {
     },
     Merge {
         files: Vec<FileMergeOptions>,
-        out_path: PathBuf,
-        append: bool,
-        source_id: String,
     },
     GetNearestPosition(u64),
     Cancel {
@@ -279,6 +276,15 @@ impl OperationAPI {
         self.cancellation_token.clone()
     }
 
+    /// API for a part of the operation, which is cancelled with the operation but can
+    /// fail and be cancelled on its own
+    pub fn child(&self) -> Self {
+        OperationAPI {
+            cancellation_token: self.cancellation_token.child_token(),
+            ..self.clone()
+        }
+    }
+
     pub async fn execute(
         &self,
         operation: Operation,
@@ -407,13 +413,12 @@ impl OperationAPI {
                         }
                     }
                 }
-                OperationKind::Merge {
-                    files: _,
-                    out_path: _,
-                    append: _,
-                    source_id: _,
-                } => {
-                    unimplemented!("merging not yet supported");
+                OperationKind::Merge { files } => {
+                    api.finish(
+                        handlers::merge::merge_files(&api, state, files).await,
+                        operation_str,
+                    )
+                    .await;
                 }
                 OperationKind::Sleep(ms, ignore_cancellation) => {
                     api.finish(
@@ -7,6 +7,7 @@ use std::path::PathBuf;
 
 const CHIPMUNK_HOME: &str = ".chipmunk";
 const CHIPMUNK_TMP: &str = "tmp";
+const CHIPMUNK_DECOMPRESSED: &str = "decompressed";
 
 pub fn get_home_dir() -> Result<PathBuf, NativeError> {
     if let Some(home) = dirs::home_dir().take() {
@@ -35,3 +36,20 @@ pub fn get_streams_dir() -> Result<PathBuf, NativeError> {
     }
     Ok(streams)
 }
+
+/// Folder of the cached plain copies of files in archives
+pub fn get_decompressed_dir() -> Result<PathBuf, NativeError> {
+    let decompressed = get_streams_dir()?.join(CHIPMUNK_DECOMPRESSED);
+    if !decompressed.exists() {
+        std::fs::create_dir(&decompressed).map_err(|e| NativeError {
+            severity: Severity::ERROR,
+            kind: NativeErrorKind::Io,
+            message: Some(format!(
+                "Fail to create folder for decompressed files {}: {}",
+                decompressed.to_string_lossy(),
+                e
+            )),
+        })?;
+    }
+    Ok(decompressed)
+}
@@ -9,6 +9,7 @@ use crate::{
 };
 use futures::Future;
 use log::{debug, error, warn};
+use merging::merger::FileMergeOptions;
 use processor::{grabber::LineRange, search::filter::SearchFilter};
 use sources::{factory::ObserveOptions, sde};
 use std::{ops::RangeInclusive, path::PathBuf};
@@ -345,6 +346,20 @@ impl Session {
             .map_err(|e| ComputationError::Communication(e.to_string()))
     }
 
+    /// Merges the given files by the timestamps of their lines into the session.
+    pub fn merge(
+        &self,
+        operation_id: Uuid,
+        files: Vec<FileMergeOptions>,
+    ) -> Result<(), ComputationError> {
+        self.tx_operations
+            .send(Operation::new(
+                operation_id,
+                operations::OperationKind::Merge { files },
+            ))
+            .map_err(|e| ComputationError::Communication(e.to_string()))
+    }
+
     pub async fn get_sources(&self) -> Result<Vec<SourceDefinition>, ComputationError> {
         self.state
             .get_sources_definitions()
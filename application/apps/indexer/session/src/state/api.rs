@@ -18,7 +18,7 @@ use processor::{
     search::searchers::{regular::RegularSearchHolder, values::ValueSearchHolder},
 };
 use sources::factory::ObserveOptions;
-use std::{collections::HashMap, fmt::Display, ops::RangeInclusive, path::PathBuf};
+use std::{collections::HashMap, fmt::Display, fs::File, ops::RangeInclusive, path::PathBuf};
 use tokio::sync::{
     mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
     oneshot,
@@ -31,6 +31,7 @@ use super::values::graph::CandlePoint;
 pub enum Api {
     SetSessionFile((Option<PathBuf>, oneshot::Sender<Result<(), NativeError>>)),
     GetSessionFile(oneshot::Sender<Result<PathBuf, NativeError>>),
+    DetachSessionFile((Option<File>, oneshot::Sender<Result<(), NativeError>>)),
     WriteSessionFile((u16, String, oneshot::Sender<Result<(), NativeError>>)),
     FlushSessionFile(oneshot::Sender<Result<(), NativeError>>),
     GetSessionFileOrigin(oneshot::Sender<Result<Option<SessionFileOrigin>, NativeError>>),
@@ -186,6 +187,7 @@ impl Display for Api {
             match self {
                 Self::SetSessionFile(_) => "SetSessionFile",
                 Self::GetSessionFile(_) => "GetSessionFile",
+                Self::DetachSessionFile(_) => "DetachSessionFile",
                 Self::WriteSessionFile(_) => "WriteSessionFile",
                 Self::FlushSessionFile(_) => "FlushSessionFile",
                 Self::GetSessionFileOrigin(_) => "GetSessionFileOrigin",
@@ -392,6 +394,14 @@ impl SessionStateAPI {
             .await?
     }
 
+    /// Replaces the linked session file by a generated copy of `linked`, the file which
+    /// was indexed, so rows can be written to the session
+    pub async fn detach_session_file(&self, linked: Option<File>) -> Result<(), NativeError> {
+        let (tx, rx) = oneshot::channel();
+        self.exec_operation(Api::DetachSessionFile((linked, tx)), rx)
+            .await?
+    }
+
     pub async fn get_session_file(&self) -> Result<PathBuf, NativeError> {
         let (tx, rx) = oneshot::channel();
         self.exec_operation(Api::GetSessionFile(tx), rx).await?
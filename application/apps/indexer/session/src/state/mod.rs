@@ -203,6 +203,44 @@ impl SessionState {
         }
     }
 
+    async fn handle_detach_session_file(
+        &mut self,
+        linked: Option<File>,
+        state_cancellation_token: CancellationToken,
+        tx_callback_events: UnboundedSender<CallbackEvent>,
+    ) -> Result<(), NativeError> {
+        let rows = self.session_file.len();
+        let unchanged = self.session_file.detach(linked)?;
+        let filename = self.session_file.filename()?;
+        self.attachments.set_dest_path(filename.clone());
+        if unchanged {
+            // searches continue on the copy
+            if let SearcherState::Available(searcher) = &mut self.searchers.regular {
+                searcher.file_path = filename.clone();
+            }
+            if let SearcherState::Available(searcher) = &mut self.searchers.values {
+                searcher.file_path = filename;
+            }
+        } else {
+            if !self.searchers.regular.is_using() {
+                self.searchers.regular.not_inited();
+                self.search_map.set(None, None);
+                self.indexes.drop_search()?;
+                tx_callback_events.send(CallbackEvent::no_search_results())?;
+                tx_callback_events.send(CallbackEvent::SearchMapUpdated(None))?;
+            }
+            if !self.searchers.values.is_using() {
+                self.searchers.values.not_inited();
+            }
+        }
+        // rows written to the linked file after the last update are part of the copy
+        if self.session_file.len() > rows {
+            self.update_searchers(state_cancellation_token, tx_callback_events)
+                .await?;
+        }
+        Ok(())
+    }
+
     async fn update_searchers(
         &mut self,
         state_cancellation_token: CancellationToken,
@@ -414,6 +452,21 @@ pub async fn run(
                     NativeError::channel("Failed to response to Api::SetSessionFile")
                 })?;
             }
+            Api::DetachSessionFile((linked, tx_response)) => {
+                tx_response
+                    .send(
+                        state
+                            .handle_detach_session_file(
+                                linked,
+                                state_cancellation_token.clone(),
+                                tx_callback_events.clone(),
+                            )
+                            .await,
+                    )
+                    .map_err(|_| {
+                        NativeError::channel("Failed to respond to Api::DetachSessionFile")
+                    })?;
+            }
             Api::GetSessionFile(tx_response) => {
                 tx_response
                     .send(state.session_file.filename())
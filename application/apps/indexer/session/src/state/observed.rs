@@ -1,4 +1,7 @@
-use sources::factory::{FileFormat, ObserveOptions, ObserveOrigin, ParserType};
+use sources::{
+    factory::{FileFormat, ObserveOptions, ObserveOrigin, ParserType},
+    SourceFilter,
+};
 use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
@@ -18,27 +21,39 @@ impl Observed {
     pub fn is_file_based_export_possible(&self) -> bool {
         let mut possibility = true;
         self.executed.iter().for_each(|opt| {
-            if matches!(opt.origin, ObserveOrigin::Stream(..)) {
+            // the files of a directory change during observing
+            if matches!(
+                opt.origin,
+                ObserveOrigin::Stream(..) | ObserveOrigin::Directory(..)
+            ) {
                 possibility = false;
             }
         });
         possibility
     }
 
-    pub fn get_files(&self) -> Vec<(ParserType, FileFormat, PathBuf)> {
-        let mut files: Vec<(ParserType, FileFormat, PathBuf)> = vec![];
+    pub fn get_files(&self) -> Vec<(ParserType, Option<SourceFilter>, FileFormat, PathBuf)> {
+        let mut files: Vec<(ParserType, Option<SourceFilter>, FileFormat, PathBuf)> = vec![];
         self.executed.iter().for_each(|opt| match &opt.origin {
-            ObserveOrigin::File(_, file_format, filename) => {
-                files.push((opt.parser.clone(), file_format.clone(), filename.clone()))
-            }
+            ObserveOrigin::File(_, file_format, filename) => files.push((
+                opt.parser.clone(),
+                opt.filter.clone(),
+                file_format.clone(),
+                filename.clone(),
+            )),
             ObserveOrigin::Concat(list) => {
                 files.append(
                     &mut list
                         .iter()
                         .map(|(_, file_format, filename)| {
-                            (opt.parser.clone(), file_format.clone(), filename.clone())
+                            (
+                                opt.parser.clone(),
+                                opt.filter.clone(),
+                                file_format.clone(),
+                                filename.clone(),
+                            )
                         })
-                        .collect::<Vec<(ParserType, FileFormat, PathBuf)>>(),
+                        .collect::<Vec<(ParserType, Option<SourceFilter>, FileFormat, PathBuf)>>(),
                 );
             }
             _ => {}
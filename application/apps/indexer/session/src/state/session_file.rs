@@ -12,7 +12,7 @@ use processor::{
 use serde::{Deserialize, Serialize};
 use std::{
     fs::File,
-    io::{BufWriter, Write},
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
     path::PathBuf,
     time::Instant,
 };
@@ -116,6 +116,76 @@ impl SessionFile {
         }
     }
 
+    /// Replaces the linked file by a generated copy of its content, so rows can be added
+    /// to the session (e.g. after the linked file was rotated). `linked` is the file which
+    /// was indexed; it's copied completely, including rows written after the last update.
+    /// If it's missing or doesn't contain the indexed content anymore (e.g. it was
+    /// truncated), the content is lost: the first row explains it and the others are kept
+    /// as empty rows, so the row numbers don't change.
+    ///
+    /// Returns `false` if the content of the copy differs from the indexed content (e.g. a
+    /// line break was added to the last row), so searches have to be started over.
+    pub fn detach(&mut self, linked: Option<File>) -> Result<bool, NativeError> {
+        let Some(SessionFileOrigin::Linked(linked_filename)) = self.filename.clone() else {
+            return Err(NativeError {
+                severity: Severity::ERROR,
+                kind: NativeErrorKind::Grabber,
+                message: Some(String::from("Session file isn't linked")),
+            });
+        };
+        let (rows, bytes) = (self.len(), self.read_bytes());
+        let streams = paths::get_streams_dir()?;
+        let filename = streams.join(format!("{}.session", Uuid::new_v4()));
+        debug!("Session file detached: {}", filename.to_string_lossy());
+        let mut writer = BufWriter::new(File::create(&filename).map_err(|e| NativeError {
+            severity: Severity::ERROR,
+            kind: NativeErrorKind::Io,
+            message: Some(format!(
+                "Fail to create session writer for {}: {}",
+                filename.to_string_lossy(),
+                e
+            )),
+        })?);
+        let copied = match linked {
+            Some(mut linked) => {
+                let copied = io::copy(&mut linked, &mut writer)?;
+                if copied < bytes {
+                    None
+                } else if copied == 0 || ends_with_line_break(&mut linked)? {
+                    Some(true)
+                } else {
+                    // rows are added line by line
+                    writer.write_all(b"\n")?;
+                    Some(false)
+                }
+            }
+            None => None,
+        };
+        if copied.is_none() {
+            writer.flush()?;
+            writer.get_mut().set_len(0)?;
+            writer.seek(SeekFrom::Start(0))?;
+            if rows > 0 {
+                writeln!(
+                    writer,
+                    "--- {rows} row(s) of {} are lost, the file was changed before they could be copied ---",
+                    linked_filename.to_string_lossy()
+                )?;
+            }
+            io::copy(
+                &mut io::repeat(b'\n').take(rows.saturating_sub(1)),
+                &mut writer,
+            )?;
+        }
+        writer.flush()?;
+        let mut grabber = Grabber::lazy(TextFileSource::new(&filename))?;
+        grabber.update_from_file(None)?;
+        self.grabber = Some(Box::new(grabber));
+        self.writer = Some(writer);
+        self.filename = Some(SessionFileOrigin::Generated(filename));
+        Ok(copied.unwrap_or(rows == 0))
+    }
+
     #[allow(clippy::len_without_is_empty)]
     pub fn len(&mut self) -> u64 {
         if let Some(ref grabber) = self.grabber {
@@ -318,8 +388,65 @@ impl SessionFile {
     }
 }
 
+fn ends_with_line_break(file: &mut File) -> io::Result<bool> {
+    let mut last = [0u8];
+    file.seek(SeekFrom::Current(-1))?;
+    file.read_exact(&mut last)?;
+    Ok(last[0] == b'\n')
+}
+
 impl Default for SessionFile {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn linked(content: &str) -> (tempfile::TempDir, PathBuf, SessionFile) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("linked.log");
+        fs::write(&path, content).unwrap();
+        let mut session_file = SessionFile::new();
+        session_file.init(Some(path.clone())).unwrap();
+        session_file.update(0, CancellationToken::new()).unwrap();
+        (dir, path, session_file)
+    }
+
+    fn rows(session_file: &mut SessionFile) -> Vec<String> {
+        let len = session_file.len();
+        session_file
+            .grab(&LineRange::from(0..=len - 1))
+            .unwrap()
+            .into_iter()
+            .map(|row| row.content)
+            .collect()
+    }
+
+    #[test]
+    fn test_detach_rotated() {
+        let (_dir, path, mut session_file) = linked("one\ntwo\n");
+        let rotated = File::open(&path).unwrap();
+        fs::rename(&path, path.with_extension("1")).unwrap();
+        fs::write(&path, "new\n").unwrap();
+        assert!(session_file.detach(Some(rotated)).unwrap());
+        assert_eq!(rows(&mut session_file), vec!["one", "two"]);
+        session_file.cleanup().unwrap();
+    }
+
+    #[test]
+    fn test_detach_truncated() {
+        let (_dir, path, mut session_file) = linked("one\ntwo\nthree\n");
+        fs::write(&path, "").unwrap();
+        assert!(!session_file.detach(None).unwrap());
+        let rows = rows(&mut session_file);
+        // the row numbers are kept
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].starts_with("--- 3 row(s) of") && rows[0].contains("are lost"));
+        assert_eq!(rows[1..], [String::new(), String::new()]);
+        session_file.cleanup().unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+use log::{debug, warn};
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tokio::sync::Notify;
+
+/// Reader of a file which continues with the file at the same path after a rotation or
+/// truncation. The file is reopened on request only, once everything was read from the
+/// previous file. The read which reopens the file returns no data, so everything read
+/// afterwards belongs to the new file.
+pub struct FollowedFile {
+    path: PathBuf,
+    file: File,
+    reopen: Reopen,
+}
+
+/// Requests a [`FollowedFile`] to reopen its path and signals when it was reopened
+#[derive(Clone, Default)]
+pub struct Reopen(Arc<ReopenState>);
+
+#[derive(Default)]
+struct ReopenState {
+    requested: AtomicBool,
+    reopened: Notify,
+}
+
+impl Reopen {
+    pub fn request(&self) {
+        self.0.requested.store(true, Ordering::SeqCst);
+    }
+
+    /// Resolves as soon as the file was reopened on request
+    pub async fn reopened(&self) {
+        self.0.reopened.notified().await
+    }
+
+    fn take(&self) -> bool {
+        self.0.requested.swap(false, Ordering::SeqCst)
+    }
+}
+
+impl FollowedFile {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: File::open(path)?,
+            reopen: Reopen::default(),
+        })
+    }
+
+    pub fn reopen(&self) -> Reopen {
+        self.reopen.clone()
+    }
+}
+
+impl Read for FollowedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.file.read(buf)?;
+        if read > 0 || buf.is_empty() || !self.reopen.take() {
+            return Ok(read);
+        }
+        match File::open(&self.path) {
+            Ok(file) => {
+                debug!("Reading {} from the start", self.path.to_string_lossy());
+                self.file = file;
+                self.reopen.0.reopened.notify_one();
+                Ok(0)
+            }
+            Err(err) => {
+                // the file might be recreated yet
+                warn!("Fail to reopen {}: {err}", self.path.to_string_lossy());
+                self.reopen.request();
+                Ok(0)
+            }
+        }
+    }
+}
+
+impl Seek for FollowedFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[tokio::test]
+    async fn test_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "first\n").unwrap();
+        let mut followed = FollowedFile::open(&path).unwrap();
+        let reopen = followed.reopen();
+        let mut content = String::new();
+        followed.read_to_string(&mut content).unwrap();
+
+        fs::rename(&path, dir.path().join("app.log.1")).unwrap();
+        fs::write(&path, "second\n").unwrap();
+        followed.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "first\n");
+        reopen.request();
+        followed.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "first\n");
+        reopen.reopened().await;
+        followed.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "first\nsecond\n");
+    }
+}
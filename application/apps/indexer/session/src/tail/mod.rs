@@ -1,13 +1,28 @@
-use log::error;
-use std::path::Path;
+pub mod followed;
+
+use glob::Pattern;
+use log::{debug, warn};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::Metadata,
+    io,
+    path::{Path, PathBuf},
+};
 use thiserror::Error as ThisError;
 use tokio::{
-    sync::mpsc::Sender,
-    time::{timeout, Duration},
+    select,
+    sync::mpsc::{unbounded_channel, Sender, UnboundedReceiver, UnboundedSender},
+    time::{sleep, Duration},
 };
 use tokio_util::sync::CancellationToken;
 
+/// Interval of checks if the file cannot be watched
 const TRACKING_INTERVAL_MS: u64 = 1000;
+/// Interval of checks of a watched file, in case a notification gets lost (e.g. on
+/// network drives)
+const WATCHING_INTERVAL_MS: u64 = 5000;
 
 #[derive(ThisError, Debug)]
 pub enum Error {
@@ -17,37 +32,326 @@ pub enum Error {
     Channel(String),
 }
 
+/// Change of a tracked file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// Content was appended
+    Grown,
+    /// The file was truncated (e.g. by logrotate's `copytruncate`); its content has to
+    /// be read from the start again
+    Truncated,
+    /// The path refers to another file now (e.g. `app.log` was renamed to `app.log.1`
+    /// and a new `app.log` was created)
+    Rotated,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Grown => write!(f, "grown"),
+            Self::Truncated => write!(f, "truncated"),
+            Self::Rotated => write!(f, "rotated"),
+        }
+    }
+}
+
+/// Size and identity of the file behind a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileState {
+    size: u64,
+    /// Device and inode; a change of the file can be detected by its size only on
+    /// platforms without inodes
+    id: Option<(u64, u64)>,
+}
+
+impl FileState {
+    fn new(md: &Metadata) -> Self {
+        #[cfg(unix)]
+        let id = {
+            use std::os::unix::fs::MetadataExt;
+            Some((md.dev(), md.ino()))
+        };
+        #[cfg(not(unix))]
+        let id = None;
+        Self { size: md.len(), id }
+    }
+
+    fn change(&self, updated: &FileState) -> Option<Change> {
+        if self.id != updated.id {
+            Some(Change::Rotated)
+        } else if updated.size < self.size {
+            Some(Change::Truncated)
+        } else if updated.size > self.size {
+            Some(Change::Grown)
+        } else {
+            None
+        }
+    }
+}
+
+/// Sends the changes of the file at `path` until `shutdown` is cancelled. The file is
+/// watched for notifications of the file system; it's polled if watching isn't possible.
+/// A file which was removed (e.g. in the middle of a rotation) is waited for.
 pub async fn track(
     path: &Path,
-    tx_update: Sender<Result<(), Error>>,
+    tx_update: Sender<Result<Change, Error>>,
     shutdown: CancellationToken,
 ) -> Result<(), Error> {
     use tokio::fs;
-    let mut size = fs::metadata(path)
+    let mut state = fs::metadata(path)
         .await
-        .map(|md| md.len())
+        .map(|md| FileState::new(&md))
         .map_err(|e| Error::Io(e.to_string()))?;
+    let (tx_events, mut rx_events) = unbounded_channel();
+    let watcher = watch(path, tx_events.clone());
+    let interval = Duration::from_millis(if watcher.is_some() {
+        WATCHING_INTERVAL_MS
+    } else {
+        TRACKING_INTERVAL_MS
+    });
     loop {
-        match timeout(
-            Duration::from_millis(TRACKING_INTERVAL_MS),
-            shutdown.cancelled(),
-        )
-        .await
+        select! {
+            _ = shutdown.cancelled() => break,
+            _ = rx_events.recv() => {
+                // a single check covers all pending notifications
+                while rx_events.try_recv().is_ok() {}
+            },
+            _ = sleep(interval) => {},
+        }
+        let updated = match fs::metadata(path).await {
+            Ok(md) => FileState::new(&md),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                debug!("{} doesn't exist, waiting for it", path.to_string_lossy());
+                continue;
+            }
+            Err(err) => return Err(Error::Io(err.to_string())),
+        };
+        if let Some(change) = state.change(&updated) {
+            state = updated;
+            if change != Change::Grown {
+                debug!("{} was {change}", path.to_string_lossy());
+            }
+            if let Err(err) = tx_update.send(Ok(change)).await {
+                return Err(Error::Channel(format!("Fail to send update signal: {err}")));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Sends the paths of the files in `dir` whose names match `pattern` until `shutdown` is
+/// cancelled; the existing files first, then files as soon as they are created. Each file
+/// is sent once, unless it is received on `rx_dropped` (e.g. it couldn't be read yet); it
+/// is sent again with the next check then.
+pub async fn track_dir(
+    dir: &Path,
+    pattern: &Pattern,
+    tx_files: Sender<Result<PathBuf, Error>>,
+    mut rx_dropped: UnboundedReceiver<PathBuf>,
+    shutdown: CancellationToken,
+) -> Result<(), Error> {
+    let (tx_events, mut rx_events) = unbounded_channel();
+    let watcher = watch_dir(dir, |_| true, tx_events.clone());
+    let interval = Duration::from_millis(if watcher.is_some() {
+        WATCHING_INTERVAL_MS
+    } else {
+        TRACKING_INTERVAL_MS
+    });
+    let mut known = HashSet::new();
+    loop {
+        for file in matching_files(dir, pattern)
+            .await
+            .map_err(|e| Error::Io(e.to_string()))?
         {
-            Ok(_) => break,
-            Err(_) => {
-                let updated = fs::metadata(path)
-                    .await
-                    .map(|md| md.len())
-                    .map_err(|e| Error::Io(e.to_string()))?;
-                if updated != size {
-                    size = updated;
-                    if let Err(err) = tx_update.send(Ok(())).await {
-                        return Err(Error::Channel(format!("Fail to send update signal: {err}")));
-                    }
+            if known.insert(file.clone()) {
+                if let Err(err) = tx_files.send(Ok(file)).await {
+                    return Err(Error::Channel(format!("Fail to send file: {err}")));
                 }
             }
+        }
+        let next_check = sleep(interval);
+        tokio::pin!(next_check);
+        loop {
+            select! {
+                _ = shutdown.cancelled() => return Ok(()),
+                Some(file) = rx_dropped.recv() => {
+                    known.remove(&file);
+                    continue;
+                },
+                _ = rx_events.recv() => {
+                    while rx_events.try_recv().is_ok() {}
+                },
+                _ = &mut next_check => {},
+            }
+            break;
+        }
+    }
+}
+
+/// Returns the files in `dir` whose names match `pattern`, sorted by name
+async fn matching_files(dir: &Path, pattern: &Pattern) -> io::Result<Vec<PathBuf>> {
+    use tokio::fs;
+    let mut files = Vec::new();
+    let mut entries = fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if pattern.matches(&entry.file_name().to_string_lossy())
+            && fs::metadata(&path).await.is_ok_and(|md| md.is_file())
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Watches the directory of the file, so a file created by a rotation is noticed as well
+fn watch(path: &Path, tx_events: UnboundedSender<()>) -> Option<RecommendedWatcher> {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return None;
+    };
+    let name = name.to_os_string();
+    watch_dir(
+        dir,
+        move |path| path.file_name() == Some(name.as_os_str()),
+        tx_events,
+    )
+}
+
+/// Sends a notification for each event of the files in `dir` which are `relevant`
+fn watch_dir<F>(
+    dir: &Path,
+    relevant: F,
+    tx_events: UnboundedSender<()>,
+) -> Option<RecommendedWatcher>
+where
+    F: Fn(&Path) -> bool + Send + 'static,
+{
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            if event.paths.iter().any(|path| relevant(path)) {
+                let _ = tx_events.send(());
+            }
+        }
+    })
+    .and_then(|mut watcher| {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    });
+    match watcher {
+        Ok(watcher) => Some(watcher),
+        Err(err) => {
+            warn!(
+                "Fail to watch {}, polling it instead: {err}",
+                dir.to_string_lossy()
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, io::Write};
+    use tokio::{
+        sync::mpsc::{channel, Receiver},
+        time::timeout,
+    };
+
+    const WAITING_MS: u64 = WATCHING_INTERVAL_MS * 2;
+
+    async fn next<T>(rx: &mut Receiver<Result<T, Error>>) -> T {
+        timeout(Duration::from_millis(WAITING_MS), rx.recv())
+            .await
+            .expect("update is reported")
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_change() {
+        let state = FileState {
+            size: 10,
+            id: Some((1, 1)),
         };
+        let changed = |size, id| state.change(&FileState { size, id });
+        assert_eq!(changed(10, Some((1, 1))), None);
+        assert_eq!(changed(12, Some((1, 1))), Some(Change::Grown));
+        assert_eq!(changed(0, Some((1, 1))), Some(Change::Truncated));
+        assert_eq!(changed(20, Some((1, 2))), Some(Change::Rotated));
+    }
+
+    #[tokio::test]
+    async fn test_track_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "first\n").unwrap();
+        let (tx_update, mut rx_update) = channel(1);
+        let shutdown = CancellationToken::new();
+        let tracking = tokio::spawn({
+            let (path, shutdown) = (path.clone(), shutdown.clone());
+            async move { track(&path, tx_update, shutdown).await }
+        });
+        // let the watcher start
+        sleep(Duration::from_millis(100)).await;
+
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"second\n")
+            .unwrap();
+        assert_eq!(next(&mut rx_update).await, Change::Grown);
+
+        fs::OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        assert_eq!(next(&mut rx_update).await, Change::Truncated);
+
+        // without inodes a rotation is noticed as size change only
+        #[cfg(unix)]
+        {
+            fs::rename(&path, dir.path().join("app.log.1")).unwrap();
+            fs::write(&path, "third\n").unwrap();
+            assert_eq!(next(&mut rx_update).await, Change::Rotated);
+        }
+
+        shutdown.cancel();
+        assert!(tracking.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_track_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("b.log"), "").unwrap();
+        fs::write(dir.path().join("a.log"), "").unwrap();
+        fs::write(dir.path().join("notes.txt"), "").unwrap();
+        fs::create_dir(dir.path().join("old.log")).unwrap();
+        let (tx_files, mut rx_files) = channel(1);
+        let (tx_dropped, rx_dropped) = unbounded_channel();
+        let shutdown = CancellationToken::new();
+        let tracking = tokio::spawn({
+            let (dir, shutdown) = (dir.path().to_path_buf(), shutdown.clone());
+            async move {
+                let pattern = Pattern::new("*.log").unwrap();
+                track_dir(&dir, &pattern, tx_files, rx_dropped, shutdown).await
+            }
+        });
+        assert_eq!(next(&mut rx_files).await, dir.path().join("a.log"));
+        assert_eq!(next(&mut rx_files).await, dir.path().join("b.log"));
+        fs::write(dir.path().join("c.txt"), "").unwrap();
+        fs::write(dir.path().join("c.log"), "").unwrap();
+        assert_eq!(next(&mut rx_files).await, dir.path().join("c.log"));
+
+        tx_dropped.send(dir.path().join("a.log")).unwrap();
+        fs::write(dir.path().join("a.log"), "first\n").unwrap();
+        assert_eq!(next(&mut rx_files).await, dir.path().join("a.log"));
+
+        shutdown.cancel();
+        assert!(tracking.await.unwrap().is_ok());
     }
-    Ok(())
 }
@@ -114,6 +114,39 @@ impl UnboundSessionAPI {
             .await
     }
 
+    /// Detects the timestamp format of a text file using its first `max_lines` lines.
+    /// The result is a json list of `FormatCandidate`s, the most likely one first.
+    pub async fn detect_timestamp_format(
+        &self,
+        id: u64,
+        file_path: String,
+        max_lines: usize,
+    ) -> Result<CommandOutcome<String>, ComputationError> {
+        let (tx_results, rx_results) = oneshot::channel();
+        self.process_command(
+            id,
+            rx_results,
+            Command::DetectTimestampFormat(file_path, max_lines, tx_results),
+        )
+        .await
+    }
+
+    /// Lists the files of a zip or tar archive. The result is a json list of the
+    /// entries with their sizes and detected formats.
+    pub async fn list_archive_entries(
+        &self,
+        id: u64,
+        file_path: String,
+    ) -> Result<CommandOutcome<String>, ComputationError> {
+        let (tx_results, rx_results) = oneshot::channel();
+        self.process_command(
+            id,
+            rx_results,
+            Command::ListArchiveEntries(file_path, tx_results),
+        )
+        .await
+    }
+
     pub async fn spawn_process(
         &self,
         id: u64,
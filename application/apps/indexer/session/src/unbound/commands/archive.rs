@@ -0,0 +1,57 @@
+use super::CommandOutcome;
+use crate::{events::ComputationError, unbound::signal::Signal};
+use file_tools::detect_format;
+use serde::Serialize;
+use sources::{archive::Archive, compression::Compression};
+use std::path::Path;
+
+/// Count of bytes read from each file to detect its format
+const HEAD_LEN: usize = 10240;
+
+#[derive(Debug, Serialize)]
+struct ArchiveEntry {
+    /// Path of the file within the archive
+    path: String,
+    /// Path to observe the file with, e.g. `bundle.zip/logs/app.dlt`
+    filename: String,
+    size: u64,
+    format: String,
+    compressed: bool,
+}
+
+pub fn list_entries(
+    file_path: String,
+    signal: Signal,
+) -> Result<CommandOutcome<String>, ComputationError> {
+    let archive_path = Path::new(&file_path);
+    let archive = Archive::open(archive_path)
+        .map_err(|e| ComputationError::IoOperation(e.to_string()))?
+        .ok_or_else(|| {
+            ComputationError::OperationNotSupported(format!("{file_path} isn't an archive"))
+        })?;
+    let Some(entries) = archive
+        .entries(HEAD_LEN, Some(&signal.token()))
+        .map_err(|e| ComputationError::IoOperation(e.to_string()))?
+    else {
+        return Ok(CommandOutcome::Cancelled);
+    };
+    let entries = entries
+        .into_iter()
+        .map(|entry| ArchiveEntry {
+            filename: entry
+                .path
+                .split('/')
+                .fold(archive_path.to_path_buf(), |path, part| path.join(part))
+                .to_string_lossy()
+                .to_string(),
+            size: entry.size,
+            format: format!("{:?}", detect_format(&entry.head)),
+            compressed: Compression::detect(&entry.head).is_some(),
+            path: entry.path,
+        })
+        .collect::<Vec<ArchiveEntry>>();
+    Ok(CommandOutcome::Finished(
+        serde_json::to_string(&entries)
+            .map_err(|e| ComputationError::IoOperation(e.to_string()))?,
+    ))
+}
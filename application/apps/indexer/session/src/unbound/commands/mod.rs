@@ -1,3 +1,4 @@
+mod archive;
 mod cancel_test;
 mod checksum;
 mod dlt;
@@ -9,6 +10,7 @@ mod serial;
 mod shells;
 mod sleep;
 mod someip;
+mod timestamp;
 
 use crate::{events::ComputationError, unbound::commands::someip::get_someip_statistic};
 
@@ -84,6 +86,15 @@ pub enum Command {
         String,
         oneshot::Sender<Result<CommandOutcome<bool>, ComputationError>>,
     ),
+    DetectTimestampFormat(
+        String,
+        usize,
+        oneshot::Sender<Result<CommandOutcome<String>, ComputationError>>,
+    ),
+    ListArchiveEntries(
+        String,
+        oneshot::Sender<Result<CommandOutcome<String>, ComputationError>>,
+    ),
     CancelTest(
         i64,
         i64,
@@ -109,6 +120,8 @@ impl std::fmt::Display for Command {
                 Command::GetSomeipStatistic(_, _) => "Getting someip statistic",
                 Command::GetRegexError(_, _) => "Checking regex",
                 Command::IsFileBinary(_, _) => "Checking if file is binary",
+                Command::DetectTimestampFormat(_, _, _) => "Detecting timestamp format",
+                Command::ListArchiveEntries(_, _) => "Listing archive's entries",
             }
         )
     }
@@ -138,12 +151,18 @@ pub async fn process(command: Command, signal: Signal) {
         Command::Checksum(file, tx) => tx.send(checksum::checksum(&file, signal)).is_err(),
         Command::GetDltStats(files, tx) => tx.send(dlt::stats(files, signal)).is_err(),
         Command::GetSomeipStatistic(files, tx) => {
-            tx.send(get_someip_statistic(files, signal)).is_err()
+            tx.send(get_someip_statistic(files, signal).await).is_err()
         }
         Command::GetShellProfiles(tx) => tx.send(shells::get_valid_profiles(signal)).is_err(),
         Command::GetContextEnvvars(tx) => tx.send(shells::get_context_envvars(signal)).is_err(),
         Command::SerialPortsList(tx) => tx.send(serial::available_ports(signal)).is_err(),
         Command::IsFileBinary(file_path, tx) => tx.send(file::is_file_binary(file_path)).is_err(),
+        Command::DetectTimestampFormat(file_path, max_lines, tx) => tx
+            .send(timestamp::detect_format(file_path, max_lines, signal))
+            .is_err(),
+        Command::ListArchiveEntries(file_path, tx) => {
+            tx.send(archive::list_entries(file_path, signal)).is_err()
+        }
         Command::CancelTest(a, b, tx) => tx
             .send(cancel_test::cancel_test(a, b, signal).await)
             .is_err(),
@@ -166,6 +185,8 @@ pub fn err(command: Command, err: ComputationError) {
         Command::GetContextEnvvars(tx) => tx.send(Err(err)).is_err(),
         Command::SerialPortsList(tx) => tx.send(Err(err)).is_err(),
         Command::IsFileBinary(_filepath, tx) => tx.send(Err(err)).is_err(),
+        Command::DetectTimestampFormat(_filepath, _max_lines, tx) => tx.send(Err(err)).is_err(),
+        Command::ListArchiveEntries(_filepath, tx) => tx.send(Err(err)).is_err(),
         Command::CancelTest(_a, _b, tx) => tx.send(Err(err)).is_err(),
     } {
         error!("Fail to send error response for command: {cmd}");
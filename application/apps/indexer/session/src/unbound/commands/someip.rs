@@ -1,44 +1,82 @@
 use super::CommandOutcome;
 use crate::{events::ComputationError, unbound::signal::Signal};
+use log::{debug, error};
+use parsers::someip::SomeipStatistic;
+use sources::{
+    binary::pcap::{legacy::PcapLegacyByteSource, ng::PcapngByteSource},
+    ByteSource,
+};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+};
 
-pub fn get_someip_statistic(
-    _files: Vec<String>,
-    _signal: Signal,
+/// Block type of the section header block, which starts every pcapng file
+const PCAPNG_MAGIC: [u8; 4] = [0x0a, 0x0d, 0x0d, 0x0a];
+
+pub async fn get_someip_statistic(
+    files: Vec<String>,
+    signal: Signal,
 ) -> Result<CommandOutcome<String>, ComputationError> {
-    Err(ComputationError::OperationNotSupported("NYI".into()))
-    // use parsers::someip::{read_someip_statistic_from_pcapng, SomeipStatistic};
-    // use log::{error, warn};
-    // use std::path::Path;
+    let mut statistic = SomeipStatistic::new();
+    debug!("Getting statistic for: {files:?}");
+    for file in files.iter() {
+        if signal.is_cancelling() {
+            break;
+        }
+        match read_statistic(file, &signal).await {
+            Ok(result) => statistic.merge(result),
+            Err(err) => {
+                error!("Fail to get statistic for: {file}");
+                return Err(ComputationError::IoOperation(err));
+            }
+        }
+    }
+    if signal.is_cancelling() {
+        debug!("Operation of getting statistic for: {files:?} has been cancelled");
+        return Ok(CommandOutcome::Cancelled);
+    }
+    Ok(CommandOutcome::Finished(
+        serde_json::to_string(&statistic)
+            .map_err(|e| ComputationError::IoOperation(e.to_string()))?,
+    ))
+}
+
+/// Collects the statistic of a pcap or pcapng file. Stops early if the operation
+/// is cancelled.
+async fn read_statistic(file: &str, signal: &Signal) -> Result<SomeipStatistic, String> {
+    let mut input = File::open(file).map_err(|e| e.to_string())?;
+    let mut magic = [0u8; 4];
+    input.read_exact(&mut magic).map_err(|e| e.to_string())?;
+    input.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
+    if magic == PCAPNG_MAGIC {
+        let source = PcapngByteSource::new(input).map_err(|e| e.to_string())?;
+        collect_statistic(source, signal).await
+    } else {
+        let source = PcapLegacyByteSource::new(input).map_err(|e| e.to_string())?;
+        collect_statistic(source, signal).await
+    }
+}
 
-    // let mut statistic = SomeipStatistic::new();
-    // let mut error: Option<String> = None;
-    // warn!("Getting statistic for: {files:?}");
-    // files.iter().for_each(|file| {
-    //     if error.is_some() {
-    //         return;
-    //     }
-    //     if signal.is_cancelling() {
-    //         return;
-    //     }
-    //     match read_someip_statistic_from_pcapng(Path::new(&file), &signal.token()) {
-    //         Ok(result) => {
-    //             statistic.merge(result);
-    //         }
-    //         Err(err) => {
-    //             error = Some(err.to_string());
-    //         }
-    //     }
-    // });
-    // if let Some(err) = error {
-    //     error!("Fail to get statistic for: {files:?}");
-    //     return Err(ComputationError::IoOperation(err));
-    // }
-    // if signal.is_cancelling() {
-    //     warn!("Operation of geting statistic for: {files:?} has been cancelled");
-    //     return Ok(CommandOutcome::Cancelled);
-    // }
-    // Ok(CommandOutcome::Finished(
-    //     serde_json::to_string(&statistic)
-    //         .map_err(|e| ComputationError::IoOperation(e.to_string()))?,
-    // ))
+async fn collect_statistic<S: ByteSource>(
+    mut source: S,
+    signal: &Signal,
+) -> Result<SomeipStatistic, String> {
+    let mut statistic = SomeipStatistic::new();
+    while !signal.is_cancelling() {
+        let loaded = match source.load(None).await.map_err(|e| e.to_string())? {
+            // the data held back by the source at the end of the file
+            None => source.finish().map_err(|e| e.to_string())?,
+            loaded => loaded,
+        };
+        match loaded {
+            Some(_) => {
+                // an incomplete message is counted once the rest of it is loaded
+                let processed = statistic.collect(source.current_slice());
+                source.consume(processed);
+            }
+            None => break,
+        }
+    }
+    Ok(statistic)
 }
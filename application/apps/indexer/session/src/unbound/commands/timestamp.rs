@@ -0,0 +1,21 @@
+use super::CommandOutcome;
+use crate::{events::ComputationError, unbound::signal::Signal};
+use processor::parse::detect_timestamp_formats_in_file;
+use std::path::Path;
+
+pub fn detect_format(
+    file_path: String,
+    max_lines: usize,
+    signal: Signal,
+) -> Result<CommandOutcome<String>, ComputationError> {
+    let Some(candidates) =
+        detect_timestamp_formats_in_file(Path::new(&file_path), max_lines, Some(signal.token()))
+            .map_err(|e| ComputationError::IoOperation(e.to_string()))?
+    else {
+        return Ok(CommandOutcome::Cancelled);
+    };
+    Ok(CommandOutcome::Finished(
+        serde_json::to_string(&candidates)
+            .map_err(|e| ComputationError::IoOperation(e.to_string()))?,
+    ))
+}
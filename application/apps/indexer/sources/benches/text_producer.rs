@@ -18,7 +18,7 @@ fn text_producer(c: &mut Criterion) {
             .to_async(tokio::runtime::Runtime::new().unwrap())
             .iter_batched(
                 || {
-                    let parser = StringTokenizer {};
+                    let parser = StringTokenizer::default();
                     let source = create_binary_bytesource(data);
                     let producer = MessageProducer::new(parser, source, black_box(None));
 
@@ -0,0 +1,286 @@
+use crate::compression::Compression;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+use tokio_util::sync::CancellationToken;
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+/// Magic of zip archives without entries
+const ZIP_EMPTY_MAGIC: &[u8] = b"PK\x05\x06";
+const TAR_MAGIC_OFFSET: usize = 257;
+const TAR_MAGIC: &[u8] = b"ustar";
+
+/// Formats of archives; tar archives might be compressed as a whole
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar(Option<Compression>),
+}
+
+/// File stored in an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Path of the file within the archive, separated by `/`
+    pub path: String,
+    /// Uncompressed size
+    pub size: u64,
+    /// First bytes of the content, e.g. to detect its format
+    pub head: Vec<u8>,
+}
+
+/// Zip or tar archive, which is read entry by entry without extracting it
+pub struct Archive {
+    path: PathBuf,
+    format: ArchiveFormat,
+}
+
+impl Archive {
+    /// Opens the file as archive; returns `None` if it isn't a zip or (compressed) tar
+    /// archive
+    pub fn open(path: &Path) -> io::Result<Option<Self>> {
+        let compression = Compression::of_file(path)?;
+        let mut head = Vec::new();
+        open_decompressed(path, compression)?
+            .take((TAR_MAGIC_OFFSET + TAR_MAGIC.len()) as u64)
+            .read_to_end(&mut head)?;
+        let format = if compression.is_none()
+            && (head.starts_with(ZIP_MAGIC) || head.starts_with(ZIP_EMPTY_MAGIC))
+        {
+            Some(ArchiveFormat::Zip)
+        } else if head.get(TAR_MAGIC_OFFSET..) == Some(TAR_MAGIC) {
+            Some(ArchiveFormat::Tar(compression))
+        } else {
+            None
+        };
+        Ok(format.map(|format| Self {
+            path: path.to_path_buf(),
+            format,
+        }))
+    }
+
+    pub fn format(&self) -> ArchiveFormat {
+        self.format
+    }
+
+    /// Lists the files of the archive together with the first `head_len` bytes of
+    /// their content. Returns `None` if the listing was cancelled, which is checked
+    /// before each file.
+    pub fn entries(
+        &self,
+        head_len: usize,
+        cancel: Option<&CancellationToken>,
+    ) -> io::Result<Option<Vec<ArchiveEntry>>> {
+        let mut entries = Vec::new();
+        let mut cancelled = false;
+        self.for_each_file(|path, size, content| {
+            if cancel.is_some_and(|cancel| cancel.is_cancelled()) {
+                cancelled = true;
+                return Ok(false);
+            }
+            let mut head = Vec::new();
+            content.take(head_len as u64).read_to_end(&mut head)?;
+            entries.push(ArchiveEntry {
+                path: path.to_owned(),
+                size,
+                head,
+            });
+            Ok(true)
+        })?;
+        Ok((!cancelled).then_some(entries))
+    }
+
+    /// Writes the content of the file `entry` into `dest` and returns the count of
+    /// written bytes. Only the content of this file is decompressed.
+    pub fn extract<W: Write>(&self, entry: &str, dest: W) -> io::Result<u64> {
+        let mut written = None;
+        let mut output = BufWriter::new(dest);
+        self.for_each_file(|path, _, content| {
+            if path != entry {
+                return Ok(true);
+            }
+            written = Some(io::copy(content, &mut output)?);
+            output.flush()?;
+            Ok(false)
+        })?;
+        written.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no file {entry} in archive {}", self.path.display()),
+            )
+        })
+    }
+
+    /// Calls `f` with the path, size and content of the files of the archive until it
+    /// returns `false`
+    fn for_each_file<F>(&self, mut f: F) -> io::Result<()>
+    where
+        F: FnMut(&str, u64, &mut dyn Read) -> io::Result<bool>,
+    {
+        match self.format {
+            ArchiveFormat::Zip => {
+                let mut archive = zip::ZipArchive::new(File::open(&self.path)?)?;
+                for index in 0..archive.len() {
+                    let mut file = archive.by_index(index)?;
+                    if file.is_dir() {
+                        continue;
+                    }
+                    let path = normalize(file.name());
+                    let size = file.size();
+                    if !f(&path, size, &mut file)? {
+                        break;
+                    }
+                }
+            }
+            ArchiveFormat::Tar(compression) => {
+                let mut archive = tar::Archive::new(open_decompressed(&self.path, compression)?);
+                for entry in archive.entries()? {
+                    let mut entry = entry?;
+                    if !entry.header().entry_type().is_file() {
+                        continue;
+                    }
+                    let path = normalize(&entry.path()?.to_string_lossy());
+                    let size = entry.size();
+                    if !f(&path, size, &mut entry)? {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn open_decompressed(path: &Path, compression: Option<Compression>) -> io::Result<Box<dyn Read>> {
+    let file = File::open(path)?;
+    match compression {
+        Some(compression) => Ok(compression.decoder(file)?),
+        None => Ok(Box::new(file)),
+    }
+}
+
+fn normalize(path: &str) -> String {
+    path.replace('\\', "/").trim_start_matches("./").to_owned()
+}
+
+/// Splits a path pointing into an archive, e.g. `bundle.zip/logs/app.dlt`, into the
+/// path of the archive and the path of the file within it. Returns `None` if the path
+/// exists or doesn't lead through a file.
+pub fn split_entry_path(path: &Path) -> Option<(PathBuf, String)> {
+    if path.exists() {
+        return None;
+    }
+    let archive = path
+        .ancestors()
+        .skip(1)
+        .find(|ancestor| ancestor.is_file())?;
+    let entry = path
+        .strip_prefix(archive)
+        .ok()?
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    Some((archive.to_path_buf(), entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use zip::write::FileOptions;
+
+    const FILES: [(&str, &[u8]); 2] = [
+        ("logs/app.log", b"first line\nsecond line\n"),
+        ("trace.dlt", b"DLT\x01content"),
+    ];
+
+    fn zip_archive(dest: &Path) {
+        let mut writer = zip::ZipWriter::new(File::create(dest).unwrap());
+        writer
+            .add_directory("logs/", FileOptions::default())
+            .unwrap();
+        for (path, content) in FILES {
+            writer.start_file(path, FileOptions::default()).unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    fn tar_gz_archive(dest: &Path) {
+        let encoder = GzEncoder::new(File::create(dest).unwrap(), Default::default());
+        let mut builder = tar::Builder::new(encoder);
+        for (path, content) in FILES {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, format!("./{path}"), content)
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_read_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, create, format) in [
+            ("bundle.zip", zip_archive as fn(&Path), ArchiveFormat::Zip),
+            (
+                "bundle.tar.gz",
+                tar_gz_archive,
+                ArchiveFormat::Tar(Some(Compression::Gzip)),
+            ),
+        ] {
+            let path = dir.path().join(name);
+            create(&path);
+            let archive = Archive::open(&path).unwrap().unwrap();
+            assert_eq!(archive.format(), format);
+            let entries = archive.entries(4, None).unwrap().unwrap();
+            assert_eq!(
+                entries,
+                FILES
+                    .iter()
+                    .map(|(path, content)| ArchiveEntry {
+                        path: path.to_string(),
+                        size: content.len() as u64,
+                        head: content[..4].to_vec(),
+                    })
+                    .collect::<Vec<_>>(),
+                "{name}"
+            );
+
+            let mut extracted = Vec::new();
+            assert_eq!(archive.extract("logs/app.log", &mut extracted).unwrap(), 23);
+            assert_eq!(extracted, FILES[0].1);
+            assert!(archive.extract("missing.log", io::sink()).is_err());
+
+            let cancel = CancellationToken::new();
+            cancel.cancel();
+            assert_eq!(archive.entries(4, Some(&cancel)).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_no_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plain.log");
+        std::fs::write(&path, "just text").unwrap();
+        assert!(Archive::open(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_split_entry_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("bundle.zip");
+        std::fs::write(&archive, "").unwrap();
+        assert_eq!(split_entry_path(&archive), None);
+        assert_eq!(split_entry_path(&dir.path().join("missing/app.log")), None);
+        assert_eq!(
+            split_entry_path(&archive.join("logs").join("app.log")),
+            Some((archive, String::from("logs/app.log")))
+        );
+    }
+}
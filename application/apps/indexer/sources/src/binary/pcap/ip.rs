@@ -0,0 +1,414 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+};
+
+/// Time in ms after which an incomplete fragment group is dropped
+const FRAGMENT_TIMEOUT_MS: u64 = 30_000;
+/// Max count of bytes buffered for incomplete fragment groups. If exceeded, the oldest
+/// groups are dropped.
+const MAX_FRAGMENT_BYTES: usize = 32 * 1024 * 1024;
+/// Max size of a reassembled IP payload
+const MAX_PAYLOAD_SIZE: usize = 65_535;
+
+const ETHERNET_HEADER_LENGTH: usize = 14;
+const ETHER_TYPE_IPV4: u16 = 0x0800;
+const ETHER_TYPE_IPV6: u16 = 0x86DD;
+const ETHER_TYPES_VLAN: [u16; 3] = [0x8100, 0x88A8, 0x9100];
+const IPV6_HEADER_LENGTH: usize = 40;
+const IPV6_FRAGMENT_HEADER: u8 = 44;
+/// IPv6 extension headers which can precede the fragment header
+const IPV6_UNFRAGMENTABLE_HEADERS: [u8; 3] = [0, 43, 60];
+
+/// Identifies the fragments of one IP datagram
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum GroupKey {
+    V4 {
+        source: [u8; 4],
+        destination: [u8; 4],
+        protocol: u8,
+        id: u16,
+    },
+    V6 {
+        source: [u8; 16],
+        destination: [u8; 16],
+        id: u32,
+    },
+}
+
+/// A fragment within its frame
+struct Fragment<'a> {
+    key: GroupKey,
+    /// Offset of the data within the datagram payload
+    offset: usize,
+    more: bool,
+    data: &'a [u8],
+    /// Everything before the fragmented data (link and IP headers), if this is the
+    /// first fragment; used as headers of the reassembled frame
+    headers: Option<Headers<'a>>,
+}
+
+struct Headers<'a> {
+    bytes: Cow<'a, [u8]>,
+    /// Start of the IP header within the bytes
+    ip_start: usize,
+    /// Position of the "next header" field pointing to the IPv6 fragment header
+    next_header_pos: Option<usize>,
+    /// Protocol of the fragmented payload
+    next_header: u8,
+}
+
+/// Fragments of one IP datagram
+struct Group {
+    fragments: BTreeMap<usize, Vec<u8>>,
+    /// Headers taken from the first fragment
+    headers: Option<Headers<'static>>,
+    total_len: Option<usize>,
+    size: usize,
+    started: Option<u64>,
+    sequence: u64,
+}
+
+impl Group {
+    /// Returns the payload if all fragments are available
+    fn payload(&self) -> Option<Vec<u8>> {
+        let total_len = self.total_len?;
+        self.headers.as_ref()?;
+        let mut payload = Vec::with_capacity(total_len);
+        for (offset, data) in self.fragments.iter() {
+            if *offset > payload.len() {
+                return None;
+            }
+            let known = payload.len() - offset;
+            if known < data.len() {
+                payload.extend_from_slice(&data[known..]);
+            }
+        }
+        (payload.len() >= total_len).then(|| {
+            payload.truncate(total_len);
+            payload
+        })
+    }
+}
+
+/// Reassembles fragmented IPv4 and IPv6 datagrams of ethernet frames
+///
+/// Fragment groups are dropped if they aren't completed within a timeout (measured by
+/// the timestamps of the frames) or if the buffered fragments exceed a memory limit.
+#[derive(Default)]
+pub struct IpReassembler {
+    groups: HashMap<GroupKey, Group>,
+    size: usize,
+    sequence: u64,
+    /// Count of dropped incomplete fragment groups
+    dropped: usize,
+}
+
+impl IpReassembler {
+    /// Adds a frame; frames which aren't fragmented are passed through. Returns `None`
+    /// if the frame is a fragment of a datagram which isn't complete yet.
+    pub fn add_frame<'a>(
+        &mut self,
+        frame: &'a [u8],
+        timestamp: Option<u64>,
+    ) -> Option<Cow<'a, [u8]>> {
+        if let Some(now) = timestamp {
+            self.expire(now);
+        }
+        let Some(fragment) = parse_fragment(frame) else {
+            return Some(Cow::Borrowed(frame));
+        };
+        self.sequence += 1;
+        let group = self
+            .groups
+            .entry(fragment.key.clone())
+            .or_insert_with(|| Group {
+                fragments: BTreeMap::new(),
+                headers: None,
+                total_len: None,
+                size: 0,
+                started: timestamp,
+                sequence: self.sequence,
+            });
+        if let Some(headers) = fragment.headers {
+            group.headers = Some(Headers {
+                bytes: Cow::Owned(headers.bytes.into_owned()),
+                ..headers
+            });
+        }
+        if !fragment.more {
+            group.total_len = Some(fragment.offset + fragment.data.len());
+        }
+        if fragment.offset + fragment.data.len() > MAX_PAYLOAD_SIZE {
+            warn!("dropping IP fragments exceeding the max datagram size");
+            if let Some(group) = self.groups.remove(&fragment.key) {
+                self.size -= group.size;
+                self.dropped += 1;
+            }
+            return None;
+        }
+        let buffered = group.fragments.entry(fragment.offset).or_default();
+        if buffered.len() < fragment.data.len() {
+            group.size += fragment.data.len() - buffered.len();
+            self.size += fragment.data.len() - buffered.len();
+            *buffered = fragment.data.to_vec();
+        }
+        if let Some(payload) = group.payload() {
+            let group = self.groups.remove(&fragment.key)?;
+            self.size -= group.size;
+            return Some(Cow::Owned(reassembled_frame(group.headers?, payload)));
+        }
+        while self.size > MAX_FRAGMENT_BYTES {
+            let Some(oldest) = self
+                .groups
+                .iter()
+                .min_by_key(|(_, group)| group.sequence)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            warn!("dropping incomplete IP fragments, memory limit exceeded");
+            self.drop_group(&oldest);
+        }
+        None
+    }
+
+    /// Count of incomplete fragment groups; either dropped or still waiting for fragments
+    pub fn incomplete(&self) -> usize {
+        self.dropped + self.groups.len()
+    }
+
+    fn expire(&mut self, now: u64) {
+        let expired: Vec<GroupKey> = self
+            .groups
+            .iter()
+            .filter(|(_, group)| {
+                group
+                    .started
+                    .is_some_and(|started| now.saturating_sub(started) > FRAGMENT_TIMEOUT_MS)
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            debug!("dropping incomplete IP fragments after timeout");
+            self.drop_group(&key);
+        }
+    }
+
+    fn drop_group(&mut self, key: &GroupKey) {
+        if let Some(group) = self.groups.remove(key) {
+            self.size -= group.size;
+            self.dropped += 1;
+        }
+    }
+}
+
+/// Builds the frame of the reassembled datagram from the headers of the first fragment
+fn reassembled_frame(headers: Headers, payload: Vec<u8>) -> Vec<u8> {
+    let Headers {
+        bytes,
+        ip_start,
+        next_header_pos,
+        next_header,
+    } = headers;
+    let mut headers = bytes.into_owned();
+    match next_header_pos {
+        // IPv6: the fragment header is removed
+        Some(pos) => {
+            headers[pos] = next_header;
+            let len = (headers.len() - ip_start - IPV6_HEADER_LENGTH + payload.len()) as u16;
+            headers[ip_start + 4..ip_start + 6].copy_from_slice(&len.to_be_bytes());
+        }
+        // IPv4: no more fragments, offset 0
+        None => {
+            let len = (headers.len() - ip_start + payload.len()) as u16;
+            headers[ip_start + 2..ip_start + 4].copy_from_slice(&len.to_be_bytes());
+            headers[ip_start + 6] = 0;
+            headers[ip_start + 7] = 0;
+        }
+    }
+    headers.extend(payload);
+    headers
+}
+
+fn read_u16(bytes: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*bytes.get(pos)?, *bytes.get(pos + 1)?]))
+}
+
+/// Returns the fragment contained in the ethernet frame, if it is one
+fn parse_fragment(frame: &[u8]) -> Option<Fragment<'_>> {
+    let mut pos = ETHERNET_HEADER_LENGTH - 2;
+    let mut ether_type = read_u16(frame, pos)?;
+    while ETHER_TYPES_VLAN.contains(&ether_type) {
+        pos += 4;
+        ether_type = read_u16(frame, pos)?;
+    }
+    let ip_start = pos + 2;
+    let ip = frame.get(ip_start..)?;
+    match ether_type {
+        ETHER_TYPE_IPV4 => {
+            let header_len = (*ip.first()? & 0x0F) as usize * 4;
+            let total_len = (read_u16(ip, 2)? as usize).min(ip.len());
+            let flags = read_u16(ip, 6)?;
+            let more = flags & 0x2000 != 0;
+            let offset = (flags & 0x1FFF) as usize * 8;
+            if !more && offset == 0 {
+                return None;
+            }
+            Some(Fragment {
+                key: GroupKey::V4 {
+                    source: ip.get(12..16)?.try_into().ok()?,
+                    destination: ip.get(16..20)?.try_into().ok()?,
+                    protocol: *ip.get(9)?,
+                    id: read_u16(ip, 4)?,
+                },
+                offset,
+                more,
+                data: ip.get(header_len..total_len)?,
+                headers: (offset == 0).then(|| Headers {
+                    bytes: Cow::Borrowed(&frame[..ip_start + header_len]),
+                    ip_start,
+                    next_header_pos: None,
+                    next_header: ip[9],
+                }),
+            })
+        }
+        ETHER_TYPE_IPV6 => {
+            let total_len = (IPV6_HEADER_LENGTH + read_u16(ip, 4)? as usize).min(ip.len());
+            let mut next_header_pos = 6;
+            let mut header_pos = IPV6_HEADER_LENGTH;
+            while IPV6_UNFRAGMENTABLE_HEADERS.contains(ip.get(next_header_pos)?) {
+                next_header_pos = header_pos;
+                header_pos += (*ip.get(header_pos + 1)? as usize + 1) * 8;
+            }
+            if ip[next_header_pos] != IPV6_FRAGMENT_HEADER {
+                return None;
+            }
+            let fragment_header = ip.get(header_pos..header_pos + 8)?;
+            let flags = read_u16(fragment_header, 2)?;
+            let offset = (flags >> 3) as usize * 8;
+            Some(Fragment {
+                key: GroupKey::V6 {
+                    source: ip.get(8..24)?.try_into().ok()?,
+                    destination: ip.get(24..40)?.try_into().ok()?,
+                    id: u32::from_be_bytes(fragment_header[4..8].try_into().ok()?),
+                },
+                offset,
+                more: flags & 1 != 0,
+                data: ip.get(header_pos + 8..total_len)?,
+                headers: (offset == 0).then(|| Headers {
+                    bytes: Cow::Borrowed(&frame[..ip_start + header_pos]),
+                    ip_start,
+                    next_header_pos: Some(ip_start + next_header_pos),
+                    next_header: fragment_header[0],
+                }),
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ETHERNET_HEADER: [u8; 14] = [
+        0xb8, 0x27, 0xeb, 0x1d, 0x24, 0xc9, 0xb8, 0x27, 0xeb, 0x98, 0x94, 0xfa, 0x08, 0x00,
+    ];
+
+    fn ipv4_fragment(id: u16, offset: usize, more: bool, data: &[u8]) -> Vec<u8> {
+        let mut frame = ETHERNET_HEADER.to_vec();
+        frame.extend_from_slice(&[0x45, 0x00]);
+        frame.extend_from_slice(&(20 + data.len() as u16).to_be_bytes());
+        frame.extend_from_slice(&id.to_be_bytes());
+        frame.extend_from_slice(
+            &((offset / 8) as u16 | if more { 0x2000 } else { 0 }).to_be_bytes(),
+        );
+        frame.extend_from_slice(&[0x40, 0x11, 0x00, 0x00]);
+        frame.extend_from_slice(&[172, 22, 12, 79, 172, 22, 12, 80]);
+        frame.extend_from_slice(data);
+        frame
+    }
+
+    fn ipv6_fragment(id: u32, offset: usize, more: bool, data: &[u8]) -> Vec<u8> {
+        let mut frame = ETHERNET_HEADER[..12].to_vec();
+        frame.extend_from_slice(&[0x86, 0xDD, 0x60, 0x00, 0x00, 0x00]);
+        frame.extend_from_slice(&(8 + data.len() as u16).to_be_bytes());
+        frame.extend_from_slice(&[IPV6_FRAGMENT_HEADER, 0x40]);
+        frame.extend_from_slice(&[0xfe, 0x80]);
+        frame.extend_from_slice(&[0; 14]);
+        frame.extend_from_slice(&[0xfe, 0x80]);
+        frame.extend_from_slice(&[1; 14]);
+        frame.extend_from_slice(&[0x11, 0x00]);
+        frame.extend_from_slice(&((offset as u16) | more as u16).to_be_bytes());
+        frame.extend_from_slice(&id.to_be_bytes());
+        frame.extend_from_slice(data);
+        frame
+    }
+
+    #[test]
+    fn test_pass_through() {
+        let mut reassembler = IpReassembler::default();
+        let frame = ipv4_fragment(1, 0, false, b"complete");
+        assert_eq!(
+            reassembler.add_frame(&frame, None),
+            Some(Cow::Borrowed(&frame[..]))
+        );
+        assert_eq!(reassembler.add_frame(&frame[..10], None).unwrap().len(), 10);
+    }
+
+    #[test]
+    fn test_reassemble_ipv4() {
+        let data: Vec<u8> = (0..40).collect();
+        let mut reassembler = IpReassembler::default();
+        assert!(reassembler
+            .add_frame(&ipv4_fragment(7, 32, false, &data[32..]), Some(0))
+            .is_none());
+        assert!(reassembler
+            .add_frame(&ipv4_fragment(8, 0, true, &data[..16]), Some(0))
+            .is_none());
+        assert!(reassembler
+            .add_frame(&ipv4_fragment(7, 0, true, &data[..16]), Some(1))
+            .is_none());
+        let last = ipv4_fragment(7, 16, true, &data[16..32]);
+        let frame = reassembler.add_frame(&last, Some(2)).unwrap();
+        assert_eq!(frame.as_ref(), ipv4_fragment(7, 0, false, &data).as_slice());
+        assert_eq!(reassembler.incomplete(), 1);
+    }
+
+    #[test]
+    fn test_reassemble_ipv6() {
+        let data: Vec<u8> = (0..24).collect();
+        let mut reassembler = IpReassembler::default();
+        assert!(reassembler
+            .add_frame(&ipv6_fragment(3, 16, false, &data[16..]), None)
+            .is_none());
+        let first = ipv6_fragment(3, 0, true, &data[..16]);
+        let frame = reassembler.add_frame(&first, None).unwrap();
+        let mut expected = ipv6_fragment(3, 0, false, &[]);
+        expected.truncate(ETHERNET_HEADER_LENGTH + IPV6_HEADER_LENGTH);
+        expected[ETHERNET_HEADER_LENGTH + 4..ETHERNET_HEADER_LENGTH + 6]
+            .copy_from_slice(&(data.len() as u16).to_be_bytes());
+        expected[ETHERNET_HEADER_LENGTH + 6] = 0x11;
+        expected.extend_from_slice(&data);
+        assert_eq!(frame.as_ref(), expected.as_slice());
+        assert_eq!(reassembler.incomplete(), 0);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let data: Vec<u8> = (0..24).collect();
+        let mut reassembler = IpReassembler::default();
+        assert!(reassembler
+            .add_frame(&ipv4_fragment(7, 0, true, &data[..16]), Some(1_000))
+            .is_none());
+        let other = ipv4_fragment(1, 0, false, b"other");
+        assert!(reassembler
+            .add_frame(&other, Some(1_000 + FRAGMENT_TIMEOUT_MS + 1))
+            .is_some());
+        assert!(reassembler
+            .add_frame(&ipv4_fragment(7, 16, false, &data[16..]), Some(40_000))
+            .is_none());
+        assert_eq!(reassembler.incomplete(), 2);
+    }
+}
@@ -1,6 +1,8 @@
 use crate::{
-    binary::pcap::debug_block, ByteSource, Error as SourceError, ReloadInfo, SourceFilter,
-    TransportProtocol,
+    binary::pcap::{
+        add_payload, debug_block, ip::IpReassembler, link::LinkLayer, tcp::FramePayloads,
+    },
+    ByteSource, Error as SourceError, ReloadInfo, SourceFilter,
 };
 use buf_redux::Buffer;
 use log::{debug, error, trace};
@@ -10,7 +12,12 @@ use std::io::Read;
 pub struct PcapLegacyByteSource<R: Read> {
     pcap_reader: LegacyPcapReader<R>,
     buffer: Buffer,
+    link_layer: LinkLayer,
+    fragments: IpReassembler,
+    payloads: FramePayloads,
     last_know_timestamp: Option<u64>,
+    /// Timestamps of the packets are in nanoseconds instead of microseconds
+    nanosecond_precision: bool,
     total: usize,
 }
 
@@ -20,7 +27,11 @@ impl<R: Read> PcapLegacyByteSource<R> {
             pcap_reader: LegacyPcapReader::new(65536, reader)
                 .map_err(|e| SourceError::Setup(format!("{e}")))?,
             buffer: Buffer::new(),
+            link_layer: LinkLayer::default(),
+            fragments: IpReassembler::default(),
+            payloads: FramePayloads::default(),
             last_know_timestamp: None,
+            nanosecond_precision: false,
             total: 0,
         })
     }
@@ -31,104 +42,132 @@ impl<R: Read + Send + Sync> ByteSource for PcapLegacyByteSource<R> {
         &mut self,
         filter: Option<&SourceFilter>,
     ) -> Result<Option<ReloadInfo>, SourceError> {
-        let raw_data;
-        let mut consumed;
         let mut skipped = 0usize;
         loop {
-            match self.pcap_reader.next() {
-                Ok((bytes_read, block)) => {
-                    self.total += bytes_read;
-                    trace!(
-                        "PcapByteSource::reload, bytes_read: {} (total: {})",
-                        bytes_read,
-                        self.total
-                    );
-                    consumed = bytes_read;
-                    match block {
-                        PcapBlockOwned::LegacyHeader(ref _hdr) => {
-                            self.pcap_reader.consume(consumed);
-                            continue;
-                        }
-                        PcapBlockOwned::Legacy(ref b) => {
-                            raw_data = &b.data[..b.origlen as usize];
-                            break;
-                        }
-                        other_type => {
-                            debug_block(other_type);
-                            skipped += consumed;
-                            debug!("skipped in total {} bytes", skipped);
-                            self.pcap_reader.consume(consumed);
-                            continue;
+            if let Some(copied) = self.payloads.deliver(&mut self.buffer, false) {
+                return Ok(Some(ReloadInfo::new(
+                    copied,
+                    self.buffer.len(),
+                    skipped,
+                    self.last_know_timestamp,
+                )));
+            }
+            // the file might have grown since the end was reached
+            let mut refilled = false;
+            let raw_data;
+            let mut consumed;
+            loop {
+                match self.pcap_reader.next() {
+                    Ok((bytes_read, block)) => {
+                        self.total += bytes_read;
+                        trace!(
+                            "PcapByteSource::reload, bytes_read: {} (total: {})",
+                            bytes_read,
+                            self.total
+                        );
+                        consumed = bytes_read;
+                        match block {
+                            PcapBlockOwned::LegacyHeader(ref hdr) => {
+                                self.nanosecond_precision = hdr.is_nanosecond_precision();
+                                self.link_layer.reset();
+                                self.link_layer.add_interface(hdr.network.0);
+                                self.pcap_reader.consume(consumed);
+                                continue;
+                            }
+                            PcapBlockOwned::Legacy(ref b) => {
+                                let fraction = if self.nanosecond_precision {
+                                    b.ts_usec as u64 / 1_000_000
+                                } else {
+                                    b.ts_usec as u64 / 1_000
+                                };
+                                self.last_know_timestamp = Some(b.ts_sec as u64 * 1_000 + fraction);
+                                raw_data = &b.data[..b.origlen as usize];
+                                break;
+                            }
+                            other_type => {
+                                debug_block(other_type);
+                                skipped += consumed;
+                                debug!("skipped in total {} bytes", skipped);
+                                self.pcap_reader.consume(consumed);
+                                continue;
+                            }
                         }
                     }
-                }
-                Err(PcapError::Eof) => {
-                    debug!("reloading from pcap file, EOF");
-                    return Ok(None);
-                }
-                Err(PcapError::Incomplete) => {
-                    trace!("reloading from pcap file, Incomplete");
-                    self.pcap_reader
-                        .refill()
-                        .expect("refill pcap reader failed");
-                    // continue;
-                }
-                Err(e) => {
-                    let m = format!("{e}");
-                    error!("reloading from pcap file, {}", m);
-                    return Err(SourceError::Unrecoverable(m));
+                    Err(PcapError::Eof) | Err(PcapError::UnexpectedEof) if !refilled => {
+                        refilled = true;
+                        self.pcap_reader.refill().map_err(|e| {
+                            SourceError::Unrecoverable(format!("refill pcap reader failed: {e}"))
+                        })?;
+                    }
+                    Err(e @ PcapError::Eof) | Err(e @ PcapError::UnexpectedEof) => {
+                        // an incomplete block at the end is read once the file grows; the
+                        // waiting fragments and messages are kept until then
+                        debug!("reloading from pcap file, {e}");
+                        return Ok(None);
+                    }
+                    Err(PcapError::Incomplete) => {
+                        trace!("reloading from pcap file, Incomplete");
+                        self.pcap_reader.refill().map_err(|e| {
+                            SourceError::Unrecoverable(format!("refill pcap reader failed: {e}"))
+                        })?;
+                    }
+                    Err(e) => {
+                        let m = format!("{e}");
+                        error!("reloading from pcap file, {}", m);
+                        return Err(SourceError::Unrecoverable(m));
+                    }
                 }
             }
-        }
-        let res = match etherparse::SlicedPacket::from_ethernet(raw_data) {
-            Ok(value) => {
-                skipped += consumed - value.payload.len();
-                match (value.transport, filter) {
-                    (
-                        Some(actual),
-                        Some(SourceFilter {
-                            transport: Some(wanted),
-                        }),
-                    ) => {
-                        let actual_tp: TransportProtocol = actual.into();
-                        let received_bytes = self.buffer.copy_from_slice(value.payload);
-                        let available_bytes = self.buffer.len();
-                        if actual_tp == *wanted {
-                            Ok(Some(ReloadInfo::new(
-                                received_bytes,
-                                available_bytes,
-                                skipped,
-                                self.last_know_timestamp,
-                            )))
-                        } else {
-                            Ok(Some(ReloadInfo::new(
-                                0,
-                                0,
-                                value.payload.len() + skipped,
-                                self.last_know_timestamp,
-                            )))
-                        }
+            let res = match self.link_layer.ethernet_frame(0, raw_data) {
+                Some(frame) => match self
+                    .fragments
+                    .add_frame(&frame, self.last_know_timestamp)
+                    .as_deref()
+                    .map(etherparse::SlicedPacket::from_ethernet)
+                {
+                    // fragment of an incomplete IP datagram
+                    None => Ok(()),
+                    Some(Ok(value)) if filter.is_none_or(|filter| filter.matches(&value)) => {
+                        skipped += consumed.saturating_sub(value.payload.len());
+                        add_payload(&mut self.payloads, &value, self.last_know_timestamp);
+                        Ok(())
                     }
-                    _ => {
-                        let copied = self.buffer.copy_from_slice(value.payload);
-                        let available_bytes = self.buffer.len();
-                        Ok(Some(ReloadInfo::new(
-                            copied,
-                            available_bytes,
-                            skipped,
-                            self.last_know_timestamp,
-                        )))
+                    Some(Ok(_)) => {
+                        skipped += consumed;
+                        trace!("frame filtered out, skipped in total {} bytes", skipped);
+                        Ok(())
                     }
+                    Some(Err(e)) => Err(SourceError::Unrecoverable(format!(
+                        "error trying to extract data from ethernet frame: {e}"
+                    ))),
+                },
+                // unsupported link type
+                None => {
+                    skipped += consumed;
+                    Ok(())
                 }
-            }
-            Err(e) => Err(SourceError::Unrecoverable(format!(
-                "error trying to extract data from ethernet frame: {e}"
-            ))),
-        };
-        // bytes are copied into buffer and can be dropped by pcap reader
-        trace!("consume {} processed bytes", consumed);
-        self.pcap_reader.consume(consumed);
-        res
+            };
+            // bytes are copied and can be dropped by pcap reader
+            trace!("consume {} processed bytes", consumed);
+            self.pcap_reader.consume(consumed);
+            res?;
+        }
+    }
+
+    fn finish(&mut self) -> Result<Option<ReloadInfo>, SourceError> {
+        // deliver what is left of the connections; waiting fragments are kept since the
+        // capture might continue
+        Ok(self
+            .payloads
+            .deliver(&mut self.buffer, true)
+            .map(|copied| ReloadInfo::new(copied, self.buffer.len(), 0, self.last_know_timestamp)))
+    }
+
+    fn report(&self) -> Option<String> {
+        let incomplete = self.fragments.incomplete();
+        (incomplete > 0).then(|| {
+            format!("{incomplete} IP datagram(s) couldn't be reassembled, fragments are missing")
+        })
     }
 
     fn current_slice(&self) -> &[u8] {
@@ -191,6 +230,8 @@ mod tests {
         println!("slice: {:x?}", slice);
         assert_eq!(slice.len(), 56);
         assert_eq!(slice, udp_payload);
+        // timestamp of the packet header in milliseconds
+        assert_eq!(reload_info.unwrap().last_known_ts, Some(1_619_531_243_294));
     }
 
     #[tokio::test]
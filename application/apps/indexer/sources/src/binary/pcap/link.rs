@@ -0,0 +1,194 @@
+use std::{borrow::Cow, collections::HashSet};
+
+// Link types, see https://www.tcpdump.org/linktypes.html
+const LINKTYPE_NULL: i32 = 0;
+const LINKTYPE_ETHERNET: i32 = 1;
+const LINKTYPE_RAW: i32 = 101;
+const LINKTYPE_LOOP: i32 = 108;
+const LINKTYPE_LINUX_SLL: i32 = 113;
+const LINKTYPE_IPV4: i32 = 228;
+const LINKTYPE_IPV6: i32 = 229;
+const LINKTYPE_LINUX_SLL2: i32 = 276;
+
+const ETHER_TYPE_IPV4: u16 = 0x0800;
+const ETHER_TYPE_IPV6: u16 = 0x86DD;
+const SLL_HEADER_LENGTH: usize = 16;
+const SLL2_HEADER_LENGTH: usize = 20;
+/// Address families of IPv6 used by the BSD loopback encapsulation
+const BSD_AF_INET6: [u32; 3] = [24, 28, 30];
+
+/// Link layers of the interfaces of a capture
+///
+/// Frames are converted to ethernet frames, so all link types share the further
+/// processing. Frames of unsupported link types are skipped.
+#[derive(Default)]
+pub struct LinkLayer {
+    /// Link type by interface id; a pcap file has a single interface
+    link_types: Vec<i32>,
+    /// Unsupported link types which were reported already
+    unsupported: HashSet<i32>,
+}
+
+impl LinkLayer {
+    /// Forgets the interfaces, e.g. at the start of a new pcapng section
+    pub fn reset(&mut self) {
+        self.link_types.clear();
+    }
+
+    pub fn add_interface(&mut self, link_type: i32) {
+        self.link_types.push(link_type);
+    }
+
+    /// Returns the frame of the interface as ethernet frame, or `None` if the link type
+    /// isn't supported. Interfaces without description are considered to be ethernet.
+    pub fn ethernet_frame<'a>(&mut self, interface: u32, frame: &'a [u8]) -> Option<Cow<'a, [u8]>> {
+        let link_type = self
+            .link_types
+            .get(interface as usize)
+            .copied()
+            .unwrap_or(LINKTYPE_ETHERNET);
+        let frame = ethernet_frame(link_type, frame);
+        if frame.is_none() && self.unsupported.insert(link_type) {
+            warn!("skipping frames of unsupported link type {link_type}");
+        }
+        frame
+    }
+}
+
+fn ethernet_frame(link_type: i32, frame: &[u8]) -> Option<Cow<'_, [u8]>> {
+    let (ether_type, payload) = match link_type {
+        LINKTYPE_ETHERNET => return Some(Cow::Borrowed(frame)),
+        LINKTYPE_RAW => match frame.first()? >> 4 {
+            4 => (ETHER_TYPE_IPV4, frame),
+            6 => (ETHER_TYPE_IPV6, frame),
+            _ => return None,
+        },
+        LINKTYPE_IPV4 => (ETHER_TYPE_IPV4, frame),
+        LINKTYPE_IPV6 => (ETHER_TYPE_IPV6, frame),
+        LINKTYPE_LINUX_SLL => (
+            u16::from_be_bytes([*frame.get(14)?, *frame.get(15)?]),
+            frame.get(SLL_HEADER_LENGTH..)?,
+        ),
+        LINKTYPE_LINUX_SLL2 => (
+            u16::from_be_bytes([*frame.first()?, *frame.get(1)?]),
+            frame.get(SLL2_HEADER_LENGTH..)?,
+        ),
+        LINKTYPE_NULL | LINKTYPE_LOOP => {
+            let family: [u8; 4] = frame.get(..4)?.try_into().ok()?;
+            // NULL uses the byte order of the capturing host, LOOP the network byte order
+            let family = match link_type {
+                LINKTYPE_LOOP => u32::from_be_bytes(family),
+                _ if family[0] == 0 => u32::from_be_bytes(family),
+                _ => u32::from_le_bytes(family),
+            };
+            let ether_type = match family {
+                2 => ETHER_TYPE_IPV4,
+                family if BSD_AF_INET6.contains(&family) => ETHER_TYPE_IPV6,
+                _ => return None,
+            };
+            (ether_type, &frame[4..])
+        }
+        _ => return None,
+    };
+    // Addresses are left empty; only the type of the payload matters
+    let mut ethernet = vec![0u8; 12];
+    ethernet.extend_from_slice(&ether_type.to_be_bytes());
+    ethernet.extend_from_slice(payload);
+    Some(Cow::Owned(ethernet))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IPV4_PACKET: [u8; 4] = [0x45, 0x00, 0x00, 0x54];
+    const IPV6_PACKET: [u8; 4] = [0x60, 0x00, 0x00, 0x00];
+
+    fn ethernet(ether_type: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&ether_type.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn test_ethernet_frames() {
+        let mut link = LinkLayer::default();
+        for (link_type, frame, expected) in [
+            (
+                LINKTYPE_ETHERNET,
+                IPV4_PACKET.to_vec(),
+                IPV4_PACKET.to_vec(),
+            ),
+            (
+                LINKTYPE_RAW,
+                IPV4_PACKET.to_vec(),
+                ethernet(0x0800, &IPV4_PACKET),
+            ),
+            (
+                LINKTYPE_RAW,
+                IPV6_PACKET.to_vec(),
+                ethernet(0x86DD, &IPV6_PACKET),
+            ),
+            (
+                LINKTYPE_IPV6,
+                IPV6_PACKET.to_vec(),
+                ethernet(0x86DD, &IPV6_PACKET),
+            ),
+            (
+                LINKTYPE_LINUX_SLL,
+                [
+                    &[0, 0, 0, 1, 0, 6, 1, 2, 3, 4, 5, 6, 0, 0, 0x81, 0x00][..],
+                    &IPV4_PACKET,
+                ]
+                .concat(),
+                ethernet(0x8100, &IPV4_PACKET),
+            ),
+            (
+                LINKTYPE_LINUX_SLL2,
+                [
+                    &[
+                        0x86, 0xDD, 0, 0, 0, 0, 0, 2, 0, 1, 0, 6, 1, 2, 3, 4, 5, 6, 0, 0,
+                    ][..],
+                    &IPV6_PACKET,
+                ]
+                .concat(),
+                ethernet(0x86DD, &IPV6_PACKET),
+            ),
+            (
+                LINKTYPE_NULL,
+                [&[2, 0, 0, 0][..], &IPV4_PACKET].concat(),
+                ethernet(0x0800, &IPV4_PACKET),
+            ),
+            (
+                LINKTYPE_LOOP,
+                [&[0, 0, 0, 30][..], &IPV6_PACKET].concat(),
+                ethernet(0x86DD, &IPV6_PACKET),
+            ),
+        ] {
+            link.reset();
+            link.add_interface(link_type);
+            assert_eq!(
+                link.ethernet_frame(0, &frame).as_deref(),
+                Some(expected.as_slice()),
+                "link type {link_type}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_interfaces() {
+        let mut link = LinkLayer::default();
+        // no interface description
+        assert!(link.ethernet_frame(0, &IPV4_PACKET).is_some());
+        link.add_interface(LINKTYPE_ETHERNET);
+        link.add_interface(147);
+        assert!(link.ethernet_frame(0, &IPV4_PACKET).is_some());
+        assert!(link.ethernet_frame(1, &IPV4_PACKET).is_none());
+        assert!(link.unsupported.contains(&147));
+        // truncated frame
+        link.reset();
+        link.add_interface(LINKTYPE_LINUX_SLL);
+        assert!(link.ethernet_frame(0, &IPV4_PACKET).is_none());
+    }
+}
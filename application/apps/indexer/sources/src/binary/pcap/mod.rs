@@ -1,7 +1,48 @@
+use etherparse::{InternetSlice, SlicedPacket, TransportSlice};
 use pcap_parser::PcapBlockOwned;
+use std::net::{IpAddr, SocketAddr};
+use tcp::{FlowKey, FramePayloads};
 
+pub mod ip;
 pub mod legacy;
+pub mod link;
 pub mod ng;
+pub mod tcp;
+
+/// Source and destination address of the IP packet
+pub(crate) fn ip_addresses(packet: &SlicedPacket) -> Option<(IpAddr, IpAddr)> {
+    match packet.ip.as_ref()? {
+        InternetSlice::Ipv4(header, _) => Some((
+            header.source_addr().into(),
+            header.destination_addr().into(),
+        )),
+        InternetSlice::Ipv6(header, _) => Some((
+            header.source_addr().into(),
+            header.destination_addr().into(),
+        )),
+    }
+}
+
+/// Adds the payload of the frame to the payloads waiting for the delivery.
+/// TCP segments are reassembled per connection.
+fn add_payload(payloads: &mut FramePayloads, packet: &SlicedPacket, timestamp: Option<u64>) {
+    if let Some(now) = timestamp {
+        payloads.expire(now);
+    }
+    match (ip_addresses(packet), packet.transport.as_ref()) {
+        (Some((source, destination)), Some(TransportSlice::Tcp(tcp))) => payloads.add_segment(
+            FlowKey {
+                source: SocketAddr::new(source, tcp.source_port()),
+                destination: SocketAddr::new(destination, tcp.destination_port()),
+            },
+            tcp.sequence_number(),
+            tcp.syn(),
+            tcp.fin() || tcp.rst(),
+            packet.payload,
+        ),
+        _ => payloads.add_datagram(packet.payload),
+    }
+}
 
 fn debug_block(b: PcapBlockOwned) {
     match b {
@@ -1,15 +1,74 @@
 use crate::{
-    binary::pcap::debug_block, ByteSource, Error as SourceError, ReloadInfo, SourceFilter,
-    TransportProtocol,
+    binary::pcap::{
+        add_payload, debug_block, ip::IpReassembler, link::LinkLayer, tcp::FramePayloads,
+    },
+    ByteSource, Error as SourceError, ReloadInfo, SourceFilter,
 };
 use buf_redux::Buffer;
-use log::{debug, error, trace};
+use log::{debug, error, trace, warn};
 use pcap_parser::{traits::PcapReaderIterator, PcapBlockOwned, PcapError, PcapNGReader};
 use std::io::Read;
 
+/// Clock of an interface, given by the options `if_tsresol` and `if_tsoffset` of its
+/// description block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct InterfaceClock {
+    /// Timestamp units per second
+    units_per_sec: u64,
+    /// Seconds to add to the timestamps
+    offset: i64,
+}
+
+impl Default for InterfaceClock {
+    /// Microseconds, as defined for interfaces without `if_tsresol`
+    fn default() -> Self {
+        Self {
+            units_per_sec: 1_000_000,
+            offset: 0,
+        }
+    }
+}
+
+impl InterfaceClock {
+    fn new(tsresol: u8, tsoffset: i64) -> Self {
+        // the most significant bit selects a power of two instead of a power of ten
+        let exponent = u32::from(tsresol & 0x7f);
+        let units_per_sec = if tsresol & 0x80 == 0 {
+            10u64.checked_pow(exponent)
+        } else {
+            2u64.checked_pow(exponent)
+        };
+        match units_per_sec {
+            Some(units_per_sec) => Self {
+                units_per_sec,
+                offset: tsoffset,
+            },
+            None => {
+                warn!("unsupported timestamp resolution {tsresol:#x}, assuming microseconds");
+                Self {
+                    offset: tsoffset,
+                    ..Self::default()
+                }
+            }
+        }
+    }
+
+    /// Converts a timestamp of the interface into milliseconds since the epoch
+    fn to_millis(self, timestamp: u64) -> u64 {
+        let millis = i128::from(timestamp) * 1000 / i128::from(self.units_per_sec)
+            + i128::from(self.offset) * 1000;
+        millis.clamp(0, i128::from(u64::MAX)) as u64
+    }
+}
+
 pub struct PcapngByteSource<R: Read> {
     pcapng_reader: PcapNGReader<R>,
     buffer: Buffer,
+    link_layer: LinkLayer,
+    /// Clocks by interface id
+    clocks: Vec<InterfaceClock>,
+    fragments: IpReassembler,
+    payloads: FramePayloads,
     last_know_timestamp: Option<u64>,
     total: usize,
 }
@@ -20,6 +79,10 @@ impl<R: Read> PcapngByteSource<R> {
             pcapng_reader: PcapNGReader::new(65536, reader)
                 .map_err(|e| SourceError::Setup(format!("{e}")))?,
             buffer: Buffer::new(),
+            link_layer: LinkLayer::default(),
+            clocks: Vec::new(),
+            fragments: IpReassembler::default(),
+            payloads: FramePayloads::default(),
             last_know_timestamp: None,
             total: 0,
         })
@@ -31,108 +94,158 @@ impl<R: Read + Send + Sync> ByteSource for PcapngByteSource<R> {
         &mut self,
         filter: Option<&SourceFilter>,
     ) -> Result<Option<ReloadInfo>, SourceError> {
-        let raw_data;
-        let mut consumed;
         let mut skipped = 0usize;
         loop {
-            match self.pcapng_reader.next() {
-                Ok((bytes_read, block)) => {
-                    self.total += bytes_read;
-                    trace!(
-                        "PcapngByteSource::reload, bytes_read: {} (total: {})",
-                        bytes_read,
-                        self.total
-                    );
-                    consumed = bytes_read;
-                    match block {
-                        PcapBlockOwned::NG(pcap_parser::Block::EnhancedPacket(ref epb)) => {
-                            trace!("Enhanced package");
-                            let ts_us: u64 = (epb.ts_high as u64) << 32 | epb.ts_low as u64;
-                            self.last_know_timestamp = Some(ts_us / 1000);
-                            raw_data = &epb.data[..epb.caplen as usize];
-                            break;
-                        }
-                        PcapBlockOwned::NG(pcap_parser::Block::SimplePacket(ref spb)) => {
-                            trace!("SimplePacket");
-                            raw_data = &spb.data[..spb.origlen as usize];
-                            break;
-                        }
-                        other_type => {
-                            debug_block(other_type);
-                            skipped += consumed;
-                            debug!("skipped in total {} bytes", skipped);
-                            self.pcapng_reader.consume(consumed);
-                            continue;
+            if let Some(copied) = self.payloads.deliver(&mut self.buffer, false) {
+                return Ok(Some(ReloadInfo::new(
+                    copied,
+                    self.buffer.len(),
+                    skipped,
+                    self.last_know_timestamp,
+                )));
+            }
+            // the file might have grown since the end was reached
+            let mut refilled = false;
+            let raw_data;
+            let interface;
+            let mut consumed;
+            loop {
+                match self.pcapng_reader.next() {
+                    Ok((bytes_read, block)) => {
+                        self.total += bytes_read;
+                        trace!(
+                            "PcapngByteSource::reload, bytes_read: {} (total: {})",
+                            bytes_read,
+                            self.total
+                        );
+                        consumed = bytes_read;
+                        match block {
+                            PcapBlockOwned::NG(pcap_parser::Block::EnhancedPacket(ref epb)) => {
+                                trace!("Enhanced package");
+                                let ts: u64 = (epb.ts_high as u64) << 32 | epb.ts_low as u64;
+                                let clock = self
+                                    .clocks
+                                    .get(epb.if_id as usize)
+                                    .copied()
+                                    .unwrap_or_default();
+                                self.last_know_timestamp = Some(clock.to_millis(ts));
+                                raw_data = &epb.data[..epb.caplen as usize];
+                                interface = epb.if_id;
+                                break;
+                            }
+                            PcapBlockOwned::NG(pcap_parser::Block::SimplePacket(ref spb)) => {
+                                trace!("SimplePacket");
+                                // simple packets have no timestamp, the one of the preceding
+                                // packet is the closest known
+                                raw_data = &spb.data[..spb.origlen as usize];
+                                // simple packets belong to the first interface
+                                interface = 0;
+                                break;
+                            }
+                            PcapBlockOwned::NG(pcap_parser::Block::SectionHeader(_)) => {
+                                trace!("SectionHeader");
+                                // interface ids are local to a section
+                                self.link_layer.reset();
+                                self.clocks.clear();
+                                skipped += consumed;
+                                self.pcapng_reader.consume(consumed);
+                                continue;
+                            }
+                            PcapBlockOwned::NG(pcap_parser::Block::InterfaceDescription(
+                                ref idb,
+                            )) => {
+                                trace!("InterfaceDescription");
+                                self.link_layer.add_interface(idb.linktype.0);
+                                self.clocks
+                                    .push(InterfaceClock::new(idb.if_tsresol, idb.if_tsoffset));
+                                skipped += consumed;
+                                self.pcapng_reader.consume(consumed);
+                                continue;
+                            }
+                            other_type => {
+                                debug_block(other_type);
+                                skipped += consumed;
+                                debug!("skipped in total {} bytes", skipped);
+                                self.pcapng_reader.consume(consumed);
+                                continue;
+                            }
                         }
                     }
-                }
-                Err(PcapError::Eof) => {
-                    debug!("reloading from pcap file, EOF");
-                    return Ok(None);
-                }
-                Err(PcapError::Incomplete) => {
-                    trace!("reloading from pcap file, Incomplete");
-                    self.pcapng_reader
-                        .refill()
-                        .expect("refill pcapng reader failed");
-                    // continue;
-                }
-                Err(e) => {
-                    let m = format!("{e}");
-                    error!("reloading from pcap file, {}", m);
-                    return Err(SourceError::Unrecoverable(m));
+                    Err(PcapError::Eof) | Err(PcapError::UnexpectedEof) if !refilled => {
+                        refilled = true;
+                        self.pcapng_reader.refill().map_err(|e| {
+                            SourceError::Unrecoverable(format!("refill pcapng reader failed: {e}"))
+                        })?;
+                    }
+                    Err(e @ PcapError::Eof) | Err(e @ PcapError::UnexpectedEof) => {
+                        // an incomplete block at the end is read once the file grows; the
+                        // waiting fragments and messages are kept until then
+                        debug!("reloading from pcap file, {e}");
+                        return Ok(None);
+                    }
+                    Err(PcapError::Incomplete) => {
+                        trace!("reloading from pcap file, Incomplete");
+                        self.pcapng_reader.refill().map_err(|e| {
+                            SourceError::Unrecoverable(format!("refill pcapng reader failed: {e}"))
+                        })?;
+                    }
+                    Err(e) => {
+                        let m = format!("{e}");
+                        error!("reloading from pcap file, {}", m);
+                        return Err(SourceError::Unrecoverable(m));
+                    }
                 }
             }
-        }
-        let res = match etherparse::SlicedPacket::from_ethernet(raw_data) {
-            Ok(value) => {
-                skipped += consumed - value.payload.len();
-                match (value.transport, filter) {
-                    (
-                        Some(actual),
-                        Some(SourceFilter {
-                            transport: Some(wanted),
-                        }),
-                    ) => {
-                        let actual_tp: TransportProtocol = actual.into();
-                        let received_bytes = self.buffer.copy_from_slice(value.payload);
-                        let available_bytes = self.buffer.len();
-                        if actual_tp == *wanted {
-                            Ok(Some(ReloadInfo::new(
-                                received_bytes,
-                                available_bytes,
-                                skipped,
-                                self.last_know_timestamp,
-                            )))
-                        } else {
-                            Ok(Some(ReloadInfo::new(
-                                0,
-                                0,
-                                value.payload.len() + skipped,
-                                self.last_know_timestamp,
-                            )))
-                        }
+            let res = match self.link_layer.ethernet_frame(interface, raw_data) {
+                Some(frame) => match self
+                    .fragments
+                    .add_frame(&frame, self.last_know_timestamp)
+                    .as_deref()
+                    .map(etherparse::SlicedPacket::from_ethernet)
+                {
+                    // fragment of an incomplete IP datagram
+                    None => Ok(()),
+                    Some(Ok(value)) if filter.is_none_or(|filter| filter.matches(&value)) => {
+                        skipped += consumed.saturating_sub(value.payload.len());
+                        add_payload(&mut self.payloads, &value, self.last_know_timestamp);
+                        Ok(())
                     }
-                    _ => {
-                        let copied = self.buffer.copy_from_slice(value.payload);
-                        let available_bytes = self.buffer.len();
-                        Ok(Some(ReloadInfo::new(
-                            copied,
-                            available_bytes,
-                            skipped,
-                            self.last_know_timestamp,
-                        )))
+                    Some(Ok(_)) => {
+                        skipped += consumed;
+                        trace!("frame filtered out, skipped in total {} bytes", skipped);
+                        Ok(())
                     }
+                    Some(Err(e)) => Err(SourceError::Unrecoverable(format!(
+                        "error trying to extract data from ethernet frame: {e}"
+                    ))),
+                },
+                // unsupported link type
+                None => {
+                    skipped += consumed;
+                    Ok(())
                 }
-            }
-            Err(e) => Err(SourceError::Unrecoverable(format!(
-                "error trying to extract data from ethernet frame: {e}"
-            ))),
-        };
-        // bytes are copied into buffer and can be dropped by pcap reader
-        trace!("consume {} processed bytes", consumed);
-        self.pcapng_reader.consume(consumed);
-        res
+            };
+            // bytes are copied and can be dropped by pcap reader
+            trace!("consume {} processed bytes", consumed);
+            self.pcapng_reader.consume(consumed);
+            res?;
+        }
+    }
+
+    fn finish(&mut self) -> Result<Option<ReloadInfo>, SourceError> {
+        // deliver what is left of the connections; waiting fragments are kept since the
+        // capture might continue
+        Ok(self
+            .payloads
+            .deliver(&mut self.buffer, true)
+            .map(|copied| ReloadInfo::new(copied, self.buffer.len(), 0, self.last_know_timestamp)))
+    }
+
+    fn report(&self) -> Option<String> {
+        let incomplete = self.fragments.incomplete();
+        (incomplete > 0).then(|| {
+            format!("{incomplete} IP datagram(s) couldn't be reassembled, fragments are missing")
+        })
     }
 
     fn current_slice(&self) -> &[u8] {
@@ -151,11 +264,15 @@ impl<R: Read + Send + Sync> ByteSource for PcapngByteSource<R> {
 #[cfg(test)]
 mod tests {
     use crate::{
-        binary::pcap::ng::PcapngByteSource,
+        binary::pcap::ng::{InterfaceClock, PcapngByteSource},
         tests::{general_source_reload_test, mock_read::MockRepeatRead},
-        ByteSource,
+        ByteSource, PortRange, SourceFilter, TransportProtocol,
     };
     use env_logger;
+    use std::{
+        io::Read,
+        sync::{Arc, Mutex},
+    };
 
     const SAMPLE_PCAPNG_DATA: &[u8] = &[
         /*0*/
@@ -211,6 +328,125 @@ mod tests {
         println!("slice: {:x?}", slice);
         assert_eq!(slice.len(), 56);
         assert_eq!(slice, udp_payload);
+        // microseconds by default
+        assert_eq!(
+            reload_info.unwrap().last_known_ts,
+            Some(0x0005_c0f4_8075_90a6 / 1000)
+        );
+    }
+
+    #[test]
+    fn test_interface_clocks() {
+        let ts = 1_700_000_000_123_456_789u64;
+        assert_eq!(
+            InterfaceClock::default().to_millis(ts / 1000),
+            1_700_000_000_123
+        );
+        // nanoseconds
+        assert_eq!(InterfaceClock::new(9, 0).to_millis(ts), 1_700_000_000_123);
+        // 2^-10 seconds
+        assert_eq!(
+            InterfaceClock::new(0x8a, 0).to_millis(1024 * 5 + 512),
+            5_500
+        );
+        // offset in seconds
+        assert_eq!(InterfaceClock::new(9, 10).to_millis(ts), 1_700_000_010_123);
+        assert_eq!(InterfaceClock::new(3, -10).to_millis(5_000), 0);
+        // unsupported resolution
+        assert_eq!(InterfaceClock::new(0x7f, 0), InterfaceClock::default());
+    }
+
+    #[tokio::test]
+    async fn test_filter_frames() {
+        let udp_payload = &SAMPLE_PCAPNG_DATA[118..=173];
+        let accepted = SourceFilter {
+            transport: Some(TransportProtocol::UDP),
+            destination_ips: vec!["172.22.12.80".parse().unwrap()],
+            destination_ports: vec![PortRange {
+                from: 50000,
+                to: 50010,
+            }],
+            ..Default::default()
+        };
+        let mut source = PcapngByteSource::new(std::io::Cursor::new(SAMPLE_PCAPNG_DATA)).unwrap();
+        source.load(Some(&accepted)).await.unwrap().unwrap();
+        assert_eq!(source.current_slice(), udp_payload);
+
+        for rejected in [
+            SourceFilter {
+                transport: Some(TransportProtocol::TCP),
+                ..Default::default()
+            },
+            SourceFilter {
+                source_ips: vec!["172.22.12.80".parse().unwrap()],
+                ..Default::default()
+            },
+            SourceFilter {
+                source_ports: vec![PortRange {
+                    from: 30490,
+                    to: 30490,
+                }],
+                ..Default::default()
+            },
+            SourceFilter {
+                vlan_ids: vec![1],
+                ..Default::default()
+            },
+        ] {
+            let mut source =
+                PcapngByteSource::new(std::io::Cursor::new(SAMPLE_PCAPNG_DATA)).unwrap();
+            assert!(source.load(Some(&rejected)).await.unwrap().is_none());
+            assert!(source.current_slice().is_empty());
+        }
+    }
+
+    /// Capture which is still being written
+    #[derive(Clone, Default)]
+    struct GrowingRead {
+        content: Arc<Mutex<(Vec<u8>, usize)>>,
+    }
+
+    impl GrowingRead {
+        fn append(&self, bytes: &[u8]) {
+            self.content.lock().unwrap().0.extend_from_slice(bytes);
+        }
+    }
+
+    impl Read for GrowingRead {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let mut content = self.content.lock().unwrap();
+            let (data, pos) = &mut *content;
+            let len = buf.len().min(data.len() - *pos);
+            buf[..len].copy_from_slice(&data[*pos..*pos + len]);
+            *pos += len;
+            Ok(len)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tail_growing_capture() {
+        let udp_payload = &SAMPLE_PCAPNG_DATA[118..=173];
+        let reader = GrowingRead::default();
+        // the enhanced packet block is written partially
+        reader.append(&SAMPLE_PCAPNG_DATA[..100]);
+        let mut source = PcapngByteSource::new(reader.clone()).unwrap();
+        assert!(source.load(None).await.unwrap().is_none());
+        assert!(source.load(None).await.unwrap().is_none());
+
+        reader.append(&SAMPLE_PCAPNG_DATA[100..]);
+        let reload_info = source.load(None).await.unwrap().unwrap();
+        assert_eq!(reload_info.newly_loaded_bytes, udp_payload.len());
+        assert_eq!(source.current_slice(), udp_payload);
+        source.consume(udp_payload.len());
+        assert!(source.load(None).await.unwrap().is_none());
+
+        // the next packet
+        reader.append(&SAMPLE_PCAPNG_DATA[48..]);
+        source.load(None).await.unwrap().unwrap();
+        assert_eq!(source.current_slice(), udp_payload);
+        source.consume(udp_payload.len());
+        // nothing is held back at the end of the capture
+        assert!(source.finish().unwrap().is_none());
     }
 
     #[tokio::test]
@@ -0,0 +1,349 @@
+use buf_redux::Buffer;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    net::SocketAddr,
+};
+
+/// Max count of out-of-order bytes buffered per connection. If exceeded, the missing
+/// bytes are given up and the connection continues with the buffered segments.
+const MAX_PENDING_BYTES: usize = 4 * 1024 * 1024;
+/// Max count of bytes of other flows and datagrams held back while the rest of an
+/// incomplete message of a connection is expected.
+const MAX_STALLED_BYTES: usize = 64 * 1024;
+/// Time in ms after which a connection without new segments is considered closed
+const FLOW_TIMEOUT_MS: u64 = 30_000;
+
+/// One direction of a TCP connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FlowKey {
+    pub source: SocketAddr,
+    pub destination: SocketAddr,
+}
+
+/// Reassembly state of one direction of a TCP connection
+struct Flow {
+    /// Sequence number of the next expected byte
+    next_seq: u32,
+    /// Stream offset of the next expected byte
+    offset: u64,
+    /// Out-of-order segments by their stream offset
+    pending: BTreeMap<u64, Vec<u8>>,
+    pending_len: usize,
+    /// Timestamp of the latest segment
+    last_seen: Option<u64>,
+}
+
+impl Flow {
+    fn new(next_seq: u32) -> Self {
+        Self {
+            next_seq,
+            offset: 0,
+            pending: BTreeMap::new(),
+            pending_len: 0,
+            last_seen: None,
+        }
+    }
+
+    /// Appends the in-order part of the segment and all buffered segments following it
+    fn append(&mut self, data: &[u8], output: &mut Vec<u8>) {
+        output.extend_from_slice(data);
+        self.next_seq = self.next_seq.wrapping_add(data.len() as u32);
+        self.offset += data.len() as u64;
+        while let Some(entry) = self.pending.first_entry() {
+            if *entry.key() > self.offset {
+                break;
+            }
+            let start = *entry.key();
+            let data = entry.remove();
+            self.pending_len -= data.len();
+            let overlap = (self.offset - start) as usize;
+            if overlap < data.len() {
+                output.extend_from_slice(&data[overlap..]);
+                let added = data.len() - overlap;
+                self.next_seq = self.next_seq.wrapping_add(added as u32);
+                self.offset += added as u64;
+            }
+        }
+    }
+
+    /// Continues with the first buffered segment, giving up the bytes before it
+    fn skip_gap(&mut self, output: &mut Vec<u8>) {
+        if let Some((&start, _)) = self.pending.first_key_value() {
+            let missing = start - self.offset;
+            self.next_seq = self.next_seq.wrapping_add(missing as u32);
+            self.offset = start;
+            self.append(&[], output);
+        }
+    }
+}
+
+/// Reassembles the byte streams of TCP connections
+///
+/// Segments are ordered by their sequence number; retransmitted and overlapping
+/// bytes are dropped. Each direction of a connection is treated as its own flow.
+#[derive(Default)]
+pub struct TcpReassembler {
+    flows: HashMap<FlowKey, Flow>,
+    /// Timestamp of the latest frame
+    now: Option<u64>,
+}
+
+impl TcpReassembler {
+    /// Adds a segment of a flow and returns the bytes which are in order now.
+    /// A flow is tracked from its first seen segment on; it ends with FIN or RST or if
+    /// no segments are seen for a while.
+    pub fn add_segment(
+        &mut self,
+        key: FlowKey,
+        seq: u32,
+        syn: bool,
+        fin: bool,
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let mut output = Vec::new();
+        // SYN occupies one sequence number
+        let seq = if syn { seq.wrapping_add(1) } else { seq };
+        if syn {
+            if let Some(flow) = self.flows.remove(&key) {
+                report_missing(&key, &flow);
+            }
+        }
+        let flow = self.flows.entry(key).or_insert_with(|| Flow::new(seq));
+        flow.last_seen = self.now;
+        let distance = seq.wrapping_sub(flow.next_seq) as i32;
+        if distance <= 0 {
+            // retransmitted bytes are dropped
+            let known = distance.unsigned_abs() as usize;
+            if known < payload.len() {
+                flow.append(&payload[known..], &mut output);
+            }
+        } else if !payload.is_empty() {
+            let start = flow.offset + distance as u64;
+            let buffered = flow.pending.entry(start).or_default();
+            if buffered.len() < payload.len() {
+                flow.pending_len += payload.len() - buffered.len();
+                *buffered = payload.to_vec();
+            }
+            if flow.pending_len > MAX_PENDING_BYTES {
+                warn!(
+                    "{} -> {}: gave up missing bytes at offset {}",
+                    key.source, key.destination, flow.offset
+                );
+                flow.skip_gap(&mut output);
+            }
+        }
+        if fin {
+            if let Some(flow) = self.flows.remove(&key) {
+                report_missing(&key, &flow);
+            }
+        }
+        output
+    }
+
+    /// Ends the flows without segments within the timeout, measured by the timestamps
+    /// of the frames
+    pub fn expire(&mut self, now: u64) {
+        self.now = Some(now);
+        self.flows.retain(|key, flow| {
+            let idle = flow
+                .last_seen
+                .is_some_and(|seen| now.saturating_sub(seen) > FLOW_TIMEOUT_MS);
+            if idle {
+                debug!(
+                    "{} -> {}: connection timed out",
+                    key.source, key.destination
+                );
+                report_missing(key, flow);
+            }
+            !idle
+        });
+    }
+}
+
+fn report_missing(key: &FlowKey, flow: &Flow) {
+    if !flow.pending.is_empty() {
+        warn!(
+            "{} -> {}: connection ended with missing bytes at offset {}, dropping {} bytes",
+            key.source, key.destination, flow.offset, flow.pending_len
+        );
+    }
+}
+
+/// Payloads of the frames of a network trace, waiting to be delivered to the parser
+///
+/// The bytes of a TCP flow are kept together: as long as the buffer of the source holds
+/// the beginning of a message of an open connection, only data of that flow is delivered
+/// next.
+#[derive(Default)]
+pub struct FramePayloads {
+    tcp: TcpReassembler,
+    ready: VecDeque<(Option<FlowKey>, Vec<u8>)>,
+    ready_len: usize,
+    /// Flow of the data delivered last
+    current: Option<FlowKey>,
+}
+
+impl FramePayloads {
+    /// Adds a TCP segment
+    pub fn add_segment(&mut self, key: FlowKey, seq: u32, syn: bool, fin: bool, payload: &[u8]) {
+        let data = self.tcp.add_segment(key, seq, syn, fin, payload);
+        self.push(Some(key), data);
+    }
+
+    /// Adds the payload of a frame not belonging to a TCP flow
+    pub fn add_datagram(&mut self, payload: &[u8]) {
+        self.push(None, payload.to_vec());
+    }
+
+    /// Ends the connections which are idle at the time of the current frame
+    pub fn expire(&mut self, now: u64) {
+        self.tcp.expire(now);
+    }
+
+    fn push(&mut self, key: Option<FlowKey>, data: Vec<u8>) {
+        if data.is_empty() {
+            return;
+        }
+        self.ready_len += data.len();
+        match self.ready.back_mut() {
+            Some((last, bytes)) if key.is_some() && *last == key => bytes.extend(data),
+            _ => self.ready.push_back((key, data)),
+        }
+    }
+
+    /// Copies the next payload into the buffer and returns the count of copied bytes.
+    /// Returns `None` if no payload can be delivered yet; with `flush` any available
+    /// payload is delivered (e.g. at the end of the trace).
+    pub fn deliver(&mut self, buffer: &mut Buffer, flush: bool) -> Option<usize> {
+        let index = match self.current {
+            // a closed connection won't continue the message
+            Some(key) if !buffer.is_empty() && !flush && self.tcp.flows.contains_key(&key) => {
+                match self.ready.iter().position(|(flow, _)| *flow == Some(key)) {
+                    Some(index) => index,
+                    None if self.ready_len > MAX_STALLED_BYTES => {
+                        warn!(
+                            "{} -> {}: no continuation of the incomplete message",
+                            key.source, key.destination
+                        );
+                        0
+                    }
+                    None => return None,
+                }
+            }
+            _ => 0,
+        };
+        let (key, data) = self.ready.remove(index)?;
+        self.ready_len -= data.len();
+        self.current = key;
+        Some(buffer.copy_from_slice(&data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(port: u16) -> FlowKey {
+        FlowKey {
+            source: SocketAddr::from(([10, 0, 0, 1], port)),
+            destination: SocketAddr::from(([10, 0, 0, 2], 3490)),
+        }
+    }
+
+    #[test]
+    fn test_reorder_segments() {
+        let mut tcp = TcpReassembler::default();
+        assert_eq!(tcp.add_segment(key(1), 99, true, false, b""), b"");
+        assert_eq!(tcp.add_segment(key(1), 100, false, false, b"abc"), b"abc");
+        // out of order
+        assert_eq!(tcp.add_segment(key(1), 106, false, false, b"ghi"), b"");
+        assert_eq!(
+            tcp.add_segment(key(1), 103, false, false, b"def"),
+            b"defghi"
+        );
+        // retransmission and overlap
+        assert_eq!(tcp.add_segment(key(1), 103, false, false, b"def"), b"");
+        assert_eq!(tcp.add_segment(key(1), 107, false, false, b"hijk"), b"jk");
+        // other flow
+        assert_eq!(tcp.add_segment(key(2), 5000, false, false, b"xyz"), b"xyz");
+        assert_eq!(tcp.add_segment(key(1), 111, false, true, b"l"), b"l");
+        // a new connection starts over
+        assert_eq!(tcp.add_segment(key(1), 7, false, false, b"mn"), b"mn");
+    }
+
+    #[test]
+    fn test_sequence_wrap() {
+        let mut tcp = TcpReassembler::default();
+        assert_eq!(
+            tcp.add_segment(key(1), u32::MAX - 1, false, false, b"ab"),
+            b"ab"
+        );
+        assert_eq!(tcp.add_segment(key(1), 2, false, false, b"ef"), b"");
+        assert_eq!(tcp.add_segment(key(1), 0, false, false, b"cd"), b"cdef");
+    }
+
+    #[test]
+    fn test_give_up_gap() {
+        let mut tcp = TcpReassembler::default();
+        assert_eq!(tcp.add_segment(key(1), 0, false, false, b"a"), b"a");
+        let chunk = vec![b'x'; MAX_PENDING_BYTES / 2];
+        assert!(tcp.add_segment(key(1), 10, false, false, &chunk).is_empty());
+        let second = tcp.add_segment(key(1), 10 + chunk.len() as u32, false, false, &chunk);
+        assert_eq!(second.len(), 0);
+        let third = tcp.add_segment(key(1), 10 + 2 * chunk.len() as u32, false, false, b"z");
+        assert_eq!(third.len(), 2 * chunk.len() + 1);
+        assert_eq!(tcp.add_segment(key(1), 1, false, false, b"late"), b"");
+    }
+
+    #[test]
+    fn test_deliver_flows_separately() {
+        let mut payloads = FramePayloads::default();
+        let mut buffer = Buffer::new();
+        payloads.add_segment(key(1), 0, false, false, b"first-");
+        payloads.add_segment(key(2), 0, false, false, b"other");
+        payloads.add_datagram(b"datagram");
+        payloads.add_segment(key(1), 6, false, false, b"second");
+
+        assert_eq!(payloads.deliver(&mut buffer, false), Some(6));
+        // the buffer holds an incomplete message of the first flow
+        assert_eq!(payloads.deliver(&mut buffer, false), Some(6));
+        assert_eq!(buffer.buf(), b"first-second");
+        assert_eq!(payloads.deliver(&mut buffer, false), None);
+        assert_eq!(payloads.deliver(&mut buffer, true), Some(5));
+        // the connection is closed
+        payloads.add_segment(key(2), 5, false, true, b"");
+        assert_eq!(payloads.deliver(&mut buffer, false), Some(8));
+        assert_eq!(buffer.buf(), b"first-secondotherdatagram");
+        assert_eq!(payloads.deliver(&mut buffer, false), None);
+    }
+
+    #[test]
+    fn test_deliver_after_idle_flow() {
+        let mut payloads = FramePayloads::default();
+        let mut buffer = Buffer::new();
+        payloads.expire(1_000);
+        payloads.add_segment(key(1), 0, false, false, b"first-");
+        payloads.add_datagram(b"datagram");
+        assert_eq!(payloads.deliver(&mut buffer, false), Some(6));
+        // the rest of the message of the first flow is expected
+        payloads.expire(1_000 + FLOW_TIMEOUT_MS);
+        assert_eq!(payloads.deliver(&mut buffer, false), None);
+        // the connection is idle for too long
+        payloads.expire(1_001 + FLOW_TIMEOUT_MS);
+        assert_eq!(payloads.deliver(&mut buffer, false), Some(8));
+        assert_eq!(buffer.buf(), b"first-datagram");
+    }
+
+    #[test]
+    fn test_deliver_stalled_data() {
+        let mut payloads = FramePayloads::default();
+        let mut buffer = Buffer::new();
+        payloads.add_segment(key(1), 0, false, false, b"first-");
+        assert_eq!(payloads.deliver(&mut buffer, false), Some(6));
+        let datagram = vec![b'x'; MAX_STALLED_BYTES];
+        payloads.add_datagram(&datagram);
+        assert_eq!(payloads.deliver(&mut buffer, false), None);
+        payloads.add_datagram(b"y");
+        assert_eq!(payloads.deliver(&mut buffer, false), Some(datagram.len()));
+    }
+}
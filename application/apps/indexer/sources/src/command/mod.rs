@@ -1 +1,3 @@
 pub mod process;
+#[cfg(unix)]
+pub mod pty;
@@ -1,9 +1,26 @@
+#[cfg(unix)]
+use super::pty::{self, Pty};
+#[cfg(unix)]
+use crate::factory::PtySize;
 use crate::{sde, ByteSource, Error as SourceError, ReloadInfo, SourceFilter};
 use buf_redux::Buffer;
 use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
 use shellexpand::tilde;
-use std::{collections::HashMap, ffi::OsString, path::PathBuf, process::Stdio};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fmt,
+    path::PathBuf,
+    process::{ExitStatus, Stdio},
+    sync::{Arc, OnceLock},
+};
 use thiserror::Error;
+#[cfg(unix)]
+use tokio::{
+    io::AsyncReadExt,
+    time::{timeout, Duration},
+};
 use tokio::{
     io::AsyncWriteExt,
     process::{Child, ChildStderr, ChildStdin, ChildStdout, Command},
@@ -20,6 +37,19 @@ lazy_static! {
     static ref ESC_RE: Regex = Regex::new(r"\\\s").expect("Regex must compile (fail with ESC_RE)");
 }
 
+/// Marks the lines the child has written to stderr
+pub const STDERR_MARKER: &str = "[stderr] ";
+
+/// Terminal type announced to children running in a pseudo-terminal, unless `TERM` is
+/// set explicitly
+#[cfg(unix)]
+const PTY_TERM: &str = "xterm-256color";
+
+/// Time after which output of a pseudo-terminal without a newline (e.g. a prompt) is
+/// delivered as a line
+#[cfg(unix)]
+const PTY_FLUSH_TIMEOUT: Duration = Duration::from_millis(200);
+
 #[derive(Error, Debug)]
 pub enum ProcessError {
     #[error("{0}")]
@@ -28,16 +58,132 @@ pub enum ProcessError {
     Unrecoverable(String),
 }
 
+/// How the child has ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProcessExit {
+    Code(i32),
+    /// Terminated by the signal (unix platforms only)
+    Signal(i32),
+}
+
+impl ProcessExit {
+    pub fn success(&self) -> bool {
+        matches!(self, Self::Code(0))
+    }
+}
+
+impl From<ExitStatus> for ProcessExit {
+    fn from(status: ExitStatus) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if let Some(signal) = status.signal() {
+                return Self::Signal(signal);
+            }
+        }
+        // there is either a code or a signal
+        Self::Code(status.code().unwrap_or(-1))
+    }
+}
+
+impl fmt::Display for ProcessExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Code(code) => write!(f, "exited with code {code}"),
+            Self::Signal(signal) => write!(f, "was terminated by signal {signal}"),
+        }
+    }
+}
+
+/// Reports how the child of a [`ProcessSource`] has ended, once its output was read
+/// completely
+#[derive(Clone, Default)]
+pub struct ProcessExitReport(Arc<OnceLock<ProcessExit>>);
+
+impl ProcessExitReport {
+    pub fn get(&self) -> Option<ProcessExit> {
+        self.0.get().copied()
+    }
+}
+
+/// Source for the output of a command. Lines written to stderr are prefixed by
+/// [`STDERR_MARKER`]; once the output has ended, a final line reports the exit of the
+/// child.
 pub struct ProcessSource {
     process: Child,
     buffer: Buffer,
-    stdout: FramedRead<ChildStdout, LinesCodec>,
-    stderr: FramedRead<ChildStderr, LinesCodec>,
-    stdin: ChildStdin,
+    io: ProcessIo,
+    exit: ProcessExitReport,
+}
+
+enum ProcessIo {
+    Pipes {
+        stdout: FramedRead<ChildStdout, LinesCodec>,
+        stderr: FramedRead<ChildStderr, LinesCodec>,
+        stdin: ChildStdin,
+        stdout_open: bool,
+        stderr_open: bool,
+    },
+    /// Output and input of the child running in a pseudo-terminal; stdout and stderr
+    /// cannot be told apart there
+    #[cfg(unix)]
+    Pty(PtyOutput),
+}
+
+/// Output of a child running in a pseudo-terminal, split into lines
+#[cfg(unix)]
+struct PtyOutput {
+    pty: Pty,
+    /// Output read but not delivered yet
+    pending: Vec<u8>,
+}
+
+#[cfg(unix)]
+impl PtyOutput {
+    /// Returns the next line. Output without a newline is delivered as a line once no more
+    /// output follows shortly, so prompts waiting for input are shown.
+    /// This function is cancel-safe; read output is kept until it is delivered.
+    async fn next_line(&mut self) -> Option<Result<String, codec::LinesCodecError>> {
+        let mut chunk = [0u8; 4096];
+        loop {
+            if let Some(pos) = self.pending.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = self.pending.drain(..=pos).collect();
+                return Some(Ok(pty_line(&line[..pos])));
+            }
+            let read = if self.pending.is_empty() {
+                self.pty.read(&mut chunk).await
+            } else {
+                match timeout(PTY_FLUSH_TIMEOUT, self.pty.read(&mut chunk)).await {
+                    Ok(read) => read,
+                    Err(_) => Ok(0),
+                }
+            };
+            match read {
+                Ok(0) if self.pending.is_empty() => return None,
+                // the end of the output or no more output for now
+                Ok(0) => {
+                    let line = pty_line(&self.pending);
+                    self.pending.clear();
+                    return Some(Ok(line));
+                }
+                Ok(len) => self.pending.extend_from_slice(&chunk[..len]),
+                Err(err) => return Some(Err(codec::LinesCodecError::Io(err))),
+            }
+        }
+    }
+}
+
+/// Terminals end lines with "\r\n"
+#[cfg(unix)]
+fn pty_line(line: &[u8]) -> String {
+    String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line)).into_owned()
 }
 
 impl Drop for ProcessSource {
     fn drop(&mut self) {
+        if self.exit.get().is_some() {
+            return;
+        }
         if let Err(err) = self.process.start_kill() {
             error!("Fail to kill child process: {err}");
         }
@@ -110,19 +256,55 @@ impl ProcessSource {
             .map_err(|e| ProcessError::Setup(format!("{e}")))
     }
 
-    pub async fn new(
-        command: String,
+    #[cfg(unix)]
+    fn spawn_in_pty(
+        cmd: OsString,
+        args: Vec<OsString>,
         cwd: PathBuf,
         envs: HashMap<String, String>,
-    ) -> Result<Self, ProcessError> {
-        let mut args = ProcessSource::parse_command(&command)?;
-        let cmd = if args.is_empty() {
+        slave: &pty::PtySlave,
+    ) -> Result<Child, ProcessError> {
+        let stdio = || {
+            slave
+                .stdio()
+                .map_err(|e| ProcessError::Setup(format!("{e}")))
+        };
+        let mut command = Command::new(cmd);
+        command
+            .args(args)
+            .current_dir(OsString::from(cwd))
+            .env("TERM", PTY_TERM)
+            .envs(envs)
+            .stdout(stdio()?)
+            .stderr(stdio()?)
+            .stdin(stdio()?)
+            .kill_on_drop(true);
+        // SAFETY: only async-signal-safe functions are called in the child
+        unsafe {
+            command.pre_exec(pty::make_controlling_terminal);
+        }
+        command
+            .spawn()
+            .map_err(|e| ProcessError::Setup(format!("{e}")))
+    }
+
+    fn split_command(command: &str) -> Result<(OsString, Vec<OsString>), ProcessError> {
+        let mut args = ProcessSource::parse_command(command)?;
+        if args.is_empty() {
             return Err(ProcessError::Setup(format!(
                 "Not command has been found in \"{command}\""
             )));
-        } else {
-            args.remove(0)
-        };
+        }
+        let cmd = args.remove(0);
+        Ok((cmd, args))
+    }
+
+    pub async fn new(
+        command: String,
+        cwd: PathBuf,
+        envs: HashMap<String, String>,
+    ) -> Result<Self, ProcessError> {
+        let (cmd, args) = ProcessSource::split_command(&command)?;
         let mut process = ProcessSource::spawn(cmd, args, cwd, envs)?;
         let stdout = codec::FramedRead::new(
             process
@@ -145,11 +327,47 @@ impl ProcessSource {
         Ok(Self {
             process,
             buffer: Buffer::new(),
-            stdout,
-            stderr,
-            stdin,
+            io: ProcessIo::Pipes {
+                stdout,
+                stderr,
+                stdin,
+                stdout_open: true,
+                stderr_open: true,
+            },
+            exit: ProcessExitReport::default(),
+        })
+    }
+
+    /// Runs the command in a pseudo-terminal of the given size, so it behaves as if it was
+    /// started in a shell (e.g. colored and unbuffered output, interactive prompts). Its
+    /// stdout and stderr are read from the terminal and written data is its input.
+    #[cfg(unix)]
+    pub async fn with_pty(
+        command: String,
+        cwd: PathBuf,
+        envs: HashMap<String, String>,
+        size: &PtySize,
+    ) -> Result<Self, ProcessError> {
+        let (cmd, args) = ProcessSource::split_command(&command)?;
+        let (pty, slave) =
+            Pty::open(size).map_err(|e| ProcessError::Setup(format!("Fail to open PTY: {e}")))?;
+        let process = ProcessSource::spawn_in_pty(cmd, args, cwd, envs, &slave)?;
+        // the output ends as soon as the child closed its side of the terminal
+        drop(slave);
+        Ok(Self {
+            process,
+            buffer: Buffer::new(),
+            io: ProcessIo::Pty(PtyOutput {
+                pty,
+                pending: Vec::new(),
+            }),
+            exit: ProcessExitReport::default(),
         })
     }
+
+    pub fn exit_report(&self) -> ProcessExitReport {
+        self.exit.clone()
+    }
 }
 
 impl ByteSource for ProcessSource {
@@ -157,41 +375,50 @@ impl ByteSource for ProcessSource {
         &mut self,
         _filter: Option<&SourceFilter>,
     ) -> Result<Option<ReloadInfo>, SourceError> {
-        let mut closing = false;
-        let mut output;
-        // Implementation is cancel-safe here because there is no data gathered between to await
-        // calls. The only multiple await calls here, are actually while closing where there is
-        // no data to lose anyway.
-        loop {
-            if !closing {
-                output = select! {
-                    res = self.stdout.next() => res,
-                    res = self.stderr.next() => {
-                        if res.is_none() {
-                            closing = true;
+        let output = match &mut self.io {
+            ProcessIo::Pipes {
+                stdout,
+                stderr,
+                stdout_open,
+                stderr_open,
+                ..
+            } => {
+                // Implementation is cancel-safe here because there is no data gathered between
+                // to await calls. A stream is closed only if it has ended.
+                loop {
+                    let (output, from_stderr) = select! {
+                        res = stdout.next(), if *stdout_open => (res, false),
+                        res = stderr.next(), if *stderr_open => (res, true),
+                        else => break None,
+                    };
+                    match output {
+                        Some(Ok(line)) if from_stderr => {
+                            break Some(Ok(format!("{STDERR_MARKER}{line}")));
                         }
-                        res
-                    },
-                };
-                if !closing {
-                    break;
+                        Some(output) => break Some(output),
+                        None if from_stderr => *stderr_open = false,
+                        None => *stdout_open = false,
+                    }
                 }
-            } else {
-                output = self.stdout.next().await;
-                break;
             }
-        }
-        if let Some(Ok(line)) = output {
-            let stored = line.len() + 1;
-            self.buffer.copy_from_slice(line.as_bytes());
-            self.buffer.copy_from_slice(b"\n");
-            let available_bytes = self.buffer.len();
-            Ok(Some(ReloadInfo::new(stored, available_bytes, 0, None)))
-        } else if let Some(Err(err)) = output {
-            Err(SourceError::Unrecoverable(format!("{err}")))
-        } else {
-            Ok(None)
-        }
+            #[cfg(unix)]
+            ProcessIo::Pty(output) => output.next_line().await,
+        };
+        let line = match output {
+            Some(Ok(line)) => line,
+            Some(Err(err)) => return Err(SourceError::Unrecoverable(format!("{err}"))),
+            None if self.exit.get().is_some() => return Ok(None),
+            None => {
+                let exit = ProcessExit::from(self.process.wait().await.map_err(SourceError::Io)?);
+                let _ = self.exit.0.set(exit);
+                format!("--- process {exit} ---")
+            }
+        };
+        let stored = line.len() + 1;
+        self.buffer.copy_from_slice(line.as_bytes());
+        self.buffer.copy_from_slice(b"\n");
+        let available_bytes = self.buffer.len();
+        Ok(Some(ReloadInfo::new(stored, available_bytes, 0, None)))
     }
 
     fn current_slice(&self) -> &[u8] {
@@ -215,7 +442,15 @@ impl ByteSource for ProcessSource {
             sde::SdeRequest::WriteText(ref str) => str.as_bytes(),
             sde::SdeRequest::WriteBytes(ref bytes) => bytes,
         };
-        self.stdin.write_all(bytes).await.map_err(SourceError::Io)?;
+        match &mut self.io {
+            ProcessIo::Pipes { stdin, .. } => {
+                stdin.write_all(bytes).await.map_err(SourceError::Io)?;
+            }
+            #[cfg(unix)]
+            ProcessIo::Pty(output) => {
+                output.pty.write_all(bytes).await.map_err(SourceError::Io)?;
+            }
+        }
         Ok(sde::SdeResponse { bytes: bytes.len() })
     }
 }
@@ -273,6 +508,147 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(unix)]
+    async fn read_lines(process_source: &mut ProcessSource) -> Vec<String> {
+        let mut lines = vec![];
+        while process_source
+            .load(None)
+            .await
+            .expect("Reload data from process source failed")
+            .is_some()
+        {
+            lines.push(String::from_utf8_lossy(process_source.current_slice()).to_string());
+            process_source.consume(process_source.current_slice().len());
+        }
+        lines
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stderr_and_exit() -> Result<(), ProcessError> {
+        use std::env;
+        let mut process_source = ProcessSource::new(
+            String::from(r#"sh -c "echo out; echo err >&2; exit 3""#),
+            env::current_dir().unwrap(),
+            HashMap::new(),
+        )
+        .await?;
+        let exit = process_source.exit_report();
+        let mut lines = read_lines(&mut process_source).await;
+        assert_eq!(
+            lines.pop().as_deref(),
+            Some("--- process exited with code 3 ---\n")
+        );
+        lines.sort();
+        assert_eq!(lines, ["[stderr] err\n", "out\n"]);
+        assert_eq!(exit.get(), Some(ProcessExit::Code(3)));
+
+        let mut process_source = ProcessSource::new(
+            String::from(r#"sh -c "kill -9 $$""#),
+            env::current_dir().unwrap(),
+            HashMap::new(),
+        )
+        .await?;
+        let exit = process_source.exit_report();
+        assert_eq!(
+            read_lines(&mut process_source).await,
+            ["--- process was terminated by signal 9 ---\n"]
+        );
+        assert_eq!(exit.get(), Some(ProcessExit::Signal(9)));
+        assert!(!ProcessExit::Signal(9).success());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pty() -> Result<(), ProcessError> {
+        use std::env;
+        let size = PtySize {
+            rows: 30,
+            cols: 100,
+        };
+        let mut process_source = ProcessSource::with_pty(
+            String::from(r#"sh -c "test -t 1 && stty size && head -n 1""#),
+            env::current_dir().unwrap(),
+            HashMap::new(),
+            &size,
+        )
+        .await?;
+        let mut lines = vec![];
+        while process_source
+            .load(None)
+            .await
+            .expect("Reload data from process source failed")
+            .is_some()
+        {
+            let line = String::from_utf8_lossy(process_source.current_slice()).to_string();
+            process_source.consume(process_source.current_slice().len());
+            if line == "30 100\n" {
+                process_source
+                    .income(sde::SdeRequest::WriteText(String::from("ping\n")))
+                    .await
+                    .expect("Writing to the terminal failed");
+            }
+            lines.push(line);
+        }
+        // the input is echoed by the terminal
+        assert_eq!(
+            lines,
+            [
+                "30 100\n",
+                "ping\n",
+                "ping\n",
+                "--- process exited with code 0 ---\n"
+            ]
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pty_prompt() -> Result<(), ProcessError> {
+        use std::env;
+        let size = PtySize {
+            rows: 30,
+            cols: 100,
+        };
+        let mut process_source = ProcessSource::with_pty(
+            String::from(r#"sh -c "printf 'name? ' && head -n 1""#),
+            env::current_dir().unwrap(),
+            HashMap::new(),
+            &size,
+        )
+        .await?;
+        let mut lines = vec![];
+        while process_source
+            .load(None)
+            .await
+            .expect("Reload data from process source failed")
+            .is_some()
+        {
+            let line = String::from_utf8_lossy(process_source.current_slice()).to_string();
+            process_source.consume(process_source.current_slice().len());
+            // the prompt is delivered although it isn't a complete line
+            if line == "name? \n" {
+                process_source
+                    .income(sde::SdeRequest::WriteText(String::from("ping\n")))
+                    .await
+                    .expect("Writing to the terminal failed");
+            }
+            lines.push(line);
+        }
+        assert_eq!(
+            lines,
+            [
+                "name? \n",
+                "ping\n",
+                "ping\n",
+                "--- process exited with code 0 ---\n"
+            ]
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_source_reload() {
         use std::env;
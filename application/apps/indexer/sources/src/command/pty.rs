@@ -0,0 +1,149 @@
+use crate::factory::PtySize;
+use std::{
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    pin::Pin,
+    process::Stdio,
+    task::{ready, Context, Poll},
+};
+use tokio::io::{unix::AsyncFd, AsyncRead, ReadBuf};
+
+/// Master side of a pseudo-terminal. Reading delivers the output of the child running in
+/// the terminal, writing is its input.
+pub struct Pty {
+    master: AsyncFd<OwnedFd>,
+}
+
+/// Terminal side of a [`Pty`], which becomes stdin, stdout and stderr of the child
+pub struct PtySlave(OwnedFd);
+
+impl Pty {
+    pub fn open(size: &PtySize) -> io::Result<(Self, PtySlave)> {
+        let mut winsize = libc::winsize {
+            ws_row: size.rows,
+            ws_col: size.cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        let (mut master, mut slave) = (0, 0);
+        // SAFETY: the pointers refer to valid locals; no name buffer is requested. The window
+        // size is passed mutable as some platforms declare it so
+        let res = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::addr_of_mut!(winsize),
+            )
+        };
+        if res != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: both descriptors were just opened and are owned by nobody else
+        let (master, slave) =
+            unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+        set_nonblocking(&master)?;
+        Ok((
+            Self {
+                master: AsyncFd::new(master)?,
+            },
+            PtySlave(slave),
+        ))
+    }
+
+    /// Writes all bytes to the terminal as if they were typed
+    pub async fn write_all(&self, mut bytes: &[u8]) -> io::Result<()> {
+        while !bytes.is_empty() {
+            let mut guard = self.master.writable().await?;
+            match guard.try_io(|fd| {
+                // SAFETY: the buffer is valid for its length
+                let res =
+                    unsafe { libc::write(fd.as_raw_fd(), bytes.as_ptr() as *const _, bytes.len()) };
+                if res < 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(res as usize)
+                }
+            }) {
+                Ok(written) => bytes = &bytes[written?..],
+                Err(_would_block) => continue,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AsyncRead for Pty {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            let mut guard = ready!(self.master.poll_read_ready(cx))?;
+            let unfilled = buf.initialize_unfilled();
+            match guard.try_io(|fd| {
+                // SAFETY: the buffer is valid for its length
+                let res = unsafe {
+                    libc::read(
+                        fd.as_raw_fd(),
+                        unfilled.as_mut_ptr() as *mut _,
+                        unfilled.len(),
+                    )
+                };
+                if res < 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(res as usize)
+                }
+            }) {
+                Ok(Ok(read)) => {
+                    buf.advance(read);
+                    return Poll::Ready(Ok(()));
+                }
+                // Linux reports EIO once the child and all its descendants closed the
+                // terminal, which is the end of the output
+                Ok(Err(err)) if err.raw_os_error() == Some(libc::EIO) => {
+                    return Poll::Ready(Ok(()))
+                }
+                Ok(Err(err)) => return Poll::Ready(Err(err)),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+impl PtySlave {
+    /// Returns a handle for one of the standard streams of the child
+    pub fn stdio(&self) -> io::Result<Stdio> {
+        Ok(Stdio::from(self.0.try_clone()?))
+    }
+}
+
+/// Makes the child the leader of a new session with the terminal as its controlling
+/// terminal, so job control and `isatty` checks work as in a shell. Has to be called
+/// between fork and exec, with stdin being the terminal.
+pub fn make_controlling_terminal() -> io::Result<()> {
+    // SAFETY: both calls are async-signal-safe and only affect the child
+    unsafe {
+        if libc::setsid() < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if libc::ioctl(0, libc::TIOCSCTTY as _, 0) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn set_nonblocking(fd: &OwnedFd) -> io::Result<()> {
+    // SAFETY: the descriptor is valid as it's owned
+    unsafe {
+        let flags = libc::fcntl(fd.as_raw_fd(), libc::F_GETFL);
+        if flags < 0 || libc::fcntl(fd.as_raw_fd(), libc::F_SETFL, flags | libc::O_NONBLOCK) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
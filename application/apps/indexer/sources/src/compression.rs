@@ -0,0 +1,228 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    sync::Mutex,
+};
+
+/// Compression formats of input files, detected by their magic bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Xz,
+    Bzip2,
+}
+
+impl Compression {
+    /// Count of bytes needed to detect any of the formats
+    pub const MAGIC_LEN: usize = 6;
+
+    /// Detects the compression from the first bytes of a file
+    pub fn detect(head: &[u8]) -> Option<Self> {
+        match head {
+            [0x1f, 0x8b, ..] => Some(Self::Gzip),
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Self::Zstd),
+            [0xfd, b'7', b'z', b'X', b'Z', 0x00, ..] => Some(Self::Xz),
+            // the block size follows the magic
+            [b'B', b'Z', b'h', b'1'..=b'9', ..] => Some(Self::Bzip2),
+            _ => None,
+        }
+    }
+
+    /// Detects the compression of the file; `None` for uncompressed files
+    pub fn of_file(path: &Path) -> io::Result<Option<Self>> {
+        let mut head = Vec::with_capacity(Self::MAGIC_LEN);
+        File::open(path)?
+            .take(Self::MAGIC_LEN as u64)
+            .read_to_end(&mut head)?;
+        Ok(Self::detect(&head))
+    }
+
+    /// File extensions used for the format
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Self::Gzip => &["gz", "gzip"],
+            Self::Zstd => &["zst", "zstd"],
+            Self::Xz => &["xz"],
+            Self::Bzip2 => &["bz2", "bzip2"],
+        }
+    }
+
+    /// Wraps the reader into a reader of the decompressed content. Concatenated
+    /// streams (e.g. appended gzip members) are decompressed as one.
+    pub fn decoder<'a, R: Read + Send + 'a>(
+        &self,
+        reader: R,
+    ) -> io::Result<Box<dyn Read + Send + 'a>> {
+        let reader = BufReader::new(reader);
+        Ok(match self {
+            Self::Gzip => Box::new(flate2::bufread::MultiGzDecoder::new(reader)),
+            Self::Zstd => Box::new(zstd::Decoder::with_buffer(reader)?),
+            Self::Xz => Box::new(xz2::bufread::XzDecoder::new_multi_decoder(reader)),
+            Self::Bzip2 => Box::new(bzip2::bufread::MultiBzDecoder::new(reader)),
+        })
+    }
+}
+
+/// Input file for the sources, which is decompressed while it is read if it's compressed
+pub enum Input {
+    Plain(File),
+    Decompressed(Decompressed),
+}
+
+impl Input {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(match Compression::of_file(path)? {
+            Some(compression) => {
+                Self::Decompressed(Decompressed::new(compression, File::open(path)?)?)
+            }
+            None => Self::Plain(File::open(path)?),
+        })
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(file) => file.read(buf),
+            Self::Decompressed(decompressed) => decompressed.read(buf),
+        }
+    }
+}
+
+impl Seek for Input {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Self::Plain(file) => file.seek(pos),
+            Self::Decompressed(decompressed) => decompressed.seek(pos),
+        }
+    }
+}
+
+/// Reader of the decompressed content of a stream. It can seek forward only, since the
+/// content is decompressed on the fly.
+pub struct Decompressed {
+    // the decoders aren't shareable between threads, while the sources have to be
+    decoder: Mutex<Box<dyn Read + Send>>,
+    position: u64,
+}
+
+impl Decompressed {
+    pub fn new<R: Read + Send + 'static>(compression: Compression, reader: R) -> io::Result<Self> {
+        Ok(Self {
+            decoder: Mutex::new(compression.decoder(reader)?),
+            position: 0,
+        })
+    }
+}
+
+impl Read for Decompressed {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let decoder = self
+            .decoder
+            .get_mut()
+            .map_err(|_| io::Error::other("Decoder is poisoned"))?;
+        let read = decoder.read(buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for Decompressed {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(_) => None,
+        };
+        match target {
+            Some(target) if target >= self.position => {
+                let skip = target - self.position;
+                let skipped = io::copy(&mut self.by_ref().take(skip), &mut io::sink())?;
+                if skipped < skip {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                Ok(self.position)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Decompressed content can be read forward only",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression as Level};
+    use std::io::{Cursor, Write};
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            Compression::detect(&[0x1f, 0x8b, 0x08]),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd, 0x04]),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(
+            Compression::detect(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]),
+            Some(Compression::Xz)
+        );
+        assert_eq!(Compression::detect(b"BZh91AY"), Some(Compression::Bzip2));
+        assert_eq!(Compression::detect(b"BZh is text"), None);
+        assert_eq!(Compression::detect(&[0x0a, 0x0d, 0x0d, 0x0a]), None);
+        assert_eq!(Compression::detect(&[0x1f]), None);
+    }
+
+    #[test]
+    fn test_decompress_concatenated_members() {
+        let mut compressed = Vec::new();
+        for member in [&b"first line\n"[..], b"second line\n"] {
+            let mut encoder = GzEncoder::new(Vec::new(), Level::default());
+            encoder.write_all(member).unwrap();
+            compressed.extend(encoder.finish().unwrap());
+        }
+        let compression = Compression::detect(&compressed).unwrap();
+        let mut content = String::new();
+        compression
+            .decoder(compressed.as_slice())
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "first line\nsecond line\n");
+    }
+
+    #[test]
+    fn test_decompressed_seek() {
+        let mut encoder = GzEncoder::new(Vec::new(), Level::default());
+        encoder.write_all(b"0123456789").unwrap();
+        let compressed = encoder.finish().unwrap();
+        let mut decompressed =
+            Decompressed::new(Compression::Gzip, Cursor::new(compressed)).unwrap();
+        assert_eq!(decompressed.seek(SeekFrom::Start(2)).unwrap(), 2);
+        assert_eq!(decompressed.seek(SeekFrom::Current(3)).unwrap(), 5);
+        assert!(decompressed.seek(SeekFrom::Start(1)).is_err());
+        let mut rest = String::new();
+        decompressed.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "56789");
+        assert!(decompressed.seek(SeekFrom::Current(1)).is_err());
+    }
+
+    #[test]
+    fn test_decompress_zstd() {
+        let compressed = zstd::encode_all(&b"some log line\n"[..], 0).unwrap();
+        let mut content = Vec::new();
+        Compression::detect(&compressed)
+            .unwrap()
+            .decoder(compressed.as_slice())
+            .unwrap()
+            .read_to_end(&mut content)
+            .unwrap();
+        assert_eq!(content, b"some log line\n");
+    }
+}
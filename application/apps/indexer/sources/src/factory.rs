@@ -1,7 +1,8 @@
+use crate::SourceFilter;
 use indexer_base::config::MulticastInfo;
-use parsers::dlt;
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::PathBuf};
+use parsers::{dlt, text::TextEncoding};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
 use uuid::Uuid;
 
 #[allow(clippy::large_enum_variant)]
@@ -9,7 +10,10 @@ use uuid::Uuid;
 pub enum ParserType {
     Dlt(DltParserSettings),
     SomeIp(SomeIpParserSettings),
-    Text,
+    Text(#[serde(deserialize_with = "text_settings_or_default")] TextParserSettings),
+    Columns(ColumnsParserSettings),
+    JsonLines(JsonLinesParserSettings),
+    Syslog(SyslogParserSettings),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -67,12 +71,56 @@ pub struct SomeIpParserSettings {
     pub fibex_file_paths: Option<Vec<String>>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TextParserSettings {
+    #[serde(default)]
+    pub encoding: TextEncoding,
+    /// Regex matching the first line of a record. If set, following lines which don't
+    /// match are joined to the record (e.g. stack traces).
+    #[serde(default)]
+    pub record_start: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ColumnsParserSettings {
+    /// Regex with named capture groups, each group defines a column
+    pub pattern: String,
+    #[serde(default)]
+    pub encoding: TextEncoding,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JsonLinesParserSettings {
+    /// Key paths (e.g. `ctx.request_id`) of the values to show as columns
+    pub keys: Vec<String>,
+    #[serde(default)]
+    pub encoding: TextEncoding,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SyslogParserSettings {
+    /// Set for sources loading one datagram at a time (UDP, Unix datagram sockets)
+    #[serde(skip)]
+    pub datagrams: bool,
+}
+
+/// Clients without any text settings send `null` for them
+fn text_settings_or_default<'de, D>(deserializer: D) -> Result<TextParserSettings, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<TextParserSettings>::deserialize(deserializer)?.unwrap_or_default())
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum Transport {
     Process(ProcessTransportConfig),
     TCP(TCPTransportConfig),
+    TCPServer(TCPServerTransportConfig),
     UDP(UDPTransportConfig),
     Serial(SerialTransportConfig),
+    Unix(UnixTransportConfig),
+    WebSocket(WebSocketTransportConfig),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -80,6 +128,17 @@ pub struct ProcessTransportConfig {
     pub cwd: PathBuf,
     pub command: String,
     pub envs: HashMap<String, String>,
+    /// Runs the command in a pseudo-terminal of this size (available on unix platforms
+    /// only)
+    #[serde(default)]
+    pub pty: Option<PtySize>,
+}
+
+/// Window size of a pseudo-terminal
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PtySize {
+    pub rows: u16,
+    pub cols: u16,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -92,11 +151,50 @@ pub struct SerialTransportConfig {
     pub stop_bits: u8,
     pub send_data_delay: u8,
     pub exclusive: bool,
+    /// Opens the port again after the device was lost (e.g. unplugged)
+    #[serde(default)]
+    pub reconnect: Option<ReconnectPolicy>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TCPTransportConfig {
     pub bind_addr: String,
+    /// Connects again after the connection was closed by the remote end
+    #[serde(default)]
+    pub reconnect: Option<ReconnectPolicy>,
+}
+
+/// Attempts to restore a lost connection with exponential backoff
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// Maximum count of attempts after the connection was lost; `None` for attempting
+    /// until the observing is stopped
+    pub max_attempts: Option<u32>,
+    /// Delay before the first attempt; it's doubled with each further attempt
+    pub initial_delay_ms: u64,
+    /// Upper limit of the delay between attempts
+    pub max_delay_ms: u64,
+}
+
+impl ReconnectPolicy {
+    /// Returns the delay before the attempt (counted from 0) or `None` if there are
+    /// no attempts left
+    pub fn delay(&self, attempt: u32) -> Option<Duration> {
+        if self.max_attempts.is_some_and(|max| attempt >= max) {
+            return None;
+        }
+        let delay = self
+            .initial_delay_ms
+            .saturating_mul(2u64.saturating_pow(attempt))
+            .min(self.max_delay_ms);
+        Some(Duration::from_millis(delay))
+    }
+}
+
+/// Listens for incoming connections; messages are framed as described in RFC 6587
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TCPServerTransportConfig {
+    pub bind_addr: String,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -105,6 +203,31 @@ pub struct UDPTransportConfig {
     pub multicast: Vec<MulticastInfo>,
 }
 
+/// Unix domain socket (available on unix platforms only)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UnixTransportConfig {
+    pub path: PathBuf,
+    pub socket_type: UnixSocketType,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum UnixSocketType {
+    /// Connects to the socket listening at the path
+    Stream,
+    /// Binds the socket to the path and receives the datagrams sent to it
+    Datagram,
+}
+
+/// Client of a WebSocket server; text frames are read as lines, binary frames as raw bytes
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebSocketTransportConfig {
+    /// `ws://` URL of the server
+    pub url: String,
+    /// Headers of the opening handshake (e.g. for authorization)
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum FileFormat {
     PcapNG,
@@ -118,12 +241,18 @@ pub enum ObserveOrigin {
     File(String, FileFormat, PathBuf),
     Concat(Vec<(String, FileFormat, PathBuf)>),
     Stream(String, Transport),
+    /// Files in the directory whose names match the glob pattern, including files which
+    /// are created later. Each file is a source of its own.
+    Directory(PathBuf, String, FileFormat),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ObserveOptions {
     pub origin: ObserveOrigin,
     pub parser: ParserType,
+    /// Filter for the frames of network traces (pcap/pcapng files)
+    #[serde(default)]
+    pub filter: Option<SourceFilter>,
 }
 
 impl ObserveOptions {
@@ -131,6 +260,33 @@ impl ObserveOptions {
         ObserveOptions {
             origin: ObserveOrigin::File(Uuid::new_v4().to_string(), file_origin, filename),
             parser,
+            filter: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconnect_delays() {
+        let policy = ReconnectPolicy {
+            max_attempts: Some(5),
+            initial_delay_ms: 100,
+            max_delay_ms: 1000,
+        };
+        let delays = (0..6)
+            .map(|attempt| policy.delay(attempt).map(|delay| delay.as_millis()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            vec![Some(100), Some(200), Some(400), Some(800), Some(1000), None]
+        );
+        let unlimited = ReconnectPolicy {
+            max_attempts: None,
+            ..policy
+        };
+        assert_eq!(unlimited.delay(100), Some(Duration::from_millis(1000)));
+    }
+}
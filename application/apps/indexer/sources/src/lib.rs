@@ -1,6 +1,8 @@
 // Rust can't currently distinguish between dev and none-dev dependencies at the moment. There is
 // an open issue for this case: "https://github.com/rust-lang/rust/issues/129637"
 
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 use thiserror::Error;
 
 #[macro_use]
@@ -12,15 +14,17 @@ extern crate log;
 #[cfg(test)]
 mod tests;
 
+pub mod archive;
 pub mod binary;
 pub mod command;
+pub mod compression;
 pub mod factory;
 pub mod producer;
 pub mod sde;
 pub mod serial;
 pub mod socket;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TransportProtocol {
     TCP,
     UDP,
@@ -37,9 +41,90 @@ impl From<etherparse::TransportSlice<'_>> for TransportProtocol {
     }
 }
 
-#[derive(Debug)]
+/// Filter for the frames of network traces. Only the payload of matching frames is
+/// delivered; an empty list of values doesn't restrict the frames.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SourceFilter {
-    transport: Option<TransportProtocol>,
+    pub transport: Option<TransportProtocol>,
+    pub source_ips: Vec<IpAddr>,
+    pub destination_ips: Vec<IpAddr>,
+    pub source_ports: Vec<PortRange>,
+    pub destination_ports: Vec<PortRange>,
+    /// Matches the outer or inner VLAN ID of a frame
+    pub vlan_ids: Vec<u16>,
+}
+
+/// Inclusive range of ports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortRange {
+    pub from: u16,
+    pub to: u16,
+}
+
+impl PortRange {
+    pub fn contains(&self, port: u16) -> bool {
+        (self.from..=self.to).contains(&port)
+    }
+}
+
+impl SourceFilter {
+    /// Checks if the frame passes the filter
+    pub(crate) fn matches(&self, packet: &etherparse::SlicedPacket) -> bool {
+        use etherparse::{TransportSlice, VlanSlice};
+
+        if !self.vlan_ids.is_empty() {
+            let vlan_ids = match packet.vlan.as_ref() {
+                Some(VlanSlice::SingleVlan(vlan)) => vec![vlan.vlan_identifier()],
+                Some(VlanSlice::DoubleVlan(vlan)) => vec![
+                    vlan.outer().vlan_identifier(),
+                    vlan.inner().vlan_identifier(),
+                ],
+                None => vec![],
+            };
+            if !vlan_ids.iter().any(|id| self.vlan_ids.contains(id)) {
+                return false;
+            }
+        }
+        if !self.source_ips.is_empty() || !self.destination_ips.is_empty() {
+            let Some((source, destination)) = binary::pcap::ip_addresses(packet) else {
+                return false;
+            };
+            if !matches_any(&self.source_ips, |ip| *ip == source)
+                || !matches_any(&self.destination_ips, |ip| *ip == destination)
+            {
+                return false;
+            }
+        }
+        if let Some(wanted) = self.transport.as_ref() {
+            match packet.transport.as_ref() {
+                Some(actual) if TransportProtocol::from(actual.clone()) == *wanted => {}
+                _ => return false,
+            }
+        }
+        if !self.source_ports.is_empty() || !self.destination_ports.is_empty() {
+            let (source, destination) = match packet.transport.as_ref() {
+                Some(TransportSlice::Udp(header)) => {
+                    (header.source_port(), header.destination_port())
+                }
+                Some(TransportSlice::Tcp(header)) => {
+                    (header.source_port(), header.destination_port())
+                }
+                _ => return false,
+            };
+            if !matches_any(&self.source_ports, |range| range.contains(source))
+                || !matches_any(&self.destination_ports, |range| range.contains(destination))
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// An empty list of accepted values matches everything
+fn matches_any<T>(accepted: &[T], predicate: impl Fn(&T) -> bool) -> bool {
+    accepted.is_empty() || accepted.iter().any(predicate)
 }
 
 #[derive(Debug)]
@@ -125,6 +210,20 @@ pub trait ByteSource: Send + Sync {
     /// implementing the method [`ByteSource::income()`].
     async fn load(&mut self, filter: Option<&SourceFilter>) -> Result<Option<ReloadInfo>, Error>;
 
+    /// Called once [`ByteSource::load()`] delivers no more data and no more is expected
+    /// for now (e.g. a tailed file isn't tailed anymore or was idle for a while). Sources
+    /// holding back data until more input arrives load it here; this function is called
+    /// until it returns Ok(None). Loading continues if the input grows afterwards.
+    fn finish(&mut self) -> Result<Option<ReloadInfo>, Error> {
+        Ok(None)
+    }
+
+    /// Describes problems with the input which didn't stop the reading (e.g. network
+    /// packets which couldn't be reassembled), known once the source was finished
+    fn report(&self) -> Option<String> {
+        None
+    }
+
     /// In case the ByteSource is some kind of connection that does not end,
     /// cancel can be implemented that will give the ByteSource the chance to perform some
     /// cleanup before the ByteSource is discarded
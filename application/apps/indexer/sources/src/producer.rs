@@ -4,7 +4,7 @@ mod tests;
 use crate::{sde::SdeMsg, ByteSource, ReloadInfo, SourceFilter};
 use async_stream::stream;
 use log::warn;
-use parsers::{Error as ParserError, LogMessage, MessageStreamItem, Parser};
+use parsers::{Error as ParserError, LogMessage, MessageStreamItem, ParseYield, Parser};
 use std::marker::PhantomData;
 use tokio::{
     select,
@@ -36,6 +36,11 @@ where
     total_loaded: usize,
     total_skipped: usize,
     done: bool,
+    tailing: bool,
+    /// the data held back is delivered although the source is tailed
+    flushing: bool,
+    /// the message delivered by the last flush, which might be continued
+    flushed: Option<String>,
     rx_sde: Option<SdeReceiver>,
 }
 
@@ -52,9 +57,46 @@ impl<T: LogMessage, P: Parser<T>, D: ByteSource> MessageProducer<T, P, D> {
             total_loaded: 0,
             total_skipped: 0,
             done: false,
+            tailing: false,
+            flushing: false,
+            flushed: None,
             rx_sde,
         }
     }
+    /// only load the data of frames passing the filter from the byte source
+    pub fn with_filter(mut self, filter: Option<SourceFilter>) -> Self {
+        self.filter = filter;
+        self
+    }
+    /// the end of the byte source is only temporary (e.g. a tailed file), so data held
+    /// back by the parser is kept until [`MessageProducer::finish`] is called
+    pub fn with_tailing(mut self, tailing: bool) -> Self {
+        self.tailing = tailing;
+        self
+    }
+    /// continue producing after the byte source ran out of data, e.g. once a tailed
+    /// file has grown
+    pub fn resume(&mut self) {
+        self.done = false;
+        self.flushing = false;
+    }
+    /// a tailed source is idle at its end; the data held back so far is delivered with
+    /// the next segments. The source and parser continue afterwards: if more data continues
+    /// the record delivered last, the whole record is delivered again.
+    pub fn flush(&mut self) {
+        self.flushing = true;
+        self.done = false;
+    }
+    /// the end of the input is reached for good; the data held back so far is delivered
+    /// with the next segments
+    pub fn finish(&mut self) {
+        self.tailing = false;
+        self.done = false;
+    }
+    /// problems with the input reported by the byte source, known once it's finished or idle
+    pub fn report(&self) -> Option<String> {
+        self.byte_source.report()
+    }
     /// create a stream of pairs that contain the count of all consumed bytes and the
     /// MessageStreamItems in a boxed slice
     pub fn as_stream(&mut self) -> impl Stream<Item = Box<[(usize, MessageStreamItem<T>)]>> + '_ {
@@ -169,8 +211,18 @@ impl<T: LogMessage, P: Parser<T>, D: ByteSource> MessageProducer<T, P, D> {
                     })
                     .collect::<Box<[_]>>()
                 }) {
-                Ok(items) => {
+                Ok(mut items) => {
                     self.byte_source.consume(total_consumed);
+                    if let Some(flushed) = self.flushed.take() {
+                        // the record delivered by the flush wasn't continued
+                        if let Some((_, item)) =
+                            items.iter_mut().find(|(_, item)| rendered(item).is_some())
+                        {
+                            if rendered(item).as_ref() == Some(&flushed) {
+                                *item = MessageStreamItem::Skipped;
+                            }
+                        }
+                    }
                     return Some(items);
                 }
                 Err(ParserError::Incomplete) => {
@@ -179,6 +231,36 @@ impl<T: LogMessage, P: Parser<T>, D: ByteSource> MessageProducer<T, P, D> {
 
                     // Stop if there is no new available bytes.
                     if newly_loaded == 0 {
+                        // Parsers holding back data deliver it at the real end or once a
+                        // tailed source is idle; a tailed source might continue the held
+                        // back record, so it's kept then
+                        let rest = if self.tailing && !self.flushing {
+                            None
+                        } else {
+                            self.parser.parse_rest(self.byte_source.current_slice())
+                        };
+                        if let Some((consumed, item)) = rest {
+                            let item = MessageStreamItem::Item(item);
+                            let delivered =
+                                self.flushed.is_some() && rendered(&item) == self.flushed;
+                            if !self.tailing {
+                                self.flushed = None;
+                                self.byte_source.consume(consumed);
+                                return Some(Box::new([(
+                                    consumed + skipped_bytes,
+                                    if delivered {
+                                        MessageStreamItem::Skipped
+                                    } else {
+                                        item
+                                    },
+                                )]));
+                            }
+                            if !delivered {
+                                // the bytes are consumed with the complete record
+                                self.flushed = rendered(&item);
+                                return Some(Box::new([(0, item)]));
+                            }
+                        }
                         trace!("No new bytes has been added. Returning Done");
                         let unused = skipped_bytes + available;
                         self.done = true;
@@ -239,7 +321,25 @@ impl<T: LogMessage, P: Parser<T>, D: ByteSource> MessageProducer<T, P, D> {
     ///
     /// Option<(newly_loaded_bytes, available_bytes, skipped_bytes)>
     async fn load(&mut self) -> Option<(usize, usize, usize)> {
-        match self.byte_source.load(self.filter.as_ref()).await {
+        let mut reloaded = self.byte_source.load(self.filter.as_ref()).await;
+        if (!self.tailing || self.flushing)
+            && matches!(
+                reloaded,
+                Ok(None)
+                    | Ok(Some(ReloadInfo {
+                        newly_loaded_bytes: 0,
+                        ..
+                    }))
+            )
+        {
+            // the (possibly temporary) end of the input: the source delivers the data it
+            // held back
+            match self.byte_source.finish() {
+                Ok(None) => {}
+                finished => reloaded = finished,
+            }
+        }
+        match reloaded {
             Ok(Some(ReloadInfo {
                 newly_loaded_bytes,
                 available_bytes,
@@ -277,3 +377,14 @@ impl<T: LogMessage, P: Parser<T>, D: ByteSource> MessageProducer<T, P, D> {
         }
     }
 }
+
+/// Content of a message as written into the session
+fn rendered<T: LogMessage>(item: &MessageStreamItem<T>) -> Option<String> {
+    match item {
+        MessageStreamItem::Item(ParseYield::Message(msg))
+        | MessageStreamItem::Item(ParseYield::MessageAndAttachment((msg, _))) => {
+            Some(msg.to_string())
+        }
+        _ => None,
+    }
+}
@@ -1,2 +1,6 @@
 pub mod tcp;
+pub mod tcp_server;
 pub mod udp;
+#[cfg(unix)]
+pub mod unix;
+pub mod websocket;
@@ -0,0 +1,286 @@
+use crate::{ByteSource, Error as SourceError, ReloadInfo, SourceFilter};
+use buf_redux::Buffer;
+use std::net::SocketAddr;
+use tokio::{
+    io::AsyncReadExt,
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+const MAX_READ_SIZE: usize = 65_507;
+/// Max. size of a message; the bytes of a longer one are delivered once they exceed it
+const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+/// Accepts incoming TCP connections, e.g. of devices pushing their syslog messages
+pub struct TcpServer {
+    listener: TcpListener,
+}
+
+impl TcpServer {
+    pub async fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self, std::io::Error> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, std::io::Error> {
+        self.listener.local_addr()
+    }
+
+    /// Waits for the next client
+    pub async fn accept(&self) -> Result<TcpClientSource, std::io::Error> {
+        let (socket, peer) = self.listener.accept().await?;
+        debug!("Accepted TCP client {peer}");
+        Ok(TcpClientSource::new(socket, peer))
+    }
+}
+
+/// Source for the data of one client connected to a [`TcpServer`]
+///
+/// Messages are framed as described in RFC 6587, either by octet counting
+/// (`LEN SP MSG`) or terminated by a newline (or NUL). Only complete messages are
+/// delivered; messages with the newline framing are delivered with a trailing
+/// newline and messages which contain a newline keep their octet counting.
+/// Messages longer than 64 KiB are split.
+pub struct TcpClientSource {
+    buffer: Buffer,
+    socket: TcpStream,
+    peer: SocketAddr,
+    /// Received bytes which don't form a complete message yet
+    pending: Vec<u8>,
+    tmp_buffer: Vec<u8>,
+}
+
+impl TcpClientSource {
+    fn new(socket: TcpStream, peer: SocketAddr) -> Self {
+        Self {
+            buffer: Buffer::new(),
+            socket,
+            peer,
+            pending: Vec::new(),
+            tmp_buffer: vec![0u8; MAX_READ_SIZE],
+        }
+    }
+
+    /// Address of the connected client
+    pub fn peer(&self) -> SocketAddr {
+        self.peer
+    }
+
+    /// Moves all complete messages from the pending bytes into the buffer.
+    /// Returns the count of added bytes.
+    fn take_messages(&mut self) -> usize {
+        let mut added = 0;
+        let mut pos = 0;
+        while let Some((consumed, frame)) = next_frame(&self.pending[pos..]) {
+            pos += consumed;
+            // e.g. a newline after an octet counted message
+            if let Frame::Terminated([]) = frame {
+                continue;
+            }
+            added += add_message(&mut self.buffer, frame);
+        }
+        self.pending.drain(..pos);
+        if self.pending.len() > MAX_MESSAGE_SIZE {
+            // the rest of the message follows as message of its own
+            warn!(
+                "{} sent a message exceeding {MAX_MESSAGE_SIZE} bytes, it's split",
+                self.peer
+            );
+            let pending = std::mem::take(&mut self.pending);
+            let start = octet_count(&pending).map_or(0, |(start, _)| start);
+            added += add_message(&mut self.buffer, Frame::OctetCounted(&pending[start..]));
+        }
+        added
+    }
+}
+
+enum Frame<'a> {
+    OctetCounted(&'a [u8]),
+    Terminated(&'a [u8]),
+}
+
+/// Adds the message to the buffer and returns the count of added bytes
+fn add_message(buffer: &mut Buffer, frame: Frame) -> usize {
+    let normalized = match frame {
+        Frame::OctetCounted(msg) if msg.contains(&b'\n') => {
+            let mut normalized = format!("{} ", msg.len()).into_bytes();
+            normalized.extend_from_slice(msg);
+            normalized
+        }
+        Frame::OctetCounted(msg) | Frame::Terminated(msg) => {
+            let mut normalized = msg.to_vec();
+            normalized.push(b'\n');
+            normalized
+        }
+    };
+    buffer.reserve(normalized.len());
+    buffer.copy_from_slice(&normalized);
+    normalized.len()
+}
+
+/// Parses the prefix of the octet counting framing; returns the start of the message
+/// together with its length
+fn octet_count(input: &[u8]) -> Option<(usize, usize)> {
+    let digits = input.iter().take_while(|b| b.is_ascii_digit()).count();
+    if digits == 0
+        || digits >= 10
+        || input[0] == b'0'
+        || input.get(digits) != Some(&b' ')
+        || input.get(digits + 1).is_some_and(|b| *b != b'<')
+    {
+        return None;
+    }
+    let len = std::str::from_utf8(&input[..digits])
+        .ok()?
+        .parse::<usize>()
+        .ok()?;
+    Some((digits + 1, len))
+}
+
+/// Finds the first complete message in `input` (RFC 6587)
+fn next_frame(input: &[u8]) -> Option<(usize, Frame<'_>)> {
+    if let Some((start, len)) = octet_count(input) {
+        return input
+            .get(start..start + len)
+            .map(|msg| (start + len, Frame::OctetCounted(msg)));
+    }
+    let end = input.iter().position(|b| *b == b'\n' || *b == b'\0')?;
+    let msg = &input[..end];
+    let msg = msg.strip_suffix(b"\r").unwrap_or(msg);
+    Some((end + 1, Frame::Terminated(msg)))
+}
+
+impl ByteSource for TcpClientSource {
+    async fn load(
+        &mut self,
+        _filter: Option<&SourceFilter>,
+    ) -> Result<Option<ReloadInfo>, SourceError> {
+        loop {
+            let len = self
+                .socket
+                .read(&mut self.tmp_buffer)
+                .await
+                .map_err(|e| SourceError::Unrecoverable(format!("{e}")))?;
+            trace!("---> Received {} bytes from {}", len, self.peer);
+            if len == 0 {
+                // the client closed the connection; an incomplete message is dropped
+                if !self.pending.is_empty() {
+                    warn!(
+                        "{} closed the connection, dropping {} bytes of an incomplete message",
+                        self.peer,
+                        self.pending.len()
+                    );
+                    self.pending.clear();
+                }
+                return Ok(Some(ReloadInfo::new(0, self.buffer.len(), 0, None)));
+            }
+            self.pending.extend_from_slice(&self.tmp_buffer[..len]);
+            let added = self.take_messages();
+            if added > 0 {
+                return Ok(Some(ReloadInfo::new(added, self.buffer.len(), 0, None)));
+            }
+        }
+    }
+
+    fn current_slice(&self) -> &[u8] {
+        self.buffer.buf()
+    }
+
+    fn consume(&mut self, offset: usize) {
+        self.buffer.consume(offset)
+    }
+
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::general_source_reload_test;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn test_framing() -> Result<(), std::io::Error> {
+        let server = TcpServer::bind("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        let send_handle = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            for chunk in [
+                &b"<13>one\r\n<13>tw"[..],
+                b"o\n11 <13>with\nnl",
+                b"8 <13>last\n<14>next\0",
+            ] {
+                stream.write_all(chunk).await.unwrap();
+                stream.flush().await.unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+        });
+        let mut source = server.accept().await?;
+        assert_eq!(source.peer().ip(), addr.ip());
+        let mut received = vec![];
+        loop {
+            let info = source.load(None).await.unwrap().unwrap();
+            if info.newly_loaded_bytes == 0 {
+                break;
+            }
+            received.extend_from_slice(source.current_slice());
+            source.consume(source.len());
+        }
+        send_handle.await.unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&received),
+            "<13>one\n<13>two\n11 <13>with\nnl<13>last\n<14>next\n"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_split_long_message() -> Result<(), std::io::Error> {
+        let server = TcpServer::bind("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        let send_handle = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            // neither terminated nor as long as announced, one byte too long
+            stream.write_all(b"999999999 <13>").await.unwrap();
+            stream
+                .write_all(&[b'x'; MAX_MESSAGE_SIZE - 13])
+                .await
+                .unwrap();
+        });
+        let mut source = server.accept().await?;
+        let mut received = vec![];
+        loop {
+            let info = source.load(None).await.unwrap().unwrap();
+            if info.newly_loaded_bytes == 0 {
+                break;
+            }
+            received.extend_from_slice(source.current_slice());
+            source.consume(source.len());
+        }
+        send_handle.await.unwrap();
+        assert!(received.starts_with(b"<13>xxx"));
+        assert_eq!(received.len(), MAX_MESSAGE_SIZE - 8);
+        assert!(source.pending.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_general_source_reload() {
+        let server = TcpServer::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            for msg in ["one\n", "two\n", "three\n"] {
+                stream.write_all(msg.as_bytes()).await.unwrap();
+                stream.flush().await.unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            }
+        });
+        let mut source = server.accept().await.unwrap();
+
+        general_source_reload_test(&mut source).await;
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{
+    factory::UnixSocketType, sde, ByteSource, Error as SourceError, ReloadInfo, SourceFilter,
+};
+use buf_redux::Buffer;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{UnixDatagram, UnixStream},
+};
+
+const MAX_READ_SIZE: usize = 65_507;
+
+enum Socket {
+    Stream(UnixStream),
+    Datagram {
+        socket: UnixDatagram,
+        /// Bound address of the latest sender; written data is sent to it
+        peer: Option<PathBuf>,
+    },
+}
+
+/// Source for a Unix domain socket. A stream socket is connected to the socket listening
+/// at the path; a datagram socket is bound to the path, which is removed again once the
+/// source is dropped.
+pub struct UnixSource {
+    buffer: Buffer,
+    socket: Socket,
+    path: PathBuf,
+    tmp_buffer: Vec<u8>,
+}
+
+impl UnixSource {
+    pub async fn new(path: &Path, socket_type: UnixSocketType) -> Result<Self, io::Error> {
+        let socket = match socket_type {
+            UnixSocketType::Stream => Socket::Stream(UnixStream::connect(path).await?),
+            UnixSocketType::Datagram => Socket::Datagram {
+                socket: UnixDatagram::bind(path)?,
+                peer: None,
+            },
+        };
+        Ok(Self {
+            buffer: Buffer::new(),
+            socket,
+            path: path.to_path_buf(),
+            tmp_buffer: vec![0u8; MAX_READ_SIZE],
+        })
+    }
+
+    /// Returns true if each load delivers one datagram
+    pub fn is_datagram(&self) -> bool {
+        matches!(self.socket, Socket::Datagram { .. })
+    }
+}
+
+impl Drop for UnixSource {
+    fn drop(&mut self) {
+        if let Socket::Datagram { .. } = self.socket {
+            if let Err(err) = std::fs::remove_file(&self.path) {
+                warn!(
+                    "Fail to remove socket {}: {err}",
+                    self.path.to_string_lossy()
+                );
+            }
+        }
+    }
+}
+
+impl ByteSource for UnixSource {
+    async fn load(
+        &mut self,
+        _filter: Option<&SourceFilter>,
+    ) -> Result<Option<ReloadInfo>, SourceError> {
+        // Both reads are cancel-safe; nothing is received if they are dropped
+        let len = match &mut self.socket {
+            Socket::Stream(stream) => stream
+                .read(&mut self.tmp_buffer)
+                .await
+                .map_err(|e| SourceError::Setup(format!("{e}")))?,
+            Socket::Datagram { socket, peer } => {
+                let (len, addr) = socket
+                    .recv_from(&mut self.tmp_buffer)
+                    .await
+                    .map_err(|e| SourceError::Setup(format!("{e}")))?;
+                if let Some(path) = addr.as_pathname() {
+                    *peer = Some(path.to_path_buf());
+                }
+                len
+            }
+        };
+        trace!("---> Received {} bytes", len);
+        if len > 0 {
+            self.buffer.copy_from_slice(&self.tmp_buffer[..len]);
+        }
+        let available_bytes = self.buffer.len();
+        Ok(Some(ReloadInfo::new(len, available_bytes, 0, None)))
+    }
+
+    fn current_slice(&self) -> &[u8] {
+        self.buffer.buf()
+    }
+
+    fn consume(&mut self, offset: usize) {
+        self.buffer.consume(offset)
+    }
+
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    async fn income(&mut self, request: sde::SdeRequest) -> Result<sde::SdeResponse, SourceError> {
+        let bytes = match request {
+            sde::SdeRequest::WriteText(ref str) => str.as_bytes(),
+            sde::SdeRequest::WriteBytes(ref bytes) => bytes,
+        };
+        match &mut self.socket {
+            Socket::Stream(stream) => {
+                stream.write_all(bytes).await.map_err(SourceError::Io)?;
+                Ok(sde::SdeResponse { bytes: bytes.len() })
+            }
+            Socket::Datagram { socket, peer } => {
+                let Some(peer) = peer.as_ref() else {
+                    return Err(SourceError::Setup(String::from(
+                        "No sender with a bound address to write to yet",
+                    )));
+                };
+                let bytes = socket.send_to(bytes, peer).await.map_err(SourceError::Io)?;
+                Ok(sde::SdeResponse { bytes })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::general_source_reload_test;
+    use tokio::net::UnixListener;
+
+    #[tokio::test]
+    async fn test_stream() -> Result<(), io::Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("daemon.sock");
+        let listener = UnixListener::bind(&path)?;
+        let serve_handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.write_all(b"one\ntwo\n").await.unwrap();
+            let mut request = [0u8; 6];
+            stream.read_exact(&mut request).await.unwrap();
+            request
+        });
+        let mut source = UnixSource::new(&path, UnixSocketType::Stream).await?;
+        assert!(!source.is_datagram());
+        let mut received = vec![];
+        loop {
+            let info = source.load(None).await.unwrap().unwrap();
+            if info.newly_loaded_bytes == 0 {
+                break;
+            }
+            received.extend_from_slice(source.current_slice());
+            source.consume(source.len());
+            if received.len() == 8 {
+                let response = source
+                    .income(sde::SdeRequest::WriteText(String::from("three\n")))
+                    .await
+                    .unwrap();
+                assert_eq!(response.bytes, 6);
+            }
+        }
+        assert_eq!(received, b"one\ntwo\n");
+        assert_eq!(&serve_handle.await.unwrap(), b"three\n");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_datagram() -> Result<(), io::Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("log.sock");
+        let mut source = UnixSource::new(&path, UnixSocketType::Datagram).await?;
+        assert!(source.is_datagram());
+        assert!(source
+            .income(sde::SdeRequest::WriteText(String::from("ping")))
+            .await
+            .is_err());
+
+        let sender = UnixDatagram::bind(dir.path().join("sender.sock"))?;
+        for msg in ["one", "two"] {
+            sender.send_to(msg.as_bytes(), &path).await?;
+            let info = source.load(None).await.unwrap().unwrap();
+            assert_eq!(info.newly_loaded_bytes, msg.len());
+            assert_eq!(source.current_slice(), msg.as_bytes());
+            source.consume(msg.len());
+        }
+        source
+            .income(sde::SdeRequest::WriteBytes(b"ping".to_vec()))
+            .await
+            .unwrap();
+        let mut response = [0u8; 4];
+        let len = sender.recv(&mut response).await?;
+        assert_eq!(&response[..len], b"ping");
+
+        drop(source);
+        assert!(!path.exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_general_source_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("daemon.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            for msg in ["one\n", "two\n", "three\n"] {
+                stream.write_all(msg.as_bytes()).await.unwrap();
+                stream.flush().await.unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            }
+        });
+        let mut source = UnixSource::new(&path, UnixSocketType::Stream)
+            .await
+            .unwrap();
+
+        general_source_reload_test(&mut source).await;
+    }
+}
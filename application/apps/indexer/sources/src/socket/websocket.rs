@@ -0,0 +1,225 @@
+use crate::{sde, ByteSource, Error as SourceError, ReloadInfo, SourceFilter};
+use buf_redux::Buffer;
+use futures::{
+    stream::{SplitSink, SplitStream, StreamExt},
+    SinkExt,
+};
+use std::collections::HashMap;
+use thiserror::Error;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{
+        self,
+        client::IntoClientRequest,
+        http::{HeaderName, HeaderValue},
+        Message,
+    },
+    MaybeTlsStream, WebSocketStream,
+};
+
+#[derive(Error, Debug)]
+pub enum WebSocketSourceError {
+    #[error("Invalid header {0}: {1}")]
+    Header(String, String),
+    #[error("WebSocket error: {0}")]
+    WebSocket(Box<tungstenite::Error>),
+}
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Source for the frames received from a WebSocket server. Text frames are delivered as
+/// lines, binary frames as they are.
+pub struct WebSocketSource {
+    write_stream: SplitSink<Socket, Message>,
+    read_stream: SplitStream<Socket>,
+    buffer: Buffer,
+}
+
+impl WebSocketSource {
+    pub async fn connect(
+        url: &str,
+        headers: &HashMap<String, String>,
+    ) -> Result<Self, WebSocketSourceError> {
+        let mut request = url
+            .into_client_request()
+            .map_err(|e| WebSocketSourceError::WebSocket(Box::new(e)))?;
+        for (name, value) in headers {
+            let header = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| WebSocketSourceError::Header(name.to_owned(), e.to_string()))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| WebSocketSourceError::Header(name.to_owned(), e.to_string()))?;
+            request.headers_mut().insert(header, value);
+        }
+        let (socket, _) = connect_async(request)
+            .await
+            .map_err(|e| WebSocketSourceError::WebSocket(Box::new(e)))?;
+        let (write_stream, read_stream) = socket.split();
+        Ok(Self {
+            write_stream,
+            read_stream,
+            buffer: Buffer::new(),
+        })
+    }
+}
+
+impl ByteSource for WebSocketSource {
+    async fn load(
+        &mut self,
+        _filter: Option<&SourceFilter>,
+    ) -> Result<Option<ReloadInfo>, SourceError> {
+        // Implementation is cancel-safe here because there is one await call on a stream only.
+        let len = loop {
+            match self.read_stream.next().await {
+                Some(Ok(Message::Text(text))) => {
+                    self.buffer.reserve(text.len() + 1);
+                    let mut len = self.buffer.copy_from_slice(text.as_bytes());
+                    if !text.ends_with('\n') {
+                        len += self.buffer.copy_from_slice(b"\n");
+                    }
+                    break len;
+                }
+                Some(Ok(Message::Binary(bytes))) => {
+                    self.buffer.reserve(bytes.len());
+                    break self.buffer.copy_from_slice(&bytes);
+                }
+                Some(Ok(Message::Close(frame))) => {
+                    debug!("WebSocket was closed: {frame:?}");
+                    break 0;
+                }
+                // pings are answered by the socket itself
+                Some(Ok(_)) => continue,
+                Some(Err(err)) => {
+                    return Err(SourceError::Setup(format!("Failed to read frame: {err}")));
+                }
+                None => break 0,
+            }
+        };
+        trace!("---> Received {} bytes", len);
+        let available_bytes = self.buffer.len();
+        Ok(Some(ReloadInfo::new(len, available_bytes, 0, None)))
+    }
+
+    fn current_slice(&self) -> &[u8] {
+        self.buffer.buf()
+    }
+
+    fn consume(&mut self, offset: usize) {
+        self.buffer.consume(offset)
+    }
+
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    async fn income(&mut self, request: sde::SdeRequest) -> Result<sde::SdeResponse, SourceError> {
+        let (message, len) = match request {
+            sde::SdeRequest::WriteText(str) => {
+                let len = str.len();
+                (Message::Text(str), len)
+            }
+            sde::SdeRequest::WriteBytes(bytes) => {
+                let len = bytes.len();
+                (Message::Binary(bytes), len)
+            }
+        };
+        self.write_stream
+            .send(message)
+            .await
+            .map_err(|e| SourceError::Setup(format!("Failed to send frame: {e}")))?;
+        Ok(sde::SdeResponse { bytes: len })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::general_source_reload_test;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::{
+        accept_hdr_async,
+        tungstenite::handshake::server::{Request, Response},
+    };
+
+    async fn serve(listener: TcpListener) -> Socket {
+        let (stream, _) = listener.accept().await.unwrap();
+        let callback = |request: &Request, response: Response| {
+            assert_eq!(request.headers().get("x-relay-token").unwrap(), "secret");
+            Ok(response)
+        };
+        accept_hdr_async(MaybeTlsStream::Plain(stream), callback)
+            .await
+            .unwrap()
+    }
+
+    fn headers() -> HashMap<String, String> {
+        HashMap::from([(String::from("X-Relay-Token"), String::from("secret"))])
+    }
+
+    #[tokio::test]
+    async fn test_frames() -> Result<(), WebSocketSourceError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let serve_handle = tokio::spawn(async move {
+            let mut socket = serve(listener).await;
+            for msg in [
+                Message::Text(String::from("one")),
+                Message::Text(String::from("two\n")),
+                Message::Binary(vec![0x3d, 0x00, 0x01]),
+            ] {
+                socket.send(msg).await.unwrap();
+            }
+            let request = socket.next().await.unwrap().unwrap();
+            socket.close(None).await.unwrap();
+            request
+        });
+        let mut source = WebSocketSource::connect(&url, &headers()).await?;
+        let mut received = vec![];
+        loop {
+            let info = source.load(None).await.unwrap().unwrap();
+            if info.newly_loaded_bytes == 0 {
+                break;
+            }
+            received.extend_from_slice(source.current_slice());
+            source.consume(source.len());
+            if received.ends_with(&[0x00, 0x01]) {
+                let response = source
+                    .income(sde::SdeRequest::WriteText(String::from("ping")))
+                    .await
+                    .unwrap();
+                assert_eq!(response.bytes, 4);
+            }
+        }
+        assert_eq!(received, b"one\ntwo\n\x3d\x00\x01");
+        assert_eq!(
+            serve_handle.await.unwrap(),
+            Message::Text(String::from("ping"))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_invalid_header() {
+        let headers = HashMap::from([(String::from("in valid"), String::from("value"))]);
+        assert!(matches!(
+            WebSocketSource::connect("ws://127.0.0.1:1", &headers).await,
+            Err(WebSocketSourceError::Header(..))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_general_source_reload() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut socket = serve(listener).await;
+            for msg in ["one", "two", "three"] {
+                socket.send(Message::Text(msg.to_owned())).await.unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            }
+        });
+        let mut source = WebSocketSource::connect(&url, &headers()).await.unwrap();
+
+        general_source_reload_test(&mut source).await;
+    }
+}
@@ -150,6 +150,37 @@ impl UnboundJobs {
             .map(CommandOutcomeWrapper)
     }
 
+    #[node_bindgen]
+    async fn detect_timestamp_format(
+        &self,
+        id: i64,
+        file_path: String,
+        max_lines: i64,
+    ) -> Result<CommandOutcomeWrapper<String>, ComputationErrorWrapper> {
+        self.api
+            .as_ref()
+            .ok_or(ComputationError::SessionUnavailable)?
+            .detect_timestamp_format(u64_from_i64(id)?, file_path, usize_from_i64(max_lines)?)
+            .await
+            .map_err(ComputationErrorWrapper)
+            .map(CommandOutcomeWrapper)
+    }
+
+    #[node_bindgen]
+    async fn list_archive_entries(
+        &self,
+        id: i64,
+        file_path: String,
+    ) -> Result<CommandOutcomeWrapper<String>, ComputationErrorWrapper> {
+        self.api
+            .as_ref()
+            .ok_or(ComputationError::SessionUnavailable)?
+            .list_archive_entries(u64_from_i64(id)?, file_path)
+            .await
+            .map_err(ComputationErrorWrapper)
+            .map(CommandOutcomeWrapper)
+    }
+
     #[node_bindgen]
     async fn spawn_process(
         &self,
@@ -3,7 +3,10 @@ pub mod progress_tracker;
 
 use crate::{
     js::{
-        converting::{filter::WrappedSearchFilter, source::WrappedSourceDefinition},
+        converting::{
+            filter::WrappedSearchFilter, merge::WrappedFileMergeOptions,
+            source::WrappedSourceDefinition,
+        },
         session::events::ComputationErrorWrapper,
     },
     logging::targets,
@@ -534,6 +537,26 @@ impl RustSession {
         }
     }
 
+    #[node_bindgen]
+    async fn merge(
+        &self,
+        files: Vec<WrappedFileMergeOptions>,
+        operation_id: String,
+    ) -> Result<(), ComputationErrorWrapper> {
+        if let Some(ref session) = self.session {
+            session
+                .merge(
+                    operations::uuid_from_str(&operation_id)?,
+                    files.iter().map(|f| f.as_file_merge_options()).collect(),
+                )
+                .map_err(ComputationErrorWrapper)
+        } else {
+            Err(ComputationErrorWrapper(
+                ComputationError::SessionUnavailable,
+            ))
+        }
+    }
+
     #[node_bindgen]
     async fn apply_search_filters(
         &self,
@@ -3,6 +3,7 @@ import { Base } from '../native/native.jobs';
 import { error } from 'platform/log/utils';
 import { IFilter } from 'platform/types/filter';
 import { ShellProfile } from 'platform/types/shells';
+import { ArchiveEntry, TimestampFormatCandidate } from 'platform/types/files';
 import { SomeipStatistic } from 'platform/types/observe/parser/someip';
 import { StatisticInfo } from 'platform/types/observe/parser/dlt';
 
@@ -146,6 +147,44 @@ export class Jobs extends Base {
         return job;
     }
 
+    // Candidates are sorted by confidence, the most likely format first
+    public detectTimestampFormat(
+        path: string,
+        maxLines: number,
+    ): CancelablePromise<TimestampFormatCandidate[]> {
+        const sequence = this.sequence();
+        const job: CancelablePromise<TimestampFormatCandidate[]> = this.execute(
+            (res: string): TimestampFormatCandidate[] | Error => {
+                try {
+                    return JSON.parse(res) as TimestampFormatCandidate[];
+                } catch (e) {
+                    return new Error(error(e));
+                }
+            },
+            this.native.detectTimestampFormat(sequence, path, maxLines),
+            sequence,
+            'detectTimestampFormat',
+        );
+        return job;
+    }
+
+    public listArchiveEntries(path: string): CancelablePromise<ArchiveEntry[]> {
+        const sequence = this.sequence();
+        const job: CancelablePromise<ArchiveEntry[]> = this.execute(
+            (res: string): ArchiveEntry[] | Error => {
+                try {
+                    return JSON.parse(res) as ArchiveEntry[];
+                } catch (e) {
+                    return new Error(error(e));
+                }
+            },
+            this.native.listArchiveEntries(sequence, path),
+            sequence,
+            'listArchiveEntries',
+        );
+        return job;
+    }
+
     public getShellProfiles(): CancelablePromise<ShellProfile[]> {
         const sequence = this.sequence();
         const job: CancelablePromise<ShellProfile[]> = this.execute(
@@ -48,6 +48,11 @@ export interface IAttachmentsUpdatedUpdated {
     attachment: IAttachment;
 }
 
+export interface IEventColumnsUpdated {
+    source_id: number;
+    columns: string[];
+}
+
 export interface ISessionEvents {
     StreamUpdated: Subject<number>;
     FileRead: Subject<void>;
@@ -59,6 +64,7 @@ export interface ISessionEvents {
     MatchesUpdated: Subject<IEventMatchesUpdated>;
     Progress: Subject<IProgressEvent>;
     AttachmentsUpdated: Subject<IAttachmentsUpdatedUpdated>;
+    ColumnsUpdated: Subject<IEventColumnsUpdated>;
     SessionError: Subject<IError>;
     OperationError: Subject<IErrorEvent>;
     SessionDestroyed: Subject<void>;
@@ -80,6 +86,7 @@ interface ISessionEventsSignatures {
     MatchesUpdated: 'MatchesUpdated';
     Progress: 'Progress';
     AttachmentsUpdated: 'AttachmentsUpdated';
+    ColumnsUpdated: 'ColumnsUpdated';
     SessionError: 'SessionError';
     OperationError: 'OperationError';
     SessionDestroyed: 'SessionDestroyed';
@@ -98,6 +105,7 @@ const SessionEventsSignatures: ISessionEventsSignatures = {
     IndexedMapUpdated: 'IndexedMapUpdated',
     MatchesUpdated: 'MatchesUpdated',
     AttachmentsUpdated: 'AttachmentsUpdated',
+    ColumnsUpdated: 'ColumnsUpdated',
     Progress: 'Progress',
     SessionError: 'SessionError',
     OperationError: 'OperationError',
@@ -125,6 +133,7 @@ interface ISessionEventsInterfaces {
         ];
     };
     AttachmentsUpdated: { self: 'object'; len: 'number'; attachment: typeof Object };
+    ColumnsUpdated: { self: 'object'; source_id: 'number'; columns: typeof Array };
     SessionError: { self: 'object'; severity: 'string'; message: 'string'; kind: 'string' };
     OperationError: {
         self: 'object';
@@ -155,6 +164,7 @@ const SessionEventsInterfaces: ISessionEventsInterfaces = {
         ],
     },
     AttachmentsUpdated: { self: 'object', len: 'number', attachment: Object },
+    ColumnsUpdated: { self: 'object', source_id: 'number', columns: Array },
     SessionError: { self: 'object', severity: 'string', message: 'string', kind: 'string' },
     OperationError: {
         self: 'object',
@@ -183,6 +193,7 @@ export class EventProvider extends Computation<
         MatchesUpdated: new Subject<IEventMatchesUpdated>(), // dummy
         Progress: new Subject<IProgressEvent>(),
         AttachmentsUpdated: new Subject<IAttachmentsUpdatedUpdated>(),
+        ColumnsUpdated: new Subject<IEventColumnsUpdated>(),
         SessionError: new Subject<IError>(),
         OperationError: new Subject<IErrorEvent>(),
         SessionDestroyed: new Subject<void>(),
@@ -28,6 +28,12 @@ export abstract class JobsNative {
     public abstract getFileChecksum(sequence: number, path: string): Promise<string>;
     public abstract getDltStats(sequence: number, files: string[]): Promise<string>;
     public abstract getSomeipStatistic(sequence: number, files: string[]): Promise<string>;
+    public abstract detectTimestampFormat(
+        sequence: number,
+        filePath: string,
+        maxLines: number,
+    ): Promise<string>;
+    public abstract listArchiveEntries(sequence: number, filePath: string): Promise<string>;
     public abstract getShellProfiles(sequence: number): Promise<string>;
     public abstract getContextEnvvars(sequence: number): Promise<string>;
     public abstract getSerialPortsList(sequence: number): Promise<string[]>;
@@ -119,3 +119,23 @@ export function getFileExtention(filename: string): string {
 export function appendFileExtention(filename: string, ext: string) {
     return `${filename}${ext.startsWith('.') || filename.endsWith('.') ? '' : '.'}${ext}`;
 }
+
+export interface ArchiveEntry {
+    // Path of the file within the archive
+    path: string;
+    // Path to observe the file with, e.g. "bundle.zip/logs/app.dlt"
+    filename: string;
+    size: number;
+    // 'Text' | 'Dlt' | 'PcapLegacy' | 'PcapNG' | 'Binary'
+    format: string;
+    compressed: boolean;
+}
+
+export interface TimestampFormatCandidate {
+    // Format string as used in the merge options, e.g. "YYYY-MM-DD hh:mm:ss.s"
+    format: string;
+    // Regex matching a timestamp of this format
+    regex: string;
+    // Share of the sampled lines containing a timestamp of this format (0.0 - 1.0)
+    confidence: number;
+}
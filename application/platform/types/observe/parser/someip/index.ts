@@ -13,6 +13,8 @@ import * as str from '../../../../env/str';
 export interface SomeipStatistic {
     /** Statistic on service-ids and related method-ids */
     services: SomeipStatisticItem[];
+    /** Statistic on service-ids and related instance-ids (from service discovery) */
+    instances: SomeipStatisticItem[];
     /** Statistic on message-types and related return-codes */
     messages: SomeipStatisticItem[];
 }
@@ -5,11 +5,29 @@ import { Statics } from '../../../../env/decorators';
 import { List, IList } from '../../description';
 
 import * as str from '../../../../env/str';
+import * as obj from '../../../../env/obj';
 import * as Origin from '../../origin/index';
 import * as Stream from '../../origin/stream/index';
 import * as Files from '../../types/file';
 
-export type IConfiguration = null;
+export enum Encoding {
+    // Detected by the byte order mark; UTF-8 is used if there is none
+    Auto = 'Auto',
+    Utf8 = 'Utf8',
+    Utf16Le = 'Utf16Le',
+    Utf16Be = 'Utf16Be',
+    ShiftJis = 'ShiftJis',
+    Latin1 = 'Latin1',
+}
+
+export interface ISettings {
+    encoding?: Encoding;
+    // Regex matching the first line of a record; following lines are joined to it
+    record_start?: string | null;
+}
+
+// null is the same as the default settings (auto detected encoding, no records)
+export type IConfiguration = ISettings | null;
 
 @Statics<ConfigurationStaticDesc<IConfiguration, Protocol>>()
 export class Configuration
@@ -30,8 +48,17 @@ export class Configuration
 
     static validate(configuration: IConfiguration): Error | IConfiguration {
         try {
-            if (configuration !== null) {
-                throw new Error(`Text parser doesn't have any configuration; it should null`);
+            if (configuration === null) {
+                return configuration;
+            }
+            if (
+                configuration.encoding !== undefined &&
+                !Object.values(Encoding).includes(configuration.encoding)
+            ) {
+                throw new Error(`Unknown encoding: ${configuration.encoding}`);
+            }
+            if (configuration.record_start !== undefined && configuration.record_start !== null) {
+                obj.getAsNotEmptyString(configuration, 'record_start');
             }
             return configuration;
         } catch (e) {
@@ -53,6 +80,13 @@ export class Configuration
     }
 
     public override hash(): number {
-        return str.hash(`text`);
+        if (this.configuration === null) {
+            return str.hash(`text`);
+        }
+        return str.hash(
+            `text:${this.configuration.encoding ?? Encoding.Auto}:${
+                this.configuration.record_start ?? ''
+            }`,
+        );
     }
 }